@@ -0,0 +1,37 @@
+//! 大人数の名簿でチーム作成にかかる時間と、名前の共有を確かめる
+//! `cargo run --release --example large_roster -- 50000`のように人数を指定して実行する(省略時は50000人)
+
+use std::collections::HashSet;
+use std::env;
+use std::sync::Arc;
+use std::time::Instant;
+use anyhow::Result;
+use guccicci::domain::{Attendee, Person, TeamsCreationSetting};
+use guccicci::run_with_seed;
+
+/// 作成するチーム数
+const NUM_OF_TEAMS: u8 = 200;
+
+fn main() -> Result<()> {
+    let num_of_people: usize = env::args().nth(1).map(|n| n.parse()).transpose()?.unwrap_or(50_000);
+
+    let mut setting: TeamsCreationSetting = toml::from_str(&format!("num_of_teams = {}", NUM_OF_TEAMS))?;
+    let names: Vec<Arc<str>> = (0..num_of_people).map(|i| Arc::from(format!("Person {:06}", i))).collect();
+    setting.add_attendees(names.iter().enumerate().map(|(i, name)| Attendee::new(Person::new(name.clone()), i % 100 == 0)).collect());
+
+    let started = Instant::now();
+    let teams = run_with_seed(setting, 42)?;
+    let elapsed = started.elapsed();
+
+    // 名簿の名前と同じ領域を指している名前の数。全員分であれば名前を複製していない
+    let roster: HashSet<*const u8> = names.iter().map(|n| n.as_ptr()).collect();
+    let people: Vec<&Person> = teams.borrow_vec().iter().flat_map(|t| std::iter::once(&t.leader).chain(t.member.iter())).collect();
+    let shared = people.iter().filter(|p| roster.contains(&p.name.as_ptr())).count();
+    let name_bytes: usize = names.iter().map(|n| n.len()).sum();
+
+    println!("people: {}, teams: {}", people.len(), teams.borrow_vec().len());
+    println!("create: {:?}", elapsed);
+    println!("names shared with the roster: {} of {} ({} bytes of names allocated once)", shared, people.len(), name_bytes);
+
+    Ok(())
+}
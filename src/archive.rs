@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use crate::history::Retention;
+
+/// 結果のアーカイブに関するエラー
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    /// アーカイブ先がgitリポジトリではない
+    #[error("archive directory {0} is not a git repository")]
+    NotRepository(String),
+    /// 結果ファイルを書き込めない
+    #[error("failed to write result file {0}: {1}")]
+    Write(String, std::io::Error),
+    /// gitコマンドが失敗した
+    #[error("git {0} failed: {1}")]
+    Git(String, String)
+}
+
+/// 最後に作成した実行IDの実行時刻(UNIXエポックからのナノ秒)
+static LAST_RUN_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// 実行IDを作成する
+/// 実行時刻(UNIX秒と1秒未満のナノ秒)とシード値からなり、結果ファイル名やコミットメッセージに使う
+/// 同じシード値で続けて実行しても重ならないよう、同じプロセスでは前回より後の時刻を使う
+/// # Attributes
+/// * `seed` - 実行に使ったシード値
+pub fn run_id(seed: u64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    let last = LAST_RUN_NANOS.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(now.max(last + 1))).unwrap_or(now);
+    let nanos = now.max(last + 1);
+
+    format!("{}-{:09}-{:016x}", nanos / 1_000_000_000, nanos % 1_000_000_000, seed)
+}
+
+/// 結果をgitリポジトリに保存してコミットする
+/// # Attributes
+/// * `repo` - アーカイブ先のgitリポジトリ
+/// * `run_id` - 実行ID。`<run_id>.toml`というファイル名で保存する
+/// * `seed` - 実行に使ったシード値。コミットメッセージに含める
+/// * `content` - 保存する結果
+///
+/// # Returns
+/// Ok(保存したファイルのパス)
+pub fn archive_result(repo: &Path, run_id: &str, seed: u64, content: &str) -> Result<PathBuf, ArchiveError> {
+    if !repo.join(".git").exists() {
+        Err(ArchiveError::NotRepository(repo.display().to_string()))?
+    }
+
+    let file_name = format!("{}.toml", run_id);
+    let path = repo.join(&file_name);
+    fs::write(&path, content).map_err(|e| ArchiveError::Write(path.display().to_string(), e))?;
+
+    git(repo, &["add", "--", &file_name])?;
+    git(repo, &["commit", "-q", "-m", &format!("guccicci run {} (seed {})", run_id, seed), "--", &file_name])?;
+
+    Ok(path)
+}
+
+/// アーカイブした結果のうち、残す範囲から外れたものを削除してコミットする
+/// 実行ID(`<実行時刻>-<ナノ秒>-<シード値>`)の名前のファイルだけを対象にし、実行時刻はファイル名から読む
+/// # Attributes
+/// * `repo` - アーカイブ先のgitリポジトリ
+/// * `retention` - 残す範囲
+/// * `now` - 現在時刻(UNIX秒)
+///
+/// # Returns
+/// Ok(削除したファイルのパス(古い順))。削除するものがなければコミットしない
+pub fn prune_archive(repo: &Path, retention: &Retention, now: u64) -> Result<Vec<PathBuf>, ArchiveError> {
+    if !repo.join(".git").exists() {
+        Err(ArchiveError::NotRepository(repo.display().to_string()))?
+    }
+
+    let entries = fs::read_dir(repo).map_err(|e| ArchiveError::Write(repo.display().to_string(), e))?;
+    let mut archived: Vec<(u64, String)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str().map(|n| n.to_string()))
+        .filter_map(|name| archived_timestamp(&name).map(|t| (t, name)))
+        .collect();
+    archived.sort();
+
+    let timestamps: Vec<u64> = archived.iter().map(|(t, _)| *t).collect();
+    let expired: Vec<&str> = retention.expired(&timestamps, now).into_iter().map(|i| archived[i].1.as_str()).collect();
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    git(repo, &[&["rm", "-q", "--"], expired.as_slice()].concat())?;
+    let message = format!("guccicci prune {} runs", expired.len());
+    git(repo, &[&["commit", "-q", "-m", &message, "--"], expired.as_slice()].concat())?;
+
+    Ok(expired.iter().map(|name| repo.join(name)).collect())
+}
+
+/// アーカイブした結果のファイル名から実行時刻を読む
+/// # Returns
+/// `<実行時刻>-<ナノ秒9桁>-<シード値16桁>.toml`(以前の`<実行時刻>-<シード値16桁>.toml`も含む)の形でなければNone
+fn archived_timestamp(file_name: &str) -> Option<u64> {
+    let (secs, rest) = file_name.strip_suffix(".toml")?.split_once('-')?;
+    let seed = match rest.split_once('-') {
+        Some((nanos, seed)) if nanos.len() == 9 && nanos.chars().all(|c| c.is_ascii_digit()) => seed,
+        Some(_) => return None,
+        None => rest
+    };
+    if seed.len() != 16 || !seed.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    secs.parse().ok()
+}
+
+/// アーカイブ先のリポジトリでgitコマンドを実行する
+fn git(repo: &Path, args: &[&str]) -> Result<(), ArchiveError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| ArchiveError::Git(args[0].to_string(), e.to_string()))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(ArchiveError::Git(args[0].to_string(), String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// archive_resultのテスト
+    /// 結果ファイルを書き込み、実行IDとシード値を含むメッセージでコミットする
+    #[test]
+    fn archive_result_commits_file() {
+        let repo = std::env::temp_dir().join(format!("guccicci-archive-{}", std::process::id()));
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q"]).unwrap();
+        git(&repo, &["config", "user.name", "test"]).unwrap();
+        git(&repo, &["config", "user.email", "test@example.com"]).unwrap();
+
+        let path = archive_result(&repo, "run-1", 42, "[[team]]\n").unwrap();
+
+        let log = Command::new("git").arg("-C").arg(&repo).args(["log", "--format=%s"]).output().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "[[team]]\n");
+        assert_eq!(String::from_utf8_lossy(&log.stdout).trim(), "guccicci run run-1 (seed 42)");
+
+        fs::remove_dir_all(&repo).unwrap();
+    }
+
+    /// prune_archiveのテスト
+    /// 残す範囲から外れた結果ファイルだけを削除してコミットし、実行IDでないファイルには触れない
+    #[test]
+    fn prune_archive_removes_old_runs() {
+        let repo = std::env::temp_dir().join(format!("guccicci-archive-prune-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q"]).unwrap();
+        git(&repo, &["config", "user.name", "test"]).unwrap();
+        git(&repo, &["config", "user.email", "test@example.com"]).unwrap();
+        fs::write(repo.join("notes.toml"), "").unwrap();
+        git(&repo, &["add", "notes.toml"]).unwrap();
+        for id in ["300-000000000-000000000000002a", "100-000000000000002a", "200-000000000-000000000000002a"] {
+            archive_result(&repo, id, 42, "").unwrap();
+        }
+
+        let removed = prune_archive(&repo, &Retention { keep_runs: std::num::NonZeroUsize::new(1), keep_months: None }, 300).unwrap();
+
+        assert_eq!(removed, vec![repo.join("100-000000000000002a.toml"), repo.join("200-000000000-000000000000002a.toml")]);
+        assert!(!removed[0].exists());
+        assert!(repo.join("300-000000000-000000000000002a.toml").exists());
+        assert!(repo.join("notes.toml").exists());
+        let log = Command::new("git").arg("-C").arg(&repo).args(["log", "-1", "--format=%s"]).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&log.stdout).trim(), "guccicci prune 2 runs");
+        assert!(prune_archive(&repo, &Retention { keep_runs: std::num::NonZeroUsize::new(1), keep_months: None }, 300).unwrap().is_empty());
+
+        fs::remove_dir_all(&repo).unwrap();
+    }
+
+    /// run_idのテスト
+    /// 同じシード値で続けて作成しても実行IDが重ならず、同じ結果ファイルを上書きしない
+    #[test]
+    fn run_id_unique_for_same_seed() {
+        let repo = std::env::temp_dir().join(format!("guccicci-archive-same-seed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q"]).unwrap();
+        git(&repo, &["config", "user.name", "test"]).unwrap();
+        git(&repo, &["config", "user.email", "test@example.com"]).unwrap();
+
+        let first = run_id(42);
+        let second = run_id(42);
+        let first_path = archive_result(&repo, &first, 42, "first").unwrap();
+        let second_path = archive_result(&repo, &second, 42, "second").unwrap();
+
+        assert_ne!(first, second);
+        assert!(first.ends_with("-000000000000002a") && second.ends_with("-000000000000002a"));
+        assert_eq!(fs::read_to_string(first_path).unwrap(), "first");
+        assert_eq!(fs::read_to_string(second_path).unwrap(), "second");
+        assert!(archived_timestamp(&format!("{}.toml", first)).is_some());
+
+        fs::remove_dir_all(&repo).unwrap();
+    }
+
+    /// archive_resultのテスト
+    /// gitリポジトリでなければエラー
+    #[test]
+    fn archive_result_requires_repository() {
+        let dir = std::env::temp_dir().join(format!("guccicci-not-repo-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert!(matches!(archive_result(&dir, "run-1", 42, ""), Err(ArchiveError::NotRepository(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use serde::Deserialize;
+use crate::domain::{Teams, TeamsCreationSetting};
+use crate::formatter::{FormatOptions, OutputFormat};
+use crate::{merge_settings, run_with_constraints};
+
+/// 複数の会をまとめて作成するバッチファイル
+#[derive(Debug, Deserialize)]
+pub struct BatchFile {
+    /// 作成する会のリスト
+    events: Vec<BatchEvent>
+}
+
+/// バッチファイルの1つの会
+#[derive(Debug, Deserialize)]
+pub struct BatchEvent {
+    /// 会の名前(まとめの表示に使う)
+    name: String,
+    /// 前から順にまとめる設定ファイルのパス(バッチファイルからの相対パス)
+    settings: Vec<String>,
+    /// 指定すれば設定ファイルのチーム数を上書きする
+    num_of_teams: Option<u8>,
+    /// 指定すれば設定ファイルの出席者のうちこの名前の人だけを参加させる
+    attendees: Option<Vec<String>>,
+    /// 結果を書き込むファイルのパス(バッチファイルからの相対パス)
+    output: String,
+    /// 出力形式。指定しなければ`--format`の形式
+    format: Option<String>
+}
+
+/// 1つの会を作成した結果のまとめ
+#[derive(Debug, PartialEq)]
+pub struct EventSummary {
+    /// チーム数
+    pub num_of_teams: usize,
+    /// 参加者数
+    pub num_of_people: usize,
+    /// 結果を書き込んだファイルのパス
+    pub output: PathBuf
+}
+
+impl BatchFile {
+    /// バッチファイルを読み込む
+    /// # Attributes
+    /// * `path` - バッチファイルのパス
+    pub fn load(path: &Path) -> Result<BatchFile> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// 全ての会を作成して結果を書き込む
+    /// 失敗した会があっても残りの会は作成する
+    /// # Attributes
+    /// * `base_dir` - 相対パスの基準にするディレクトリ(バッチファイルのあるディレクトリ)
+    /// * `seed` - シード値。Noneであれば毎回ランダム
+    /// * `format` - 会に出力形式の指定がない場合の出力形式
+    /// * `format_options` - 出力オプション
+    ///
+    /// # Returns
+    /// (会の名前, 作成した結果のまとめ)のリスト
+    pub fn run(&self, base_dir: &Path, seed: Option<u64>, format: OutputFormat, format_options: &FormatOptions) -> Vec<(String, Result<EventSummary>)> {
+        self.events.iter()
+            .map(|event| (event.name.clone(), event.run(base_dir, seed, format, format_options)))
+            .collect()
+    }
+}
+
+impl BatchEvent {
+    /// 会のチーム作成設定を作る
+    /// # Attributes
+    /// * `base_dir` - 相対パスの基準にするディレクトリ
+    pub fn setting(&self, base_dir: &Path) -> Result<TeamsCreationSetting> {
+        let mut contents = self.settings.iter()
+            .map(|path| fs::read_to_string(base_dir.join(path)))
+            .collect::<Result<Vec<String>, _>>()?;
+        if let Some(num_of_teams) = self.num_of_teams {
+            contents.push(format!("num_of_teams = {}\n", num_of_teams));
+        }
+
+        let path = self.settings.last().map(PathBuf::from).unwrap_or_default();
+        let mut setting: TeamsCreationSetting = toml::from_str(&merge_settings(&contents)?)?;
+        setting.inline_attendees_file(&base_dir.join(path.parent().unwrap_or(Path::new(""))))?;
+        if let Some(names) = &self.attendees {
+            setting.retain_attendees(names)?;
+        }
+        setting.resolve_target_size()?;
+
+        Ok(setting)
+    }
+
+    fn run(&self, base_dir: &Path, seed: Option<u64>, format: OutputFormat, format_options: &FormatOptions) -> Result<EventSummary> {
+        let setting = self.setting(base_dir)?;
+        let format = match &self.format {
+            Some(name) => name.parse()?,
+            None => format
+        };
+        let format_options = FormatOptions { tables: setting.tables().to_vec(), output: setting.output().clone(), ..format_options.clone() };
+        let num_of_people = setting.all_people().len();
+
+        let teams: Teams = run_with_constraints(setting, seed, &[])?;
+        let output = base_dir.join(&self.output);
+        if let Some(dir) = output.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&output, format.render(&teams, &format_options)?)?;
+
+        Ok(EventSummary { num_of_teams: teams.borrow_vec().len(), num_of_people, output })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// BatchFile#runのテスト
+    /// 会ごとに設定をまとめ、出席者を絞ってチーム数を上書きし、結果を書き込む
+    #[test]
+    fn run_batch() {
+        let dir = std::env::temp_dir().join(format!("guccicci-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("attendees.toml"), r#"
+num_of_teams = 1
+flat = true
+
+[[attendees]]
+person = { name = "A" }
+
+[[attendees]]
+person = { name = "B" }
+
+[[attendees]]
+person = { name = "C" }
+
+[[attendees]]
+person = { name = "D" }
+"#).unwrap();
+        let batch: BatchFile = toml::from_str(r#"
+[[events]]
+name = "Lunch"
+settings = ["attendees.toml"]
+num_of_teams = 2
+output = "out/lunch.org"
+format = "org"
+
+[[events]]
+name = "Retro"
+settings = ["attendees.toml"]
+attendees = ["A", "Z"]
+output = "out/retro.toml"
+"#).unwrap();
+
+        let results = batch.run(&dir, Some(1), OutputFormat::Toml, &FormatOptions::default());
+
+        assert_eq!(results[0].0, "Lunch");
+        assert_eq!(results[0].1.as_ref().unwrap(), &EventSummary { num_of_teams: 2, num_of_people: 4, output: dir.join("out/lunch.org") });
+        assert!(fs::read_to_string(dir.join("out/lunch.org")).unwrap().starts_with("* Team 1\n"));
+        assert_eq!(results[1].1.as_ref().unwrap_err().to_string(), "Z is not in attendees");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::domain::Teams;
+use crate::hash::sha256_hex;
+
+/// シャッフルの方式の版。シード値から結果を作る方式を変えたら上げ、古い方式の結果を使わないようにする
+const SHUFFLE_VERSION: &str = "chacha8-v1";
+
+/// 設定の内容とシード値をキーにした、作成済みの結果のキャッシュ
+/// 同じ設定・同じシード値であれば作成し直さずに保存した結果を返す
+pub struct ResultCache {
+    /// 結果を保存するディレクトリ
+    dir: PathBuf
+}
+
+impl ResultCache {
+    /// 保存先のディレクトリを指定して作成する
+    /// ディレクトリがなければ最初の`put`で作成する
+    /// # Attributes
+    /// * `dir` - 保存先のディレクトリ
+    pub fn new(dir: impl Into<PathBuf>) -> ResultCache {
+        ResultCache { dir: dir.into() }
+    }
+
+    /// キャッシュのキーを作る
+    /// # Attributes
+    /// * `setting` - 結果に影響する設定の内容
+    /// * `seed` - シード値
+    pub fn key(setting: &str, seed: u64) -> String {
+        format!("{}-{:016x}", sha256_hex(format!("{}\n{}", SHUFFLE_VERSION, setting).as_bytes()), seed)
+    }
+
+    /// 保存した結果を返す
+    /// # Attributes
+    /// * `key` - キャッシュのキー
+    ///
+    /// # Returns
+    /// 保存した結果がなければOk(None)
+    pub fn get(&self, key: &str) -> Result<Option<Teams>> {
+        let path = self.path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    /// 結果を保存する
+    /// # Attributes
+    /// * `key` - キャッシュのキー
+    /// * `teams` - 保存する結果
+    pub fn put(&self, key: &str, teams: &Teams) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(key), toml::to_string_pretty(teams)?)?;
+
+        Ok(())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.toml", key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+
+    /// ResultCacheのテスト
+    /// 保存した結果を同じキーで取り出せ、設定かシード値が違えば別のキーになる
+    #[test]
+    fn result_cache() {
+        let dir = std::env::temp_dir().join(format!("guccicci-cache-{}", std::process::id()));
+        let cache = ResultCache::new(&dir);
+        let key = ResultCache::key("num_of_teams = 1", 42);
+        let mut team = Team::new(Person::new("A"));
+        team.assign(Person::new("B"));
+
+        assert!(cache.get(&key).unwrap().is_none());
+        cache.put(&key, &Teams::from_vec(vec![team])).unwrap();
+
+        let cached = cache.get(&key).unwrap().unwrap();
+        assert_eq!(&*cached.borrow_vec()[0].member[0].name, "B");
+        assert_ne!(key, ResultCache::key("num_of_teams = 1", 43));
+        assert_ne!(key, ResultCache::key("num_of_teams = 2", 42));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
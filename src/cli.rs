@@ -0,0 +1,988 @@
+use std::fmt;
+use thiserror::Error;
+use toml::Value;
+use crate::collation::Collation;
+use crate::formatter::{FormatOptions, OutputFormat};
+use crate::github::GithubTarget;
+use crate::history::Retention;
+use crate::locale::Locale;
+use crate::log::LogFormat;
+use crate::reroll::TeamSelector;
+use crate::template::TemplatePreset;
+
+/// コマンドライン引数の解析に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum CliError {
+    /// オプションに値が指定されていない
+    #[error("option {0} requires a value")]
+    ValueMissing(String),
+    /// オプションの値が不正
+    #[error("invalid value for option {0}: {1}")]
+    InvalidValue(String, String),
+    /// 未知のオプション
+    #[error("unknown option {0}")]
+    UnknownOption(String),
+    /// 再実行する実行IDが指定されていない
+    #[error("run id to replay is required")]
+    RunIdMissing,
+    /// 分割・修正する結果ファイルが指定されていない
+    #[error("result file to split or patch is required")]
+    ResultFileMissing,
+    /// バッチファイルが指定されていない
+    #[error("batch file is required")]
+    BatchFileMissing,
+    /// 未知のサブコマンド
+    #[error("unknown subcommand {0}")]
+    UnknownSubcommand(String),
+    /// `--watch`で監視する設定ファイルがない、またはサブコマンドや`--interactive`・GitHubへの投稿と一緒に指定された
+    #[error("--watch requires a setting file and cannot be used with subcommands, --interactive, --github-issue or --github-comment")]
+    WatchUnsupported
+}
+
+/// 実行するサブコマンド
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// チームを作成して出力する
+    Run,
+    /// 前回の結果からの変更を表示するだけで何も書き込まない
+    Plan {
+        /// 前回の結果ファイルのパス
+        result_path: String
+    },
+    /// チームを作成して結果ファイルに保存する
+    Apply {
+        /// 結果を保存するファイルのパス
+        result_path: String
+    },
+    /// 実行履歴に記録された実行を同じシード値で再実行し、結果が一致するか確かめる
+    Replay {
+        /// 再実行する実行ID
+        run_id: String,
+        /// 実行履歴ファイルのパス
+        history_path: String
+    },
+    /// 結果ファイルの1チームを複数のチームに分割して保存する
+    Split {
+        /// 結果ファイルのパス
+        result_path: String,
+        /// 分割するチーム
+        team: TeamSelector,
+        /// 分割後のチーム数
+        into: u8
+    },
+    /// 結果ファイルから欠席者を除いて保存する
+    Patch {
+        /// 結果ファイルのパス
+        result_path: String,
+        /// 欠席者の名前
+        absent: Vec<String>
+    },
+    /// バッチファイルに書いた複数の会のチームをまとめて作成する
+    Batch {
+        /// バッチファイルのパス
+        batch_path: String
+    },
+    /// 実行履歴・アーカイブから残す範囲を外れた実行を削除する
+    PruneHistory
+}
+
+/// 設定ファイルの代わりに標準入力から読むことを表すパス
+pub const STDIN: &str = "-";
+
+/// `--attendee`で指定した出席者
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineAttendee {
+    /// 名前
+    pub name: String,
+    /// リーダー候補かどうか(`NAME:leader`)
+    pub leader: bool
+}
+
+impl InlineAttendee {
+    /// `NAME`または`NAME:leader`を解析する
+    /// # Returns
+    /// 名前が空であればNone
+    fn parse(value: &str) -> Option<InlineAttendee> {
+        let (name, leader) = match value.strip_suffix(":leader") {
+            Some(name) => (name, true),
+            None => (value, false)
+        };
+        let name = name.trim();
+
+        (!name.is_empty()).then(|| InlineAttendee { name: name.to_string(), leader })
+    }
+}
+
+impl fmt::Display for InlineAttendee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.name, if self.leader { ":leader" } else { "" })
+    }
+}
+
+/// コマンドライン引数から読み取ったオプション
+#[derive(Debug, PartialEq)]
+pub struct CliOptions {
+    /// サブコマンド
+    pub command: Command,
+    /// 設定ファイルのパス。`-`であれば標準入力から読む
+    /// split・patch以外で省略した場合は`-`、split・patchでは省略でき、その場合は空文字列
+    pub setting_path: String,
+    /// 設定ファイルより前に指定した、先にまとめる設定ファイルのパス(指定順)
+    pub base_settings: Vec<String>,
+    /// シャッフルのシード値。指定されていなければ毎回ランダム
+    pub seed: Option<u64>,
+    /// 出力形式
+    pub format: OutputFormat,
+    /// 出力形式に共通の出力オプション
+    pub format_options: FormatOptions,
+    /// メンバーを並べ替える照合順序。指定されていなければ並べ替えない
+    pub sort: Option<Collation>,
+    /// 結果をコミットするgitリポジトリのパス。指定されていなければアーカイブしない
+    pub archive: Option<String>,
+    /// 結果を投稿するGitHubのissue。指定されていなければ投稿しない
+    pub github: Option<GithubTarget>,
+    /// 実行履歴を保存するファイルのパス。指定されていなければ記録しない
+    pub history: Option<String>,
+    /// 直近この回数の実行で同じチームになった2人を同じチームにしない(--historyが必要)
+    pub avoid_recent_pairs: Option<usize>,
+    /// 同じ人が連続してリーダーになってよい回数(--historyが必要)
+    pub max_consecutive_leads: Option<usize>,
+    /// 履歴に対する新鮮さ(新しい組の割合)の下限(0.0〜1.0、--historyが必要)。下回る結果は書き込まない
+    pub min_score: Option<f64>,
+    /// 標準エラー出力に書くログの形式。指定されていなければログを出力しない
+    pub log_format: Option<LogFormat>,
+    /// 前回の結果のうち作り直すチーム(plan/applyのみ)。指定されていなければ全チームを作る
+    pub reroll_team: Option<TeamSelector>,
+    /// 1行1人の名前のリストのパス。指定されていれば設定ファイルの出席者に追加する
+    pub names_file: Option<String>,
+    /// 結果のキャッシュを保存するディレクトリ。指定されていなければキャッシュしない
+    pub cache: Option<String>,
+    /// 今回の実行だけリーダー候補から外す出席者の名前
+    pub no_lead: Vec<String>,
+    /// 設定ファイルに合わせる組み込みのテンプレート
+    pub template: Option<TemplatePreset>,
+    /// `--attendee`で指定した出席者(指定順)
+    pub attendees: Vec<InlineAttendee>,
+    /// `--teams`で指定したチーム数。指定されていれば設定ファイルのチーム数を上書きする
+    pub num_of_teams: Option<u8>,
+    /// 出席者とチーム数を対話的に入力するかどうか
+    pub interactive: bool,
+    /// 設定ファイルが変わるたびにチームを作り直して出力し続けるかどうか
+    pub watch: bool,
+    /// `--keep-runs`・`--keep-months`で指定した実行履歴・アーカイブを残す範囲
+    /// 指定されていれば設定ファイルの`retention`の代わりに使う
+    pub retention: Retention
+}
+
+impl CliOptions {
+    /// コマンドライン引数を解析する
+    /// # Attributes
+    /// * `args` - プログラム名を除いたコマンドライン引数
+    ///
+    /// # Returns
+    /// 解析結果の`CliOptions`、不正な引数があればErr<CliError>
+    pub fn parse<I>(args: I) -> Result<CliOptions, CliError>
+    where
+        I: IntoIterator<Item = String>
+    {
+        let mut args = args.into_iter().peekable();
+        let subcommand = match args.peek().map(|a| a.as_str()) {
+            Some(name @ ("plan" | "apply" | "replay" | "split" | "patch" | "batch" | "history")) => Some(name.to_string()),
+            _ => None
+        };
+        if subcommand.is_some() {
+            args.next();
+        }
+        // historyはさらにサブコマンドを取る
+        if subcommand.as_deref() == Some("history") {
+            match args.next() {
+                Some(action) if action == "prune" => {},
+                Some(action) => Err(CliError::UnknownSubcommand(format!("history {}", action)))?,
+                None => Err(CliError::UnknownSubcommand("history".into()))?
+            }
+        }
+        let mut positionals: Vec<String> = Vec::new();
+        let mut seed: Option<u64> = None;
+        let mut format: Option<OutputFormat> = None;
+        let mut format_options = FormatOptions::default();
+        let mut sort: Option<Collation> = None;
+        let mut result_path: Option<String> = None;
+        let mut archive: Option<String> = None;
+        let mut github: Option<GithubTarget> = None;
+        let mut history: Option<String> = None;
+        let mut avoid_recent_pairs: Option<usize> = None;
+        let mut max_consecutive_leads: Option<usize> = None;
+        let mut min_score: Option<f64> = None;
+        let mut log_format: Option<LogFormat> = None;
+        let mut reroll_team: Option<TeamSelector> = None;
+        let mut names_file: Option<String> = None;
+        let mut split_team: Option<TeamSelector> = None;
+        let mut into: Option<u8> = None;
+        let mut cache: Option<String> = None;
+        let mut no_lead: Vec<String> = Vec::new();
+        let mut template: Option<TemplatePreset> = None;
+        let mut absent: Vec<String> = Vec::new();
+        let mut attendees: Vec<InlineAttendee> = Vec::new();
+        let mut num_of_teams: Option<u8> = None;
+        let mut interactive = false;
+        let mut watch = false;
+        let mut retention = Retention::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    seed = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--format" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    format = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--sort" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    sort = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--result" => {
+                    result_path = Some(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                "--archive" => {
+                    archive = Some(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                "--github-issue" => {
+                    github = Some(GithubTarget::NewIssue(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?));
+                },
+                "--github-comment" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    github = Some(GithubTarget::IssueComment(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?));
+                },
+                "--history" => {
+                    history = Some(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                "--log-format" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    log_format = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--locale" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    format_options.locale = value.parse::<Locale>().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?;
+                },
+                "--avoid-recent-pairs" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    avoid_recent_pairs = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--max-consecutive-leads" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    max_consecutive_leads = Some(value.parse().ok().filter(|n| *n >= 1).ok_or_else(|| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--min-score" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    min_score = Some(value.parse().ok().filter(|s| (0.0..=1.0).contains(s)).ok_or_else(|| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--keep-runs" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    retention.keep_runs = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--keep-months" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    retention.keep_months = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--reroll-team" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    reroll_team = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--interactive" => {
+                    interactive = true;
+                },
+                "--watch" => {
+                    watch = true;
+                },
+                "--leader-in-members" => {
+                    format_options.leader_in_members = true;
+                },
+                "--honorific" => {
+                    format_options.honorific = Some(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                "--team" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    split_team = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--into" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    into = Some(value.parse().ok().filter(|n| *n >= 2).ok_or_else(|| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--no-lead" => {
+                    no_lead.push(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                "--absent" => {
+                    absent.push(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                "--template-preset" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    template = Some(value.parse().map_err(|_| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--attendee" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    attendees.push(InlineAttendee::parse(&value).ok_or_else(|| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--teams" => {
+                    let value = args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?;
+                    num_of_teams = Some(value.parse().ok().filter(|n| *n >= 1).ok_or_else(|| CliError::InvalidValue(arg.clone(), value.clone()))?);
+                },
+                "--cache" => {
+                    cache = Some(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                "--names-file" => {
+                    names_file = Some(args.next().ok_or_else(|| CliError::ValueMissing(arg.clone()))?);
+                },
+                _ if arg.starts_with("--") => Err(CliError::UnknownOption(arg.clone()))?,
+                _ => positionals.push(arg)
+            }
+        }
+
+        // テンプレートの出力形式は`--format`を指定しなかった場合だけ使う
+        let format = format.or(template.map(|t| t.format())).unwrap_or_default();
+
+        if let Some(name @ ("split" | "patch" | "batch" | "history")) = subcommand.as_deref() {
+            if watch {
+                Err(CliError::WatchUnsupported)?
+            }
+            let mut positionals = positionals.into_iter();
+            // history pruneでは設定ファイルだけを省略可能な引数に取る
+            let first = if name == "history" { None } else { positionals.next() };
+            let command = if name == "history" {
+                if history.is_none() && archive.is_none() {
+                    Err(CliError::ValueMissing("--history".into()))?
+                }
+                Command::PruneHistory
+            } else if name == "batch" {
+                Command::Batch { batch_path: first.ok_or(CliError::BatchFileMissing)? }
+            } else if name == "split" {
+                let result_path = first.ok_or(CliError::ResultFileMissing)?;
+                Command::Split {
+                    result_path,
+                    team: split_team.ok_or_else(|| CliError::ValueMissing("--team".into()))?,
+                    into: into.ok_or_else(|| CliError::ValueMissing("--into".into()))?
+                }
+            } else if absent.is_empty() {
+                Err(CliError::ValueMissing("--absent".into()))?
+            } else {
+                Command::Patch { result_path: first.ok_or(CliError::ResultFileMissing)?, absent }
+            };
+
+            return Ok(CliOptions {
+                command,
+                setting_path: positionals.next().unwrap_or_default(),
+                base_settings: Vec::new(),
+                seed,
+                format,
+                format_options,
+                sort,
+                archive,
+                github,
+                history,
+                avoid_recent_pairs,
+                max_consecutive_leads,
+                min_score,
+                log_format,
+                reroll_team,
+                names_file,
+                cache,
+                no_lead,
+                template,
+                attendees,
+                num_of_teams,
+                interactive,
+                watch,
+                retention
+            });
+        }
+        // replayでは最初の引数が実行ID
+        let run_id = if subcommand.as_deref() == Some("replay") && !positionals.is_empty() {
+            Some(positionals.remove(0))
+        } else {
+            None
+        };
+        // `--attendee`・`--names-file`・`--interactive`を指定した場合は設定ファイルを省略でき、標準入力からも読まない
+        let setting_path = match positionals.pop() {
+            Some(path) => path,
+            None if !attendees.is_empty() || names_file.is_some() || interactive => String::new(),
+            None => STDIN.to_string()
+        };
+        let base_settings = positionals;
+        if (avoid_recent_pairs.is_some() || max_consecutive_leads.is_some() || min_score.is_some()) && history.is_none() {
+            Err(CliError::ValueMissing("--history".into()))?
+        }
+        let command = match subcommand.as_deref() {
+            Some("plan") => Command::Plan { result_path: result_path.ok_or_else(|| CliError::ValueMissing("--result".into()))? },
+            Some("apply") => Command::Apply { result_path: result_path.ok_or_else(|| CliError::ValueMissing("--result".into()))? },
+            Some(_) => Command::Replay {
+                run_id: run_id.ok_or(CliError::RunIdMissing)?,
+                history_path: history.clone().ok_or_else(|| CliError::ValueMissing("--history".into()))?
+            },
+            None => Command::Run
+        };
+        if reroll_team.is_some() && !matches!(command, Command::Plan { .. } | Command::Apply { .. }) {
+            Err(CliError::ValueMissing("--result".into()))?
+        }
+        if watch && (command != Command::Run || interactive || github.is_some() || setting_path.is_empty() || setting_path == STDIN) {
+            Err(CliError::WatchUnsupported)?
+        }
+
+        Ok(CliOptions {
+            command,
+            setting_path,
+            base_settings,
+            seed,
+            format,
+            format_options,
+            sort,
+            archive,
+            github,
+            history,
+            avoid_recent_pairs,
+            max_consecutive_leads,
+            min_score,
+            log_format,
+            reroll_team,
+            names_file,
+            cache,
+            no_lead,
+            template,
+            attendees,
+            num_of_teams,
+            interactive,
+            watch,
+            retention
+        })
+    }
+
+    /// `--attendee`と`--teams`から作る設定値(TOML)
+    ///
+    /// # Returns
+    /// どちらも指定されていなければNone
+    pub fn inline_setting(&self) -> Option<String> {
+        if self.attendees.is_empty() && self.num_of_teams.is_none() {
+            return None;
+        }
+
+        let mut table = toml::map::Map::new();
+        if let Some(num_of_teams) = self.num_of_teams {
+            table.insert("num_of_teams".into(), Value::Integer(num_of_teams.into()));
+        }
+        if !self.attendees.is_empty() {
+            let attendees = self.attendees.iter().map(|a| {
+                let mut person = toml::map::Map::new();
+                person.insert("name".into(), Value::String(a.name.clone()));
+                let mut attendee = toml::map::Map::new();
+                attendee.insert("leader".into(), Value::Boolean(a.leader));
+                attendee.insert("person".into(), Value::Table(person));
+                Value::Table(attendee)
+            }).collect();
+            table.insert("attendees".into(), Value::Array(attendees));
+        }
+
+        toml::to_string(&Value::Table(table)).ok()
+    }
+
+    /// planの結果をそのまま反映するapplyのコマンドライン引数
+    /// planの引数のサブコマンドをapplyに、`--seed`を実際に使ったシード値に置き換え、それ以外の引数はそのまま残す
+    /// `--interactive`で答えた出席者とチーム数は`--attendee`・`--teams`にする
+    /// # Attributes
+    /// * `args` - planのコマンドライン引数(プログラム名を除く)
+    /// * `seed` - planで使ったシード値
+    pub fn apply_args(&self, args: &[String], seed: u64) -> Vec<String> {
+        let mut res = vec!["apply".to_string(), "--seed".to_string(), seed.to_string()];
+
+        let mut args = args.iter().skip(usize::from(args.first().is_some_and(|a| a == "plan")));
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    args.next();
+                },
+                "--attendee" | "--teams" if self.interactive => {
+                    args.next();
+                },
+                "--interactive" => {},
+                _ => res.push(arg.clone())
+            }
+        }
+        if self.interactive {
+            for attendee in &self.attendees {
+                res.extend(["--attendee".to_string(), attendee.to_string()]);
+            }
+            if let Some(num_of_teams) = self.num_of_teams {
+                res.extend(["--teams".to_string(), num_of_teams.to_string()]);
+            }
+        }
+
+        res
+    }
+}
+
+/// シェルにそのまま貼り付けられるよう、必要であれば引数をシングルクオートで囲む
+/// # Attributes
+/// * `arg` - コマンドライン引数
+pub fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+".contains(c)) {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// CliOptions#parseのテスト
+    /// 設定ファイル名のみ指定した場合はシード値なし
+    #[test]
+    fn parse_setting_path_only() {
+        let options = CliOptions::parse(args(&["setting.toml"])).unwrap();
+
+        assert_eq!(options.command, Command::Run);
+        assert_eq!(options.setting_path, "setting.toml");
+        assert_eq!(options.seed, None);
+        assert_eq!(options.format, OutputFormat::Toml);
+        assert_eq!(options.sort, None);
+        assert_eq!(options.archive, None);
+        assert_eq!(options.history, None);
+        assert_eq!(options.avoid_recent_pairs, None);
+        assert_eq!(options.log_format, None);
+    }
+
+    /// CliOptions#parseのテスト
+    /// --seedでシード値を指定できる
+    #[test]
+    fn parse_seed() {
+        let options = CliOptions::parse(args(&["--seed", "42", "setting.toml"])).unwrap();
+
+        assert_eq!(options.seed, Some(42));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --formatで出力形式を指定できる
+    #[test]
+    fn parse_format() {
+        let options = CliOptions::parse(args(&["setting.toml", "--format", "org"])).unwrap();
+
+        assert_eq!(options.format, OutputFormat::Org);
+        assert_eq!(options.format_options, FormatOptions::default());
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--format", "xml"])),
+            Err(CliError::InvalidValue("--format".into(), "xml".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --localeでラベルの言語を指定できる
+    #[test]
+    fn parse_locale() {
+        let options = CliOptions::parse(args(&["setting.toml", "--locale", "ja"])).unwrap();
+
+        assert_eq!(options.format_options.locale, Locale::Ja);
+    }
+
+    /// CliOptions#parseのテスト
+    /// --sortでメンバーの照合順序を指定できる
+    #[test]
+    fn parse_sort() {
+        let options = CliOptions::parse(args(&["setting.toml", "--sort", "kana"])).unwrap();
+
+        assert_eq!(options.sort, Some(Collation::Kana));
+    }
+
+    /// CliOptions#parseのテスト
+    /// 先頭のplan/applyはサブコマンドとして扱い、--resultが必須になる
+    #[test]
+    fn parse_plan_and_apply() {
+        let plan = CliOptions::parse(args(&["plan", "--result", "last.toml", "setting.toml"])).unwrap();
+        let apply = CliOptions::parse(args(&["apply", "--seed", "1", "--result", "last.toml", "setting.toml"])).unwrap();
+
+        assert_eq!(plan.command, Command::Plan { result_path: "last.toml".into() });
+        assert_eq!(plan.setting_path, "setting.toml");
+        assert_eq!(apply.command, Command::Apply { result_path: "last.toml".into() });
+        assert_eq!(
+            CliOptions::parse(args(&["plan", "setting.toml"])),
+            Err(CliError::ValueMissing("--result".into()))
+        );
+    }
+
+    /// CliOptions#apply_argsのテスト
+    /// planの引数をサブコマンドと--seed以外そのまま残し、解析するとplanと同じオプションのapplyになる
+    #[test]
+    fn plan_apply_args() {
+        let plan_args = args(&[
+            "plan", "--seed", "7", "--result", "last.toml", "--names-file", "names.csv", "--no-lead", "Taro",
+            "--template-preset", "lunch", "--reroll-team", "2", "--history", "h.toml", "--avoid-recent-pairs", "3", "base.toml", "setting.toml"
+        ]);
+        let plan = CliOptions::parse(plan_args.clone()).unwrap();
+
+        let apply_args = plan.apply_args(&plan_args, 42);
+        let apply = CliOptions::parse(apply_args.clone()).unwrap();
+
+        assert_eq!(&apply_args[..3], &args(&["apply", "--seed", "42"])[..]);
+        assert_eq!(apply, CliOptions { command: Command::Apply { result_path: "last.toml".into() }, seed: Some(42), ..plan });
+    }
+
+    /// CliOptions#apply_argsのテスト
+    /// --interactiveで答えた出席者とチーム数は--attendee・--teamsにする
+    #[test]
+    fn plan_apply_args_interactive() {
+        let plan_args = args(&["plan", "--interactive", "--attendee", "A", "--result", "last.toml"]);
+        let mut plan = CliOptions::parse(plan_args.clone()).unwrap();
+        plan.attendees.push(InlineAttendee { name: "B".into(), leader: true });
+        plan.num_of_teams = Some(2);
+
+        assert_eq!(
+            plan.apply_args(&plan_args, 1),
+            args(&["apply", "--seed", "1", "--result", "last.toml", "--attendee", "A", "--attendee", "B:leader", "--teams", "2"])
+        );
+    }
+
+    /// shell_quoteのテスト
+    /// 記号や空白を含む引数だけをシングルクオートで囲む
+    #[test]
+    fn quote_shell_args() {
+        assert_eq!(shell_quote("setting.toml"), "setting.toml");
+        assert_eq!(shell_quote("Yamada Taro"), "'Yamada Taro'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    /// CliOptions#parseのテスト
+    /// replayは実行IDと設定ファイル名を受け取り、--historyが必須になる
+    #[test]
+    fn parse_replay() {
+        let options = CliOptions::parse(args(&["replay", "--history", "history.toml", "1-abc", "setting.toml"])).unwrap();
+
+        assert_eq!(options.command, Command::Replay { run_id: "1-abc".into(), history_path: "history.toml".into() });
+        assert_eq!(options.setting_path, "setting.toml");
+        assert_eq!(
+            CliOptions::parse(args(&["replay", "--history", "history.toml"])),
+            Err(CliError::RunIdMissing)
+        );
+        assert_eq!(CliOptions::parse(args(&["replay", "--history", "history.toml", "1-abc"])).unwrap().setting_path, STDIN);
+        assert_eq!(
+            CliOptions::parse(args(&["replay", "1-abc", "setting.toml"])),
+            Err(CliError::ValueMissing("--history".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --archiveでアーカイブ先のリポジトリを指定できる
+    #[test]
+    fn parse_archive() {
+        let options = CliOptions::parse(args(&["setting.toml", "--archive", "results"])).unwrap();
+
+        assert_eq!(options.archive, Some("results".into()));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --github-issueで作成するissueのタイトルを、--github-commentでコメントするissueの番号を指定できる
+    #[test]
+    fn parse_github() {
+        let issue = CliOptions::parse(args(&["setting.toml", "--github-issue", "Sprint 12 teams"])).unwrap();
+        let comment = CliOptions::parse(args(&["setting.toml", "--github-comment", "12"])).unwrap();
+
+        assert_eq!(issue.github, Some(GithubTarget::NewIssue("Sprint 12 teams".into())));
+        assert_eq!(comment.github, Some(GithubTarget::IssueComment(12)));
+        assert_eq!(CliOptions::parse(args(&["setting.toml"])).unwrap().github, None);
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--github-comment", "abc"])),
+            Err(CliError::InvalidValue("--github-comment".into(), "abc".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --historyで実行履歴ファイルを指定できる
+    #[test]
+    fn parse_history() {
+        let options = CliOptions::parse(args(&["setting.toml", "--history", "history.toml"])).unwrap();
+
+        assert_eq!(options.history, Some("history.toml".into()));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --avoid-recent-pairsには--historyが必要
+    #[test]
+    fn parse_avoid_recent_pairs() {
+        let options = CliOptions::parse(args(&["setting.toml", "--history", "history.toml", "--avoid-recent-pairs", "3"])).unwrap();
+
+        assert_eq!(options.avoid_recent_pairs, Some(3));
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--avoid-recent-pairs", "3"])),
+            Err(CliError::ValueMissing("--history".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --max-consecutive-leadsは1以上で、--historyが必要
+    #[test]
+    fn parse_max_consecutive_leads() {
+        let options = CliOptions::parse(args(&["setting.toml", "--history", "history.toml", "--max-consecutive-leads", "2"])).unwrap();
+
+        assert_eq!(options.max_consecutive_leads, Some(2));
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--max-consecutive-leads", "2"])),
+            Err(CliError::ValueMissing("--history".into()))
+        );
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--history", "history.toml", "--max-consecutive-leads", "0"])),
+            Err(CliError::InvalidValue("--max-consecutive-leads".into(), "0".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --log-formatでログの形式を指定できる
+    #[test]
+    fn parse_log_format() {
+        let options = CliOptions::parse(args(&["setting.toml", "--log-format", "json"])).unwrap();
+
+        assert_eq!(options.log_format, Some(LogFormat::Json));
+    }
+
+    /// CliOptions#parseのテスト
+    /// 不正な引数はエラーになる
+    #[test]
+    fn parse_errors() {
+        assert_eq!(CliOptions::parse(args(&["setting.toml", "--seed"])), Err(CliError::ValueMissing("--seed".into())));
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--seed", "abc"])),
+            Err(CliError::InvalidValue("--seed".into(), "abc".into()))
+        );
+        assert_eq!(CliOptions::parse(args(&["setting.toml", "--foo"])), Err(CliError::UnknownOption("--foo".into())));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --reroll-teamは番号か名前でチームを指定し、plan/applyでのみ使える
+    #[test]
+    fn parse_reroll_team() {
+        let by_index = CliOptions::parse(args(&["apply", "--result", "last.toml", "--reroll-team", "2", "setting.toml"])).unwrap();
+        let by_name = CliOptions::parse(args(&["plan", "--result", "last.toml", "--reroll-team", "Taro", "setting.toml"])).unwrap();
+
+        assert_eq!(by_index.reroll_team, Some(TeamSelector::Index(2)));
+        assert_eq!(by_name.reroll_team, Some(TeamSelector::Name("Taro".into())));
+        assert_eq!(
+            CliOptions::parse(args(&["--reroll-team", "2", "setting.toml"])),
+            Err(CliError::ValueMissing("--result".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --names-fileで名前のリストのパスを指定する。その場合は設定ファイルを省略できる
+    #[test]
+    fn parse_names_file() {
+        let options = CliOptions::parse(args(&["--names-file", "names.txt", "setting.toml"])).unwrap();
+
+        assert_eq!(options.names_file, Some("names.txt".into()));
+        assert_eq!(options.setting_path, "setting.toml");
+
+        let options = CliOptions::parse(args(&["--names-file", "names.txt", "--teams", "3"])).unwrap();
+        assert_eq!(options.setting_path, "");
+        assert_eq!(options.num_of_teams, Some(3));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --honorificで名前に付ける敬称を指定できる
+    #[test]
+    fn parse_honorific() {
+        let options = CliOptions::parse(args(&["--honorific", "さん", "setting.toml"])).unwrap();
+
+        assert_eq!(options.format_options.honorific, Some("さん".into()));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --interactiveを指定すれば設定ファイルを省略でき、標準入力から設定を読まない
+    #[test]
+    fn parse_interactive() {
+        let options = CliOptions::parse(args(&["--interactive", "--format", "org"])).unwrap();
+
+        assert!(options.interactive);
+        assert_eq!(options.setting_path, "");
+    }
+
+    /// CliOptions#parseのテスト
+    /// --leader-in-membersでリーダーをメンバーの一覧にも含める
+    #[test]
+    fn parse_leader_in_members() {
+        assert!(CliOptions::parse(args(&["--leader-in-members", "setting.toml"])).unwrap().format_options.leader_in_members);
+        assert!(!CliOptions::parse(args(&["setting.toml"])).unwrap().format_options.leader_in_members);
+    }
+
+    /// CliOptions#parseのテスト
+    /// splitは結果ファイルと--team・--intoが必須で、設定ファイルは省略できる
+    #[test]
+    fn parse_split() {
+        let options = CliOptions::parse(args(&["split", "last.toml", "--team", "Taro", "--into", "2"])).unwrap();
+        let with_setting = CliOptions::parse(args(&["split", "last.toml", "setting.toml", "--team", "1", "--into", "3"])).unwrap();
+
+        assert_eq!(options.command, Command::Split { result_path: "last.toml".into(), team: TeamSelector::Name("Taro".into()), into: 2 });
+        assert_eq!(options.setting_path, "");
+        assert_eq!(with_setting.command, Command::Split { result_path: "last.toml".into(), team: TeamSelector::Index(1), into: 3 });
+        assert_eq!(with_setting.setting_path, "setting.toml");
+        assert_eq!(
+            CliOptions::parse(args(&["split", "last.toml", "--team", "1"])),
+            Err(CliError::ValueMissing("--into".into()))
+        );
+        assert_eq!(
+            CliOptions::parse(args(&["split", "last.toml", "--team", "1", "--into", "1"])),
+            Err(CliError::InvalidValue("--into".into(), "1".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --cacheで結果のキャッシュを保存するディレクトリを指定する
+    #[test]
+    fn parse_cache() {
+        let options = CliOptions::parse(args(&["--cache", ".guccicci-cache", "--seed", "1", "setting.toml"])).unwrap();
+
+        assert_eq!(options.cache, Some(".guccicci-cache".into()));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --attendeeを指定すれば設定ファイルを省略でき、--teamsと合わせて設定値を作る
+    #[test]
+    fn parse_inline_attendees() {
+        let options = CliOptions::parse(args(&["--attendee", "Taro", "--attendee", "Hanako", "--attendee", "Jiro", "--teams", "2"])).unwrap();
+        assert_eq!(options.setting_path, "");
+        assert_eq!(options.attendees[1], InlineAttendee { name: "Hanako".into(), leader: false });
+
+        let setting: crate::domain::TeamsCreationSetting = toml::from_str(&options.inline_setting().unwrap()).unwrap();
+        assert_eq!(setting.analyze().num_of_teams, 2);
+        assert_eq!(setting.all_people().len(), 3);
+
+        let options = CliOptions::parse(args(&["--attendee", "Taro:leader", "--attendee", "Hanako"])).unwrap();
+        let setting: crate::domain::TeamsCreationSetting = toml::from_str(&format!("num_of_teams = 1\n{}", options.inline_setting().unwrap())).unwrap();
+        assert_eq!(setting.leader_candidates().len(), 1);
+
+        assert_eq!(CliOptions::parse(args(&["--attendee", ":leader"])), Err(CliError::InvalidValue("--attendee".into(), ":leader".into())));
+        assert_eq!(CliOptions::parse(args(&["--teams", "0"])), Err(CliError::InvalidValue("--teams".into(), "0".into())));
+        assert_eq!(CliOptions::parse(args(&["setting.toml"])).unwrap().inline_setting(), None);
+    }
+
+    /// CliOptions#parseのテスト
+    /// --no-leadは繰り返し指定できる
+    #[test]
+    fn parse_no_lead() {
+        let options = CliOptions::parse(args(&["--no-lead", "Taro", "--no-lead", "Hanako", "setting.toml"])).unwrap();
+
+        assert_eq!(options.no_lead, vec!["Taro".to_string(), "Hanako".to_string()]);
+    }
+
+    /// CliOptions#parseのテスト
+    /// テンプレートの出力形式は--formatを指定しなかった場合だけ使う
+    #[test]
+    fn parse_template_preset() {
+        let options = CliOptions::parse(args(&["--template-preset", "hackathon", "setting.toml"])).unwrap();
+        assert_eq!(options.template, Some(TemplatePreset::Hackathon));
+        assert_eq!(options.format, OutputFormat::Svg);
+
+        let options = CliOptions::parse(args(&["--template-preset", "hackathon", "--format", "toml", "setting.toml"])).unwrap();
+        assert_eq!(options.format, OutputFormat::Toml);
+
+        assert_eq!(
+            CliOptions::parse(args(&["--template-preset", "party", "setting.toml"])),
+            Err(CliError::InvalidValue("--template-preset".into(), "party".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// 設定ファイルを省略すると標準入力から読む
+    #[test]
+    fn parse_stdin_setting() {
+        assert_eq!(CliOptions::parse(args(&[])).unwrap().setting_path, STDIN);
+        assert_eq!(CliOptions::parse(args(&["--seed", "1", "-"])).unwrap().setting_path, STDIN);
+    }
+
+    /// CliOptions#parseのテスト
+    /// patchでは結果ファイルと欠席者が必要
+    #[test]
+    fn parse_patch() {
+        let options = CliOptions::parse(args(&["patch", "last.toml", "--absent", "Taro", "--absent", "Hanako"])).unwrap();
+
+        assert_eq!(options.command, Command::Patch { result_path: "last.toml".into(), absent: vec!["Taro".into(), "Hanako".into()] });
+        assert_eq!(options.setting_path, "");
+        assert_eq!(
+            CliOptions::parse(args(&["patch", "last.toml"])),
+            Err(CliError::ValueMissing("--absent".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// 設定ファイルを複数指定すると、最後以外は先にまとめる設定ファイルになる
+    #[test]
+    fn parse_multiple_settings() {
+        let options = CliOptions::parse(args(&["attendees.toml", "common.toml", "event.toml"])).unwrap();
+        assert_eq!(options.base_settings, vec!["attendees.toml".to_string(), "common.toml".to_string()]);
+        assert_eq!(options.setting_path, "event.toml");
+
+        let replay = CliOptions::parse(args(&["replay", "--history", "history.toml", "1-abc", "attendees.toml", "event.toml"])).unwrap();
+        assert_eq!(replay.command, Command::Replay { run_id: "1-abc".into(), history_path: "history.toml".into() });
+        assert_eq!(replay.base_settings, vec!["attendees.toml".to_string()]);
+    }
+
+    /// CliOptions#parseのテスト
+    /// batchではバッチファイルが必要
+    #[test]
+    fn parse_batch() {
+        let options = CliOptions::parse(args(&["batch", "events.toml", "--seed", "1"])).unwrap();
+
+        assert_eq!(options.command, Command::Batch { batch_path: "events.toml".into() });
+        assert_eq!(options.seed, Some(1));
+        assert_eq!(CliOptions::parse(args(&["batch"])), Err(CliError::BatchFileMissing));
+    }
+
+    /// CliOptions#parseのテスト
+    /// --min-scoreは0.0〜1.0で、--historyが必要
+    #[test]
+    fn parse_min_score() {
+        let options = CliOptions::parse(args(&["setting.toml", "--history", "history.toml", "--min-score", "0.8"])).unwrap();
+
+        assert_eq!(options.min_score, Some(0.8));
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--history", "history.toml", "--min-score", "1.5"])),
+            Err(CliError::InvalidValue("--min-score".into(), "1.5".into()))
+        );
+        assert_eq!(
+            CliOptions::parse(args(&["setting.toml", "--min-score", "0.8"])),
+            Err(CliError::ValueMissing("--history".into()))
+        );
+    }
+
+    /// CliOptions#parseのテスト
+    /// --watchには設定ファイルが必要で、サブコマンドや--interactiveとは一緒に使えない
+    #[test]
+    fn parse_watch() {
+        assert!(CliOptions::parse(args(&["setting.toml", "--watch"])).unwrap().watch);
+        assert!(!CliOptions::parse(args(&["setting.toml"])).unwrap().watch);
+        assert_eq!(CliOptions::parse(args(&["--watch"])), Err(CliError::WatchUnsupported));
+        assert_eq!(CliOptions::parse(args(&["--watch", "--attendee", "Taro"])), Err(CliError::WatchUnsupported));
+        assert_eq!(CliOptions::parse(args(&["--watch", "--interactive", "setting.toml"])), Err(CliError::WatchUnsupported));
+        assert_eq!(CliOptions::parse(args(&["apply", "--result", "last.toml", "--watch", "setting.toml"])), Err(CliError::WatchUnsupported));
+        assert_eq!(CliOptions::parse(args(&["batch", "events.toml", "--watch"])), Err(CliError::WatchUnsupported));
+        assert_eq!(CliOptions::parse(args(&["--watch", "--github-comment", "12", "setting.toml"])), Err(CliError::WatchUnsupported));
+    }
+
+    /// CliOptions#parseのテスト
+    /// history pruneでは--historyか--archiveが必要で、設定ファイルは省略できる
+    #[test]
+    fn parse_history_prune() {
+        let options = CliOptions::parse(args(&["history", "prune", "--history", "history.toml", "--keep-runs", "10", "--keep-months", "6"])).unwrap();
+
+        assert_eq!(options.command, Command::PruneHistory);
+        assert_eq!(options.setting_path, "");
+        assert_eq!(options.retention, Retention { keep_runs: std::num::NonZeroUsize::new(10), keep_months: std::num::NonZeroU64::new(6) });
+        assert_eq!(CliOptions::parse(args(&["history", "prune", "--archive", "results", "setting.toml"])).unwrap().setting_path, "setting.toml");
+        assert_eq!(CliOptions::parse(args(&["history", "prune", "--keep-runs", "10"])), Err(CliError::ValueMissing("--history".into())));
+        assert_eq!(CliOptions::parse(args(&["history", "show"])), Err(CliError::UnknownSubcommand("history show".into())));
+        assert_eq!(
+            CliOptions::parse(args(&["history", "prune", "--history", "history.toml", "--keep-runs", "0"])),
+            Err(CliError::InvalidValue("--keep-runs".into(), "0".into()))
+        );
+    }
+}
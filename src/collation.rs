@@ -0,0 +1,112 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+use thiserror::Error;
+use crate::domain::Person;
+
+/// 照合順序に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum CollationError {
+    /// 未知の照合順序
+    #[error("unknown collation {0}")]
+    UnknownCollation(String)
+}
+
+/// 名前を並べる順序
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collation {
+    /// 名前の文字コード順
+    Bytes,
+    /// 読みの五十音順
+    /// `reading`があればそれを、なければ名前を使い、カタカナはひらがなとして扱う
+    Kana
+}
+
+impl Collation {
+    /// 2人の`Person`をこの照合順序で比較する
+    /// # Attributes
+    /// * `a` - 比較する`Person`
+    /// * `b` - 比較する`Person`
+    ///
+    /// # Returns
+    /// `a`と`b`の順序
+    pub fn compare(&self, a: &Person, b: &Person) -> Ordering {
+        match self {
+            Self::Bytes => a.name.cmp(&b.name),
+            Self::Kana => kana_key(a).cmp(&kana_key(b)).then_with(|| a.name.cmp(&b.name))
+        }
+    }
+}
+
+impl FromStr for Collation {
+    type Err = CollationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(Self::Bytes),
+            "kana" => Ok(Self::Kana),
+            _ => Err(CollationError::UnknownCollation(s.to_string()))
+        }
+    }
+}
+
+/// 五十音順で比較するためのキーを作る
+/// カタカナ(ァ〜ヶ)をひらがなに寄せる
+/// # Attributes
+/// * `person` - キーを作る`Person`
+fn kana_key(person: &Person) -> Vec<char> {
+    let reading = person.reading.as_ref().unwrap_or(&person.name);
+
+    reading.chars().map(|c| match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person(name: &str, reading: Option<&str>) -> Person {
+        Person {
+            reading: reading.map(|r| r.into()),
+            ..Person::new(name)
+        }
+    }
+
+    /// Collation#compareのテスト
+    /// Bytesは名前の文字コード順に並べる
+    #[test]
+    fn compare_bytes() {
+        let mut people = [person("B", None), person("C", None), person("A", None)];
+
+        people.sort_by(|a, b| Collation::Bytes.compare(a, b));
+
+        let names: Vec<&str> = people.iter().map(|p| &*p.name).collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+    }
+
+    /// Collation#compareのテスト
+    /// Kanaは読みの五十音順に並べ、カタカナとひらがなを区別しない
+    #[test]
+    fn compare_kana() {
+        let mut people = [
+            person("渡辺", Some("わたなべ")),
+            person("佐藤", Some("さとう")),
+            person("伊藤", Some("イトウ")),
+            person("かとう", None),
+        ];
+
+        people.sort_by(|a, b| Collation::Kana.compare(a, b));
+
+        let names: Vec<&str> = people.iter().map(|p| &*p.name).collect();
+        assert_eq!(names, vec!["伊藤", "かとう", "佐藤", "渡辺"]);
+    }
+
+    /// Collation#from_strのテスト
+    #[test]
+    fn parse_collation() {
+        assert_eq!("bytes".parse(), Ok(Collation::Bytes));
+        assert_eq!("kana".parse(), Ok(Collation::Kana));
+        assert_eq!("abc".parse::<Collation>(), Err(CollationError::UnknownCollation("abc".into())));
+    }
+}
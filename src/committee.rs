@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use thiserror::Error;
+use crate::domain::{Person, Team};
+
+/// 委員会モードに関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum CommitteeError {
+    /// 役割枠が1つもない
+    #[error("roles must have at least one slot")]
+    NoRoleSlots,
+    /// 役割枠を埋められる参加者が残っていない
+    #[error("no attendee tagged '{1}' is left for the role slot in team {0}")]
+    UnfillableSlot(usize, String)
+}
+
+/// 委員会モードで各チームに必要な役割枠
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoleSlot {
+    /// 役割名。この名前のタグを持つ参加者だけが枠に入れる
+    pub role: String,
+    /// 枠の数(省略時は1)
+    count: Option<u8>
+}
+
+impl RoleSlot {
+    /// 役割枠を作成する
+    /// # Attributes
+    /// * `role` - 役割名
+    /// * `count` - 枠の数
+    pub fn new(role: impl Into<String>, count: u8) -> RoleSlot {
+        RoleSlot { role: role.into(), count: Some(count) }
+    }
+
+    /// 枠の数を返す
+    pub fn count(&self) -> u8 {
+        self.count.unwrap_or(1)
+    }
+}
+
+/// 役割枠を順番に埋めてチームを作成する
+/// 最初に埋まった枠の人がチームリーダーになる
+/// # Attributes
+/// * `slots` - 各チームに必要な役割枠
+/// * `num_of_teams` - チーム数
+/// * `pool` - (参加者, タグ)のリスト。先頭から順に枠に入れる
+///
+/// # Returns
+/// (作成されたチームのVec, どの枠にも入らなかった参加者のVec)のタプル
+pub fn fill_slots(slots: &[RoleSlot], num_of_teams: u8, mut pool: Vec<(Person, Vec<String>)>) -> Result<(Vec<Team>, Vec<Person>), CommitteeError> {
+    let mut teams = Vec::new();
+
+    for n in 1..=usize::from(num_of_teams) {
+        let mut team: Option<Team> = None;
+
+        for slot in slots {
+            for _ in 0..slot.count() {
+                let index = pool.iter()
+                    .position(|(_, tags)| tags.contains(&slot.role))
+                    .ok_or_else(|| CommitteeError::UnfillableSlot(n, slot.role.clone()))?;
+                let (person, _) = pool.remove(index);
+                let name = person.name.clone();
+
+                let team = match team.as_mut() {
+                    Some(team) => {
+                        team.assign(person);
+                        team
+                    },
+                    None => team.insert(Team::new(person))
+                };
+                team.roles.entry(slot.role.clone()).or_default().push(name);
+            }
+        }
+
+        teams.push(team.ok_or(CommitteeError::NoRoleSlots)?);
+    }
+
+    Ok((teams, pool.into_iter().map(|(person, _)| person).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attendee(name: &str, tags: &[&str]) -> (Person, Vec<String>) {
+        (Person::new(name), tags.iter().map(|t| t.to_string()).collect())
+    }
+
+    /// fill_slotsのテスト
+    /// タグを持つ参加者で枠を埋め、最初の枠の人がリーダーになる
+    #[test]
+    fn fill_role_slots() {
+        let slots = vec![RoleSlot::new("chair", 1), RoleSlot::new("reviewer", 2)];
+        let pool = vec![
+            attendee("A", &["reviewer"]),
+            attendee("B", &["chair"]),
+            attendee("C", &["reviewer"]),
+            attendee("D", &["chair", "reviewer"]),
+            attendee("E", &[]),
+        ];
+
+        let (teams, rest) = fill_slots(&slots, 1, pool).unwrap();
+
+        assert_eq!(&*teams[0].leader.name, "B");
+        assert_eq!(teams[0].member.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["A", "C"]);
+        assert_eq!(teams[0].roles["reviewer"].len(), 2);
+        assert_eq!(rest.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["D", "E"]);
+    }
+
+    /// fill_slotsのテスト
+    /// 埋められない枠があればチーム番号と役割名を返す
+    #[test]
+    fn unfillable_slot() {
+        let slots = vec![RoleSlot::new("chair", 1), RoleSlot::new("note-taker", 1)];
+        let pool = vec![
+            attendee("A", &["chair"]),
+            attendee("B", &["note-taker"]),
+            attendee("C", &["chair"]),
+        ];
+
+        assert_eq!(
+            fill_slots(&slots, 2, pool).unwrap_err(),
+            CommitteeError::UnfillableSlot(2, "note-taker".to_string())
+        );
+        assert_eq!(fill_slots(&[], 1, Vec::new()).unwrap_err(), CommitteeError::NoRoleSlots);
+    }
+}
@@ -0,0 +1,317 @@
+mod expression;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use serde::Deserialize;
+use thiserror::Error;
+use crate::domain::{Person, Team};
+use crate::history::RunRecord;
+
+pub use expression::{Expression, ExpressionError};
+
+/// 制約を満たす割り当てを探すときの最大試行回数
+pub const MAX_ATTEMPTS: usize = 1000;
+
+/// 制約に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum ConstraintError {
+    /// 試行回数内に制約を満たす割り当てが見つからなかった
+    #[error("could not satisfy constraints [{}] in {0} attempts; the roster may be too small for them", .1.join(", "))]
+    Unsatisfiable(usize, Vec<String>),
+    /// 試行回数内に制約を満たす割り当てが見つからず、同時に満たせない最小の制約の組を特定した
+    /// 組のどれか1つを外せば残りの制約は満たせる
+    #[error("could not satisfy constraints in {0} attempts; [{}] cannot all hold together, dropping any one of them makes the rest satisfiable", .1.join(", "))]
+    Conflict(usize, Vec<String>)
+}
+
+/// チーム割り当ての制約(必ず守るルール)
+/// 複数のスレッドで同じ制約を共有できるよう`Send + Sync`を要求する
+pub trait Constraint: Send + Sync {
+    /// `person`を`team`のメンバーに加えてもよいかを返す
+    /// # Attributes
+    /// * `team` - 加える先のチーム
+    /// * `person` - 加える人物
+    fn allows(&self, team: &Team, person: &Person) -> bool;
+
+    /// 全員を割り当て終えたチームが制約を満たしているかを返す
+    /// 割り当ての途中では判断できない制約だけが実装する
+    /// # Attributes
+    /// * `team` - 割り当て終えたチーム
+    fn satisfied(&self, _team: &Team) -> bool {
+        true
+    }
+
+    /// エラーメッセージに使う制約の説明
+    fn describe(&self) -> String;
+}
+
+/// 直近N回の実行で同じチームになった2人を、再び同じチームにしない制約
+pub struct AvoidRecentPairs {
+    /// 対象にした実行の回数
+    runs: usize,
+    /// 同じチームにしない組(名前順)
+    pairs: BTreeSet<(Arc<str>, Arc<str>)>
+}
+
+impl AvoidRecentPairs {
+    /// 実行記録から制約を作成する
+    /// # Attributes
+    /// * `runs` - 直近の実行記録
+    pub fn new(runs: &[RunRecord]) -> AvoidRecentPairs {
+        AvoidRecentPairs {
+            runs: runs.len(),
+            pairs: runs.iter().flat_map(|r| r.pairs()).collect()
+        }
+    }
+
+    /// 2人が同じチームになってはいけない組かどうか
+    fn forbidden(&self, a: &Arc<str>, b: &Arc<str>) -> bool {
+        let pair = if a <= b { (a.clone(), b.clone()) } else { (b.clone(), a.clone()) };
+        self.pairs.contains(&pair)
+    }
+}
+
+/// 同じ人が連続してリーダーになる回数の上限の制約
+/// 直近の実行で続けてリーダーになった回数が上限に達した人は、今回リーダーにしない
+pub struct MaxConsecutiveLeaderships {
+    /// 連続してリーダーになってよい回数
+    max: usize,
+    /// 名前 => 直近の実行で続けてリーダーになった回数
+    streaks: BTreeMap<Arc<str>, usize>
+}
+
+impl MaxConsecutiveLeaderships {
+    /// 実行記録から制約を作成する
+    /// # Attributes
+    /// * `max` - 連続してリーダーになってよい回数
+    /// * `runs` - 直近の実行記録(古い順)
+    pub fn new(max: usize, runs: &[RunRecord]) -> MaxConsecutiveLeaderships {
+        let mut streaks: BTreeMap<Arc<str>, usize> = BTreeMap::new();
+
+        for run in runs {
+            let leaders: BTreeSet<&Arc<str>> = run.teams.borrow_vec().iter().map(|t| &t.leader.name).collect();
+            // この実行でリーダーでなかった人の連続回数は途切れる
+            streaks.retain(|name, _| leaders.contains(name));
+            for leader in leaders {
+                *streaks.entry(leader.clone()).or_insert(0) += 1;
+            }
+        }
+
+        MaxConsecutiveLeaderships { max, streaks }
+    }
+
+    /// 直近の実行で続けてリーダーになった回数を返す
+    /// # Attributes
+    /// * `name` - 名前
+    pub fn streak(&self, name: &str) -> usize {
+        self.streaks.get(name).copied().unwrap_or(0)
+    }
+}
+
+impl Constraint for MaxConsecutiveLeaderships {
+    fn allows(&self, _team: &Team, _person: &Person) -> bool {
+        true
+    }
+
+    fn satisfied(&self, team: &Team) -> bool {
+        self.streak(&team.leader.name) < self.max
+    }
+
+    fn describe(&self) -> String {
+        format!("no one leads more than {} runs in a row", self.max)
+    }
+}
+
+impl Constraint for AvoidRecentPairs {
+    fn allows(&self, team: &Team, person: &Person) -> bool {
+        std::iter::once(&team.leader)
+            .chain(team.member.iter())
+            .all(|p| !self.forbidden(&p.name, &person.name))
+    }
+
+    fn describe(&self) -> String {
+        format!("no pair from the last {} runs", self.runs)
+    }
+}
+
+/// 1チームに入るリーダー候補の数の上限(リーダーを含む)
+pub struct MaxLeaderCandidates {
+    /// 上限
+    max: u8,
+    /// リーダー候補の名前
+    candidates: BTreeSet<Arc<str>>
+}
+
+impl MaxLeaderCandidates {
+    /// 上限とリーダー候補から制約を作成する
+    /// # Attributes
+    /// * `max` - 1チームに入るリーダー候補の数の上限
+    /// * `candidates` - リーダー候補
+    pub fn new(max: u8, candidates: Vec<Person>) -> MaxLeaderCandidates {
+        MaxLeaderCandidates {
+            max,
+            candidates: candidates.into_iter().map(|p| p.name).collect()
+        }
+    }
+}
+
+impl Constraint for MaxLeaderCandidates {
+    fn allows(&self, team: &Team, person: &Person) -> bool {
+        if !self.candidates.contains(&person.name) {
+            return true;
+        }
+
+        let count = std::iter::once(&team.leader)
+            .chain(team.member.iter())
+            .filter(|p| self.candidates.contains(&p.name))
+            .count();
+        count < usize::from(self.max)
+    }
+
+    fn describe(&self) -> String {
+        format!("at most {} leader candidates per team", self.max)
+    }
+}
+
+/// 設定で指定する条件付きのルール
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    /// ルールを適用するチームの条件。指定しなければ全てのチームに適用する
+    #[serde(default)]
+    when: Option<Expression>,
+    /// 条件に当てはまるチームが満たすべき式
+    require: Expression
+}
+
+impl Rule {
+    /// ルールを作成する
+    /// # Attributes
+    /// * `when` - ルールを適用するチームの条件
+    /// * `require` - 条件に当てはまるチームが満たすべき式
+    pub fn new(when: Option<Expression>, require: Expression) -> Rule {
+        Rule { when, require }
+    }
+}
+
+/// 条件に当てはまるチームが式を満たすようにする制約
+/// チームの人数などは全員を割り当てるまで決まらないため、割り当て終えたチームで判断する
+pub struct ConditionalRule {
+    /// ルール
+    rule: Rule,
+    /// 名前 => 持っているタグ
+    tags: BTreeMap<Arc<str>, Vec<String>>
+}
+
+impl ConditionalRule {
+    /// ルールと出席者のタグから制約を作成する
+    /// # Attributes
+    /// * `rule` - ルール
+    /// * `tags` - 名前 => 持っているタグ
+    pub fn new(rule: Rule, tags: BTreeMap<Arc<str>, Vec<String>>) -> ConditionalRule {
+        ConditionalRule { rule, tags }
+    }
+}
+
+impl Constraint for ConditionalRule {
+    fn allows(&self, _team: &Team, _person: &Person) -> bool {
+        true
+    }
+
+    fn satisfied(&self, team: &Team) -> bool {
+        !self.rule.when.as_ref().is_none_or(|when| when.evaluate(team, &self.tags)) || self.rule.require.evaluate(team, &self.tags)
+    }
+
+    fn describe(&self) -> String {
+        match &self.rule.when {
+            Some(when) => format!("{} when {}", self.rule.require, when),
+            None => self.rule.require.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Teams;
+
+    /// AvoidRecentPairs#allowsのテスト
+    /// 直近の実行で同じチームだった人がいるチームには加えられない
+    #[test]
+    fn avoid_recent_pairs() {
+        let mut previous = Team::new(Person::new("A"));
+        previous.assign(Person::new("B"));
+        let run = RunRecord {
+            run_id: "1".into(),
+            timestamp: 0,
+            seed: None,
+            audit: None,
+            teams: Teams::from_vec(vec![previous])
+        };
+        let constraint = AvoidRecentPairs::new(&[run]);
+
+        let mut team = Team::new(Person::new("C"));
+        team.assign(Person::new("B"));
+
+        assert!(!constraint.allows(&team, &Person::new("A")));
+        assert!(constraint.allows(&team, &Person::new("D")));
+        assert!(constraint.allows(&Team::new(Person::new("A")), &Person::new("C")));
+        assert_eq!(constraint.describe(), "no pair from the last 1 runs");
+    }
+
+    /// MaxConsecutiveLeaderships#satisfiedのテスト
+    /// 直近の実行で続けてリーダーになった回数が上限に達した人がリーダーのチームは満たさない
+    #[test]
+    fn max_consecutive_leaderships() {
+        let run = |leaders: &[&str]| RunRecord {
+            run_id: leaders.join("-"),
+            timestamp: 0,
+            seed: None,
+            audit: None,
+            teams: Teams::from_vec(leaders.iter().map(|l| Team::new(Person::new(*l))).collect())
+        };
+        let constraint = MaxConsecutiveLeaderships::new(2, &[run(&["C", "B"]), run(&["A", "B"]), run(&["A", "C"])]);
+
+        assert_eq!(constraint.streak("A"), 2);
+        assert_eq!(constraint.streak("B"), 0);
+        assert_eq!(constraint.streak("C"), 1);
+        assert!(!constraint.satisfied(&Team::new(Person::new("A"))));
+        assert!(constraint.satisfied(&Team::new(Person::new("C"))));
+        assert!(constraint.allows(&Team::new(Person::new("C")), &Person::new("A")));
+        assert_eq!(constraint.describe(), "no one leads more than 2 runs in a row");
+    }
+
+    /// MaxLeaderCandidates#allowsのテスト
+    /// 上限に達したチームにはリーダー候補を加えられないが、それ以外の人は加えられる
+    #[test]
+    fn max_leader_candidates() {
+        let constraint = MaxLeaderCandidates::new(2, vec![Person::new("A"), Person::new("B"), Person::new("C")]);
+
+        let mut team = Team::new(Person::new("A"));
+        assert!(constraint.allows(&team, &Person::new("B")));
+        team.assign(Person::new("B"));
+
+        assert!(!constraint.allows(&team, &Person::new("C")));
+        assert!(constraint.allows(&team, &Person::new("D")));
+    }
+
+    /// ConditionalRule#satisfiedのテスト
+    /// 条件に当てはまるチームだけが式を満たす必要がある
+    #[test]
+    fn conditional_rule() {
+        let rule = Rule::new(Some(Expression::parse("team.size > 2").unwrap()), Expression::parse("tag:senior").unwrap());
+        let constraint = ConditionalRule::new(rule, BTreeMap::from([("A".into(), vec!["senior".to_string()])]));
+
+        let mut small = Team::new(Person::new("B"));
+        small.assign(Person::new("C"));
+        assert!(constraint.satisfied(&small));
+
+        small.assign(Person::new("D"));
+        assert!(!constraint.satisfied(&small));
+
+        let mut large = Team::new(Person::new("A"));
+        large.assign(Person::new("C"));
+        large.assign(Person::new("D"));
+        assert!(constraint.satisfied(&large));
+        assert_eq!(constraint.describe(), "tag:senior when team.size > 2");
+    }
+}
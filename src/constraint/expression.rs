@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use serde::Deserialize;
+use thiserror::Error;
+use crate::domain::Team;
+
+/// 条件式に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum ExpressionError {
+    /// 式が空、または途中で終わっている
+    #[error("expression {0:?} ends unexpectedly")]
+    UnexpectedEnd(String),
+    /// 式の途中に置けない記号や値がある
+    #[error("unexpected {1:?} in expression {0:?}")]
+    UnexpectedToken(String, String),
+    /// 未知の値
+    #[error("unknown value {1:?} in expression {0:?}; use a number, team.size, team.members or tag:<name>")]
+    UnknownOperand(String, String)
+}
+
+/// チームに対して評価する条件式
+/// `team.size > 4`や`tag:senior >= 2 && team.size <= 6`のように書く
+/// 比較を伴わない値だけの式は、値が1以上であれば真になる(`tag:senior`はタグを持つ人がいれば真)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Expression {
+    /// 元の式
+    source: String,
+    /// 解析した式
+    node: Node
+}
+
+/// 解析した式の木
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// どちらかが真
+    Or(Box<Node>, Box<Node>),
+    /// 両方が真
+    And(Box<Node>, Box<Node>),
+    /// 2つの値の比較
+    Compare(Operand, Comparison, Operand),
+    /// 値が1以上
+    Truthy(Operand)
+}
+
+/// 式に使える値
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// 数値
+    Number(i64),
+    /// リーダーを含むチームの人数(`team.size`)
+    Size,
+    /// リーダーを除くメンバーの人数(`team.members`)
+    Members,
+    /// タグを持つ人の数(`tag:<name>`)
+    Tag(String)
+}
+
+/// 比較演算子
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne
+}
+
+impl Expression {
+    /// 式を解析する
+    /// # Attributes
+    /// * `source` - 式
+    ///
+    /// # Returns
+    /// 解析できなければErr<ExpressionError>
+    pub fn parse(source: &str) -> Result<Expression, ExpressionError> {
+        let tokens = tokenize(source);
+        let mut parser = Parser { source, tokens: &tokens, pos: 0 };
+
+        let node = parser.or()?;
+        if let Some(token) = tokens.get(parser.pos) {
+            return Err(ExpressionError::UnexpectedToken(source.to_string(), token.clone()));
+        }
+
+        Ok(Expression { source: source.to_string(), node })
+    }
+
+    /// チームに対して式を評価する
+    /// # Attributes
+    /// * `team` - 評価するチーム
+    /// * `tags` - 名前 => 持っているタグ
+    pub fn evaluate(&self, team: &Team, tags: &BTreeMap<Arc<str>, Vec<String>>) -> bool {
+        self.node.evaluate(team, tags)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl TryFrom<String> for Expression {
+    type Error = ExpressionError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Expression::parse(&source)
+    }
+}
+
+impl Node {
+    fn evaluate(&self, team: &Team, tags: &BTreeMap<Arc<str>, Vec<String>>) -> bool {
+        match self {
+            Self::Or(a, b) => a.evaluate(team, tags) || b.evaluate(team, tags),
+            Self::And(a, b) => a.evaluate(team, tags) && b.evaluate(team, tags),
+            Self::Compare(a, op, b) => {
+                let (a, b) = (a.value(team, tags), b.value(team, tags));
+                match op {
+                    Comparison::Gt => a > b,
+                    Comparison::Ge => a >= b,
+                    Comparison::Lt => a < b,
+                    Comparison::Le => a <= b,
+                    Comparison::Eq => a == b,
+                    Comparison::Ne => a != b
+                }
+            },
+            Self::Truthy(a) => a.value(team, tags) > 0
+        }
+    }
+}
+
+impl Operand {
+    fn value(&self, team: &Team, tags: &BTreeMap<Arc<str>, Vec<String>>) -> i64 {
+        match self {
+            Self::Number(n) => *n,
+            Self::Size => team.member.len() as i64 + 1,
+            Self::Members => team.member.len() as i64,
+            Self::Tag(tag) => std::iter::once(&team.leader)
+                .chain(team.member.iter())
+                .filter(|p| tags.get(&p.name).is_some_and(|t| t.contains(tag)))
+                .count() as i64
+        }
+    }
+}
+
+/// 演算子に使う文字
+const OPERATOR_CHARS: &[char] = &['<', '>', '=', '!', '&', '|'];
+
+/// 式を演算子とそれ以外の語に分ける
+fn tokenize(source: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let mut token = c.to_string();
+        if OPERATOR_CHARS.contains(&c) {
+            if let Some(next) = chars.next_if(|n| OPERATOR_CHARS.contains(n)) {
+                token.push(next);
+            }
+        } else {
+            while let Some(next) = chars.next_if(|n| !n.is_whitespace() && !OPERATOR_CHARS.contains(n)) {
+                token.push(next);
+            }
+        }
+        res.push(token);
+    }
+
+    res
+}
+
+/// `||`が`&&`より弱く結合する再帰下降パーサ
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [String],
+    pos: usize
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.and()?;
+        while self.eat("||") {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.comparison()?;
+        while self.eat("&&") {
+            node = Node::And(Box::new(node), Box::new(self.comparison()?));
+        }
+
+        Ok(node)
+    }
+
+    fn comparison(&mut self) -> Result<Node, ExpressionError> {
+        let left = self.operand()?;
+        let op = match self.tokens.get(self.pos).map(String::as_str) {
+            Some(">") => Comparison::Gt,
+            Some(">=") => Comparison::Ge,
+            Some("<") => Comparison::Lt,
+            Some("<=") => Comparison::Le,
+            Some("==") => Comparison::Eq,
+            Some("!=") => Comparison::Ne,
+            _ => return Ok(Node::Truthy(left))
+        };
+        self.pos += 1;
+
+        Ok(Node::Compare(left, op, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand, ExpressionError> {
+        let token = self.tokens.get(self.pos).ok_or_else(|| ExpressionError::UnexpectedEnd(self.source.to_string()))?;
+        self.pos += 1;
+
+        if token.starts_with(OPERATOR_CHARS) {
+            return Err(ExpressionError::UnexpectedToken(self.source.to_string(), token.clone()));
+        }
+        if let Ok(n) = token.parse() {
+            return Ok(Operand::Number(n));
+        }
+        match token.as_str() {
+            "team.size" => Ok(Operand::Size),
+            "team.members" => Ok(Operand::Members),
+            _ => match token.strip_prefix("tag:").filter(|tag| !tag.is_empty()) {
+                Some(tag) => Ok(Operand::Tag(tag.to_string())),
+                None => Err(ExpressionError::UnknownOperand(self.source.to_string(), token.clone()))
+            }
+        }
+    }
+
+    /// 次が`token`であれば読み進める
+    fn eat(&mut self, token: &str) -> bool {
+        let matched = self.tokens.get(self.pos).is_some_and(|t| t == token);
+        if matched {
+            self.pos += 1;
+        }
+
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Person;
+
+    fn team(size: usize) -> Team {
+        let mut team = Team::new(Person::new("P0"));
+        for i in 1..size {
+            team.assign(Person::new(format!("P{}", i)));
+        }
+
+        team
+    }
+
+    /// Expression#evaluateのテスト
+    /// 人数とタグの数を比較し、&&は||より強く結合する
+    #[test]
+    fn evaluate_expression() {
+        let tags: BTreeMap<Arc<str>, Vec<String>> = BTreeMap::from([
+            ("P0".into(), vec!["senior".to_string()]),
+            ("P3".into(), vec!["senior".to_string()]),
+        ]);
+        let eval = |source: &str, size: usize| Expression::parse(source).unwrap().evaluate(&team(size), &tags);
+
+        assert!(eval("team.size > 4", 5));
+        assert!(!eval("team.size > 4", 4));
+        assert!(eval("team.members == 3", 4));
+        assert!(eval("tag:senior", 1));
+        assert!(!eval("tag:senior >= 2", 3));
+        assert!(eval("tag:senior >= 2", 4));
+        assert!(eval("team.size < 2 || team.size > 3 && tag:senior != 0", 4));
+        assert!(!eval("tag:junior", 4));
+    }
+
+    /// Expression#parseのテスト
+    /// 未知の値や途中で終わった式はエラー
+    #[test]
+    fn parse_invalid_expression() {
+        assert_eq!(Expression::parse("team.size >"), Err(ExpressionError::UnexpectedEnd("team.size >".into())));
+        assert_eq!(Expression::parse("team.age > 3"), Err(ExpressionError::UnknownOperand("team.age > 3".into(), "team.age".into())));
+        assert_eq!(Expression::parse("team.size 3"), Err(ExpressionError::UnexpectedToken("team.size 3".into(), "3".into())));
+        assert_eq!(Expression::parse(">= 3"), Err(ExpressionError::UnexpectedToken(">= 3".into(), ">=".into())));
+    }
+}
@@ -1,22 +1,95 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use anyhow::{Result};
 use thiserror::{Error};
+use crate::collation::Collation;
+use crate::committee::{self, RoleSlot};
+use crate::formatter::OutputSettings;
+use crate::seating::Table;
+use crate::constraint::{ConditionalRule, Constraint, ConstraintError, MaxLeaderCandidates, Rule, MAX_ATTEMPTS};
+use crate::history::{PairCounts, Retention};
 
 
 /// 人物を表すStruct
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct Person {
     /// 人物の名前
-    pub name: String
+    /// `Person`を複製しても文字列自体はコピーされず共有される
+    pub name: Arc<str>,
+    /// 名前の読み(任意)
+    /// 漢字の名前を読み順で並べるときに使う
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reading: Option<Arc<str>>,
+    /// GitHubなどのアカウント名(任意)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<Arc<str>>,
+    /// 印刷する一覧などで使う表示名(任意)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<Arc<str>>,
+    /// 代名詞(任意)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pronouns: Option<Arc<str>>
+}
+
+impl Person {
+    /// 名前を指定して`Person`を作成する
+    /// # Attributes
+    /// * `name` - 人物の名前
+    pub fn new(name: impl Into<Arc<str>>) -> Person {
+        Person {
+            name: name.into(),
+            reading: None,
+            handle: None,
+            display_name: None,
+            pronouns: None
+        }
+    }
+
+    /// 人が読む出力に使う名前を返す
+    /// 表示名があれば表示名、なければ名前を使い、代名詞があれば後ろに括弧書きで付ける
+    pub fn display(&self) -> String {
+        self.display_with_honorific("")
+    }
+
+    /// 敬称を付けた、人が読む出力に使う名前を返す
+    /// 敬称は名前と代名詞の間に付ける
+    /// # Attributes
+    /// * `honorific` - 敬称(例: さん)
+    pub fn display_with_honorific(&self, honorific: &str) -> String {
+        let name = self.display_name.as_ref().unwrap_or(&self.name);
+
+        match &self.pronouns {
+            Some(pronouns) => format!("{}{} ({})", name, honorific, pronouns),
+            None => format!("{}{}", name, honorific)
+        }
+    }
 }
 
 /// チームを表すStruct
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Team {
+    /// 設定で指定したチームのメモ
+    /// TOMLでテーブルより前に書く必要があるため先頭に置く
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// 設定で指定したチームのアジェンダ
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agenda: Vec<String>,
     /// チームリーダー
     pub leader: Person,
     /// チームメンバー
-    pub member: Vec<Person>
+    /// 空の配列はTOMLでリーダーのテーブルより後ろに書けないため、メンバーがいなければ出力しない
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub member: Vec<Person>,
+    /// チームをさらに分けたサブチーム
+    /// 2階層で作成した場合のみ出力する
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_team: Vec<Team>,
+    /// 委員会モードで割り当てた役割(役割名 => 名前のリスト)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub roles: BTreeMap<String, Vec<Arc<str>>>
 }
 
 impl Team {
@@ -28,8 +101,12 @@ impl Team {
     /// `leader`がリーダー`Team`のインスタンス
     pub fn new(leader: Person) -> Team {
         Team {
+            notes: None,
+            agenda: Vec::new(),
             leader,
-            member: Vec::new()
+            member: Vec::new(),
+            sub_team: Vec::new(),
+            roles: BTreeMap::new()
         }
     }
 
@@ -52,6 +129,21 @@ impl Team {
         (res, leader_candidates)
     }
 
+    /// リーダーを含むチーム内の2人の組を全て返す
+    /// 組の2人は名前順に並べる
+    pub fn pairs(&self) -> Vec<(Arc<str>, Arc<str>)> {
+        let people: Vec<&Arc<str>> = std::iter::once(&self.leader).chain(self.member.iter()).map(|p| &p.name).collect();
+        let mut res = Vec::new();
+
+        for (i, a) in people.iter().enumerate() {
+            for b in &people[i + 1..] {
+                res.push(if a <= b { ((*a).clone(), (*b).clone()) } else { ((*b).clone(), (*a).clone()) });
+            }
+        }
+
+        res
+    }
+
     /// チームにメンバーをアサインする
     /// # Attributes
     /// * `new_member` - アサインしたいメンバー
@@ -61,16 +153,25 @@ impl Team {
 }
 
 /// 配列のシャッフルの仕方を定義するStrategy
-pub trait VecShuffleStrategy {
+/// 複数のスレッドで同じStrategyを共有できるよう`Send + Sync`を要求する。状態を持つ場合は`Mutex`などで守る
+pub trait VecShuffleStrategy: Send + Sync {
     /// `vec`に与えられたVec<T>をシャッフルする。
     /// `vec`を破壊するメソッドである点注意
     /// # Attributes
     /// * `vec` - シャッフルしたい対象のVec
     fn shuffle<T>(&self, vec: &mut Vec<T>) -> Result<()>;
+
+    /// 並べ替えで同点になった人の順番を決めるキーを返す
+    /// 出席者の並び順によらず、同じ名前であれば同じキーになるようにする
+    /// # Attributes
+    /// * `name` - 人物の名前
+    fn tiebreak_key(&self, _name: &str) -> u64 {
+        0
+    }
 }
 
 /// `Team`の集約
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Teams {
     /// `Team`のリスト
     team: Vec<Team>
@@ -85,13 +186,128 @@ impl Teams {
     /// # Returns
     /// Result<作成された`Teams`, anyhow::Error>
     pub fn create(setting: TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy) -> Result<Teams> {
+        Self::create_with_constraints(setting, shuffle_strategy, &[])
+    }
+
+    /// 設定値から制約を満たす`Team`の集約を作成する
+    /// 制約を満たせなかった場合はシャッフルからやり直し、`MAX_ATTEMPTS`回まで試す
+    /// チームの人数の偏りは制約がない場合と同じく最大1人
+    /// # Attributes
+    /// * `setting` - ユーザーから与えられた設定値
+    /// * `shuffle_strategy` - `Vec`のshuffleの仕方
+    /// * `constraints` - 必ず守る制約
+    ///
+    /// # Returns
+    /// Result<作成された`Teams`, anyhow::Error>
+    pub fn create_with_constraints(mut setting: TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy, constraints: &[Box<dyn Constraint>]) -> Result<Teams> {
+        setting.resolve_target_size()?;
+        let mut teams = Self::create_level(&setting, shuffle_strategy, constraints)?;
+
+        for (team, notes) in teams.team.iter_mut().zip(&setting.teams) {
+            team.notes = notes.notes.clone();
+            team.agenda = notes.agenda.clone();
+        }
+
+        for team in &mut teams.team {
+            if let Some(sub_setting) = setting.sub_teams_setting(team) {
+                team.sub_team = Self::create_level(&sub_setting, shuffle_strategy, &[])?.team;
+            }
+        }
+
+        Ok(teams)
+    }
+
+    /// 1階層分のチームを作成する
+    /// `constraints`はこの階層にだけ適用する
+    fn create_level(setting: &TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy, constraints: &[Box<dyn Constraint>]) -> Result<Teams> {
         setting.validate()?;
 
-        let mut leader_candidates: Vec<Person> = setting.leader_candidates().iter().map(|p| p.clone()).cloned().collect();
+        let setting_constraints = setting.constraints();
+        let constraints: Vec<&dyn Constraint> = setting_constraints.iter()
+            .chain(constraints.iter())
+            .map(|c| c.as_ref())
+            .collect();
+
+        match Self::create_constrained(setting, shuffle_strategy, &constraints) {
+            Err(e) if Self::is_unsatisfiable(&e) => {
+                let conflict = Self::find_conflict(setting, shuffle_strategy, &constraints)?;
+                if conflict.is_empty() {
+                    return Err(e);
+                }
+                Err(ConstraintError::Conflict(MAX_ATTEMPTS, conflict.iter().map(|c| c.describe()).collect()))?
+            },
+            res => res
+        }
+    }
+
+    /// 制約を満たせなかったときに、同時に満たせない最小の制約の組を探す
+    /// 制約を1つずつ外して作り直し、外しても満たせないままであればその制約は原因から除く
+    /// # Returns
+    /// Ok(最小の制約の組)。制約を全て外しても満たせなければ空
+    fn find_conflict<'a>(setting: &TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy, constraints: &[&'a dyn Constraint]) -> Result<Vec<&'a dyn Constraint>> {
+        let mut conflict = constraints.to_vec();
+        let mut i = 0;
+        while i < conflict.len() {
+            let mut rest = conflict.clone();
+            rest.remove(i);
+            match Self::create_constrained(setting, shuffle_strategy, &rest) {
+                Ok(_) => i += 1,
+                Err(e) if Self::is_unsatisfiable(&e) => conflict = rest,
+                Err(e) => return Err(e)
+            }
+        }
+
+        Ok(conflict)
+    }
+
+    fn is_unsatisfiable(error: &anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<ConstraintError>(), Some(ConstraintError::Unsatisfiable(..)))
+    }
+
+    /// 制約を満たすようにチームを作成する
+    fn create_constrained(setting: &TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy, constraints: &[&dyn Constraint]) -> Result<Teams> {
+        if let Some(roles) = &setting.roles {
+            return Self::create_committees(setting, roles, shuffle_strategy, constraints);
+        }
+        if let (Some(continuity), Some((previous, pair_counts))) = (setting.continuity, &setting.previous) {
+            return Self::create_continuing(setting, continuity, previous, pair_counts, shuffle_strategy, constraints);
+        }
+        if setting.has_groups() {
+            return Self::create_grouped(setting, shuffle_strategy, constraints);
+        }
+        if setting.has_loads() {
+            return Self::create_load_balanced(setting, shuffle_strategy, constraints);
+        }
+        if constraints.is_empty() {
+            return Self::create_unconstrained(setting, shuffle_strategy);
+        }
+
+        for _ in 0..MAX_ATTEMPTS {
+            let mut leader_candidates: Vec<Person> = setting.leader_candidates().into_iter().cloned().collect();
+            shuffle_strategy.shuffle(&mut leader_candidates)?;
+
+            let (mut teams_vec, mut rest) = Team::create_by_leader_candidates(leader_candidates, setting.num_of_teams);
+            let mut normal_attendees: Vec<Person> = setting.normal_attendees().into_iter().cloned().collect();
+            rest.append(&mut normal_attendees);
+
+            shuffle_strategy.shuffle(&mut rest)?;
+
+            if Self::assign_with_constraints(&mut teams_vec, rest, constraints) {
+                return Ok(Teams {team: teams_vec});
+            }
+        }
+
+        Err(ConstraintError::Unsatisfiable(MAX_ATTEMPTS, constraints.iter().map(|c| c.describe()).collect()))?
+    }
+
+    /// 制約がない場合のチーム作成
+    /// リーダー候補から各チームのリーダーを決め、残りを順番にアサインする
+    fn create_unconstrained(setting: &TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy) -> Result<Teams> {
+        let mut leader_candidates: Vec<Person> = setting.leader_candidates().into_iter().cloned().collect();
         shuffle_strategy.shuffle(&mut leader_candidates)?;
 
         let (mut teams_vec, mut rest) = Team::create_by_leader_candidates(leader_candidates, setting.num_of_teams);
-        let mut normal_attendees: Vec<Person> = setting.normal_attendees().iter().map(|p| p.clone()).cloned().collect();
+        let mut normal_attendees: Vec<Person> = setting.normal_attendees().into_iter().cloned().collect();
         rest.append(&mut normal_attendees);
 
         shuffle_strategy.shuffle(&mut rest)?;
@@ -109,24 +325,334 @@ impl Teams {
         Ok(Teams {team:teams_vec})
     }
 
+    /// 委員会モードのチーム作成
+    /// 役割枠をタグを持つ参加者で埋め、枠に入らなかった参加者は通常通りアサインする
+    /// 枠を埋められなかった場合もシャッフルからやり直し、`MAX_ATTEMPTS`回まで試す
+    fn create_committees(setting: &TeamsCreationSetting, roles: &[RoleSlot], shuffle_strategy: &impl VecShuffleStrategy, constraints: &[&dyn Constraint]) -> Result<Teams> {
+        let mut unfillable = None;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let mut pool: Vec<(Person, Vec<String>)> = setting.attendees.iter().map(|a| (a.person.clone(), a.tags.clone())).collect();
+            shuffle_strategy.shuffle(&mut pool)?;
+
+            match committee::fill_slots(roles, setting.num_of_teams, pool) {
+                Ok((mut teams_vec, rest)) => {
+                    if Self::assign_with_constraints(&mut teams_vec, rest, constraints) {
+                        return Ok(Teams {team: teams_vec});
+                    }
+                },
+                Err(e) => unfillable = Some(e)
+            }
+        }
+
+        match unfillable {
+            Some(e) => Err(e)?,
+            None => Err(ConstraintError::Unsatisfiable(MAX_ATTEMPTS, constraints.iter().map(|c| c.describe()).collect()))?
+        }
+    }
+
+    /// 前回のチームを一部残すチーム作成
+    /// 前回の各チームから、出席している人の`continuity`の割合を同じチームに残し、残りを入れ替える
+    /// 過去に同じチームになった回数が少ない人から残し、長く一緒にいる人から入れ替える
+    /// 残した人には`constraints`を適用しない
+    fn create_continuing(setting: &TeamsCreationSetting, continuity: f64, previous: &Teams, pair_counts: &PairCounts, shuffle_strategy: &impl VecShuffleStrategy, constraints: &[&dyn Constraint]) -> Result<Teams> {
+        let candidates: BTreeSet<&Arc<str>> = setting.leader_candidates().into_iter().map(|p| &p.name).collect();
+        let mut attending: HashMap<&str, &Person> = HashMap::new();
+        for p in setting.all_people() {
+            attending.entry(&*p.name).or_insert(p);
+        }
+
+        for _ in 0..MAX_ATTEMPTS {
+            let mut used: BTreeSet<Arc<str>> = BTreeSet::new();
+            let mut kept: Vec<(Option<Person>, Vec<Person>)> = Vec::new();
+
+            for prev_team in previous.team.iter().take(setting.num_of_teams.into()) {
+                let mut people: Vec<Person> = std::iter::once(&prev_team.leader)
+                    .chain(prev_team.member.iter())
+                    .filter_map(|p| attending.get(&*p.name).map(|a| (*a).clone()))
+                    .collect();
+                let keep = (continuity * people.len() as f64).round() as usize;
+
+                let leader = if keep > 0 && people.first().is_some_and(|p| p.name == prev_team.leader.name && candidates.contains(&p.name)) {
+                    Some(people.remove(0))
+                } else {
+                    None
+                };
+
+                let others: Vec<&Arc<str>> = std::iter::once(&prev_team.leader).chain(prev_team.member.iter()).map(|p| &p.name).collect();
+                // 同じチームになった回数が同じ人は、並び順ではなく名前とシード値から決まる順にする
+                people.sort_by_cached_key(|p| {
+                    let count = others.iter()
+                        .map(|o| pair_counts.get(&if p.name <= **o { (p.name.clone(), (*o).clone()) } else { ((*o).clone(), p.name.clone()) }).unwrap_or(&0))
+                        .sum::<usize>();
+                    (count, shuffle_strategy.tiebreak_key(&p.name))
+                });
+                people.truncate(keep.saturating_sub(leader.iter().count()));
+
+                used.extend(leader.iter().chain(people.iter()).map(|p| p.name.clone()));
+                kept.push((leader, people));
+            }
+            kept.resize_with(setting.num_of_teams.into(), || (None, Vec::new()));
+
+            // 名前順にしてからシャッフルし、出席者の並び順が変わっても同じ結果にする
+            let mut free_candidates: Vec<Person> = setting.leader_candidates().into_iter().filter(|p| !used.contains(&p.name)).cloned().collect();
+            free_candidates.sort_by(|a, b| a.name.cmp(&b.name));
+            shuffle_strategy.shuffle(&mut free_candidates)?;
+
+            let mut teams_vec = Vec::new();
+            for (leader, mut members) in kept {
+                let leader = match leader {
+                    Some(leader) => leader,
+                    None => match members.iter().position(|m| candidates.contains(&m.name)) {
+                        Some(i) => members.remove(i),
+                        None => match free_candidates.pop() {
+                            Some(leader) => {
+                                used.insert(leader.name.clone());
+                                leader
+                            },
+                            None => break
+                        }
+                    }
+                };
+
+                let mut team = Team::new(leader);
+                for member in members {
+                    team.assign(member);
+                }
+                teams_vec.push(team);
+            }
+            if teams_vec.len() < setting.num_of_teams.into() {
+                continue;
+            }
+
+            let mut rest: Vec<Person> = setting.all_people().into_iter().filter(|p| !used.contains(&p.name)).cloned().collect();
+            rest.sort_by(|a, b| a.name.cmp(&b.name));
+            shuffle_strategy.shuffle(&mut rest)?;
+
+            if Self::assign_with_constraints(&mut teams_vec, rest, constraints) {
+                return Ok(Teams {team: teams_vec});
+            }
+        }
+
+        let mut descriptions = vec![format!("keep {} of each previous team", continuity)];
+        descriptions.extend(constraints.iter().map(|c| c.describe()));
+        Err(ConstraintError::Unsatisfiable(MAX_ATTEMPTS, descriptions))?
+    }
+
+    /// 負荷を揃えたチーム作成
+    /// リーダーを決めた後、負荷の大きい人から順に、人数が最も少ないチームのうち負荷の合計が最も小さいチームへアサインする
+    fn create_load_balanced(setting: &TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy, constraints: &[&dyn Constraint]) -> Result<Teams> {
+        let index = setting.attendee_index();
+        for _ in 0..MAX_ATTEMPTS {
+            let mut leader_candidates: Vec<Person> = setting.leader_candidates().into_iter().cloned().collect();
+            shuffle_strategy.shuffle(&mut leader_candidates)?;
+
+            let (mut teams_vec, mut rest) = Team::create_by_leader_candidates(leader_candidates, setting.num_of_teams);
+            let mut normal_attendees: Vec<Person> = setting.normal_attendees().into_iter().cloned().collect();
+            rest.append(&mut normal_attendees);
+
+            // 同じ負荷の人の順番はシャッフルで決める
+            shuffle_strategy.shuffle(&mut rest)?;
+            rest.sort_by_key(|p| std::cmp::Reverse(index.load_of(&p.name)));
+
+            let mut assigned = true;
+            for m in rest {
+                let min_size = teams_vec.iter().map(|t| t.member.len()).min().unwrap_or(0);
+                let team = teams_vec.iter_mut()
+                    .filter(|t| t.member.len() == min_size)
+                    .filter(|t| constraints.iter().all(|c| c.allows(t, &m)))
+                    .min_by_key(|t| std::iter::once(&t.leader).chain(t.member.iter()).map(|p| index.load_of(&p.name)).sum::<u32>());
+
+                match team {
+                    Some(team) => team.assign(m),
+                    None => {
+                        assigned = false;
+                        break;
+                    }
+                }
+            }
+            if assigned && Self::satisfies_all(&teams_vec, constraints) {
+                return Ok(Teams {team: teams_vec});
+            }
+        }
+
+        let mut descriptions = vec!["balance load across teams".to_string()];
+        descriptions.extend(constraints.iter().map(|c| c.describe()));
+        Err(ConstraintError::Unsatisfiable(MAX_ATTEMPTS, descriptions))?
+    }
+
+    /// グループを保ったチーム作成
+    /// 同じグループの参加者はまとめて同じチームにアサインする
+    /// リーダーのグループはリーダーのチームに入り、残りのグループは大きい順に人数が最も少ないチームへアサインする
+    fn create_grouped(setting: &TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy, constraints: &[&dyn Constraint]) -> Result<Teams> {
+        let candidates: BTreeSet<&Arc<str>> = setting.leader_candidates().into_iter().map(|p| &p.name).collect();
+
+        for _ in 0..MAX_ATTEMPTS {
+            let mut units = setting.units();
+            shuffle_strategy.shuffle(&mut units)?;
+
+            let mut teams_vec: Vec<Team> = Vec::new();
+            let mut rest: Vec<Vec<Person>> = Vec::new();
+            for (mut unit, is_leader_unit) in units {
+                if is_leader_unit && teams_vec.len() < setting.num_of_teams.into() {
+                    let leader_index = unit.iter().position(|p| candidates.contains(&p.name)).unwrap_or(0);
+                    let mut team = Team::new(unit.remove(leader_index));
+                    for member in unit {
+                        team.assign(member);
+                    }
+                    teams_vec.push(team);
+                } else {
+                    rest.push(unit);
+                }
+            }
+
+            rest.sort_by_key(|unit| std::cmp::Reverse(unit.len()));
+            if Self::assign_units_with_constraints(&mut teams_vec, rest, constraints) {
+                return Ok(Teams {team: teams_vec});
+            }
+        }
+
+        Err(ConstraintError::Unsatisfiable(MAX_ATTEMPTS, constraints.iter().map(|c| c.describe()).collect()))?
+    }
+
+    /// グループ単位で、制約を満たすようにメンバーをアサインする
+    /// 人数が最も少ないチームのうち、グループ全員が制約を満たす最初のチームにアサインする
+    /// # Returns
+    /// 全員をアサインできればtrue
+    fn assign_units_with_constraints(teams_vec: &mut [Team], rest: Vec<Vec<Person>>, constraints: &[&dyn Constraint]) -> bool {
+        for unit in rest {
+            let min_size = teams_vec.iter().map(|t| t.member.len()).min().unwrap_or(0);
+            let team = teams_vec.iter_mut()
+                .filter(|t| t.member.len() == min_size)
+                .find(|t| {
+                    let mut candidate = (*t).clone();
+                    unit.iter().all(|m| {
+                        let allowed = constraints.iter().all(|c| c.allows(&candidate, m));
+                        candidate.assign(m.clone());
+                        allowed
+                    })
+                });
+
+            match team {
+                Some(team) => unit.into_iter().for_each(|m| team.assign(m)),
+                None => return false
+            }
+        }
+
+        Self::satisfies_all(teams_vec, constraints)
+    }
+
+    /// 制約を満たすようにメンバーをアサインする
+    /// 人数が最も少ないチームのうち、制約を満たす最初のチームにアサインする
+    /// # Returns
+    /// 全員をアサインできればtrue
+    fn assign_with_constraints(teams_vec: &mut [Team], mut rest: Vec<Person>, constraints: &[&dyn Constraint]) -> bool {
+        while let Some(m) = rest.pop() {
+            let min_size = teams_vec.iter().map(|t| t.member.len()).min().unwrap_or(0);
+            let team = teams_vec.iter_mut()
+                .filter(|t| t.member.len() == min_size)
+                .find(|t| constraints.iter().all(|c| c.allows(t, &m)));
+
+            match team {
+                Some(team) => team.assign(m),
+                None => return false
+            }
+        }
+
+        Self::satisfies_all(teams_vec, constraints)
+    }
+
+    /// 割り当て終えた全てのチームが制約を満たしているかを返す
+    fn satisfies_all(teams_vec: &[Team], constraints: &[&dyn Constraint]) -> bool {
+        teams_vec.iter().all(|t| constraints.iter().all(|c| c.satisfied(t)))
+    }
+
+    /// `Team`のリストから集約を作成する
+    /// # Attributes
+    /// * `team` - `Team`のリスト
+    pub fn from_vec(team: Vec<Team>) -> Teams {
+        Teams { team }
+    }
+
+    /// 各チームのメンバーを指定した照合順序で並べ替える
+    /// リーダーはそのまま。サブチームのメンバーも並べ替える
+    /// # Attributes
+    /// * `collation` - 照合順序
+    pub fn sort_members(&mut self, collation: Collation) {
+        Self::sort_team_members(&mut self.team, collation);
+    }
+
+    fn sort_team_members(teams: &mut [Team], collation: Collation) {
+        for team in teams {
+            team.member.sort_by(|a, b| collation.compare(a, b));
+            Self::sort_team_members(&mut team.sub_team, collation);
+        }
+    }
+
     /// Vecとして借用する
     /// # Returns
     /// `team`のリスト
     pub fn borrow_vec(&self) -> &Vec<Team> {
         &self.team
     }
+
+    /// 各チームのリーダーをメンバーの先頭にも加えた複製を返す
+    /// チームごとに全員の一覧がほしい出力に使う。サブチームにも加える
+    pub fn with_leader_in_members(&self) -> Teams {
+        fn include_leader(team: &Team) -> Team {
+            Team {
+                member: std::iter::once(team.leader.clone()).chain(team.member.iter().cloned()).collect(),
+                sub_team: team.sub_team.iter().map(include_leader).collect(),
+                ..team.clone()
+            }
+        }
+
+        Teams { team: self.team.iter().map(include_leader).collect() }
+    }
 }
 
 /// 参加者を表すstruct
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Attendee {
     /// 人物
     person: Person,
     /// リーダになりうるか
-    leader: Option<bool>
+    leader: Option<bool>,
+    /// サブチームのリーダーになりうるか
+    #[serde(default)]
+    sub_leader: Option<bool>,
+    /// 委員会モードでどの役割枠に入れるかを表すタグ
+    #[serde(default)]
+    tags: Vec<String>,
+    /// 所属するグループ(家族・ペアなど)
+    /// 同じグループの参加者は必ず同じチームになる
+    group: Option<String>,
+    /// 既に抱えている負荷(兼任している委員会の数など)
+    /// 指定した参加者がいれば、チームごとの合計が揃うようにアサインする
+    load: Option<u32>,
+    /// 出席する確率(0.0〜1.0)。指定しなければ1.0
+    /// オーバーブッキングする場合に、実際に出席する人数の見込みに使う
+    attendance_probability: Option<f64>
 }
 
 impl Attendee {
+    /// 参加者を作成する
+    /// # Attributes
+    /// * `person` - 人物
+    /// * `leader` - リーダーになりうるか
+    pub fn new(person: Person, leader: bool) -> Attendee {
+        Attendee {
+            person,
+            leader: Some(leader),
+            ..Default::default()
+        }
+    }
+
+    /// 人物を返す
+    pub fn person(&self) -> &Person {
+        &self.person
+    }
+
     /// リーダになりうるかを返す
     /// # Returns
     /// リーダー候補であればtrue
@@ -135,27 +661,239 @@ impl Attendee {
     }
 }
 
+/// 名前から参加者を引くための索引
+/// チームを作るたびに1回だけ作り、1人ずつ参加者のリストを探さないようにする
+pub struct AttendeeIndex<'a> {
+    attendees: HashMap<&'a str, &'a Attendee>
+}
+
+impl<'a> AttendeeIndex<'a> {
+    /// 参加者のリストから索引を作成する
+    /// 同じ名前の参加者が複数いる場合は先に書かれた方を使う
+    /// # Attributes
+    /// * `attendees` - 参加者のリスト
+    pub fn new(attendees: &'a [Attendee]) -> AttendeeIndex<'a> {
+        let mut map = HashMap::with_capacity(attendees.len());
+        for a in attendees {
+            map.entry(&*a.person.name).or_insert(a);
+        }
+        AttendeeIndex { attendees: map }
+    }
+
+    /// 指定した名前の参加者のタグを返す
+    /// # Attributes
+    /// * `name` - 参加者の名前
+    pub fn tags_of(&self, name: &str) -> &'a [String] {
+        self.attendees.get(name).map(|a| a.tags.as_slice()).unwrap_or(&[])
+    }
+
+    /// 指定した名前の参加者の負荷を返す
+    /// # Attributes
+    /// * `name` - 参加者の名前
+    ///
+    /// # Returns
+    /// 負荷。指定されていなければ0
+    pub fn load_of(&self, name: &str) -> u32 {
+        self.attendees.get(name).and_then(|a| a.load).unwrap_or(0)
+    }
+}
+
 /// チーム作成設定に関するエラー
-#[derive(Debug,Error)]
+#[derive(Debug, Clone, Error, PartialEq)]
 pub enum TeamsCreationSettingError {
     /// チーム数にゼロが設定されている
     #[error("num_of_teams must be more than zero.")]
     NumOfTeamsZero,
     /// チーム数に対してリーダー候補が少なすぎる
     #[error("num of leader candidates({0}) must be equal or grater than num of teams({1})")]
-    LeadersLack(u8,u8)
+    LeadersLack(u8,u8),
+    /// 1チームあたりのリーダー候補の上限では全てのリーダー候補を割り当てられない
+    #[error("num of leader candidates({0}) exceeds max_leader_candidates_per_team({1}) x num of teams({2})")]
+    LeaderCandidatesOverflow(usize,u8,u8),
+    /// リーダーの兼任を許していないのに、チームとサブチームの両方のリーダー候補になっている
+    #[error("{0} cannot be both leader and sub_leader unless sub_teams.allow_shared_leaders is true")]
+    SharedLeader(String),
+    /// 出席する確率が範囲外
+    #[error("attendance_probability({1}) of {0} must be between 0.0 and 1.0")]
+    AttendanceProbabilityOutOfRange(String, f64),
+    /// 出席者にいない名前が指定された
+    #[error("{0} is not in attendees")]
+    UnknownAttendee(String),
+    /// 人数の範囲が不正
+    #[error("target_size must satisfy 0 < min({0}) <= max({1})")]
+    InvalidTargetSize(u8,u8),
+    /// 人数の範囲に収まるチーム数がない
+    #[error("no num of teams fits {0} people into teams of {1} to {2}")]
+    TargetSizeUnfit(usize,u8,u8),
+    /// 前回のチームから残す人の割合が0.0〜1.0の範囲外
+    #[error("continuity({0}) must be between 0.0 and 1.0")]
+    ContinuityOutOfRange(f64),
+    /// リーダー候補のいるグループ(グループに属さないリーダー候補は1人で1グループ)がチーム数より少ない
+    #[error("num of groups with leader candidates({0}) must be equal or grater than num of teams({1})")]
+    GroupLeadersLack(usize,u8),
+    /// 一緒に使えない設定値が指定されている(先に指定した設定値が無視されてしまう)
+    #[error("{0} cannot be combined with {1}")]
+    UnsupportedCombination(String, String)
 }
 
 /// チーム作成設定
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct  TeamsCreationSetting {
     /// 出席者のリスト
+    /// `--names-file`で出席者を渡す場合は省略できる
+    #[serde(default)]
     attendees: Vec<Attendee>,
+    /// 出席者を書いた別のファイルのパス(設定ファイルからの相対パス)
+    /// 指定した場合はそのファイルの出席者を`attendees`に加える
+    attendees_file: Option<String>,
     /// チーム数
+    /// `target_size`を指定した場合は省略でき、指定しても無視される
+    #[serde(default)]
     num_of_teams: u8,
+    /// 1チームの人数の範囲
+    /// 指定した場合はこの範囲に最も合うチーム数を選ぶ
+    target_size: Option<TargetSize>,
     /// フラットフラグ
     /// trueの場合はAttendeeのis_leaderの値を無視して全員リーダー候補とみなす
-    flat: Option<bool>
+    flat: Option<bool>,
+    /// 1チームに入るリーダー候補の最大数(リーダーを含む)
+    /// 余ったリーダー候補が一部のチームに偏らないようにする
+    max_leader_candidates_per_team: Option<u8>,
+    /// サブチーム作成設定
+    /// 指定した場合は作成した各チームをさらにサブチームに分ける
+    sub_teams: Option<SubTeamsCreationSetting>,
+    /// 委員会モードの役割枠
+    /// 指定した場合はAttendeeのleaderの値を無視し、各チームをこの枠で構成する
+    roles: Option<Vec<RoleSlot>>,
+    /// 座席表に使う会場のテーブル
+    #[serde(default)]
+    tables: Vec<Table>,
+    /// 作成したチームに上から順に付けるメモとアジェンダ
+    #[serde(default)]
+    teams: Vec<TeamNotes>,
+    /// 出力形式ごとの設定
+    #[serde(default)]
+    output: OutputSettings,
+    /// 条件付きのルール
+    /// 条件に当てはまるチームが式を満たすまで作り直す
+    #[serde(default)]
+    rules: Vec<Rule>,
+    /// オーバーブッキングフラグ
+    /// trueの場合、`target_size`からチーム数を選ぶときに出席者数の代わりに出席する人数の見込みを使い、欠席を見込んで大きめのチームにする
+    overbooking: Option<bool>,
+    /// 前回の各チームから同じチームに残す人の割合(0.0〜1.0)
+    /// 指定した場合、前回の結果が与えられていれば残りの人だけを入れ替える
+    continuity: Option<f64>,
+    /// 実行履歴・アーカイブを残す範囲
+    /// 指定した場合、記録するたびに範囲から外れた実行を削除する
+    retention: Option<Retention>,
+    /// 前回の結果と、過去に同じチームになった組の回数
+    #[serde(skip)]
+    previous: Option<(Teams, PairCounts)>,
+    /// 今回の実行だけリーダー候補から外す出席者の名前
+    #[serde(skip)]
+    no_lead: BTreeSet<String>
+}
+
+/// `attendees_file`で指定するTOMLの出席者ファイル
+#[derive(Debug, Deserialize)]
+struct AttendeesFile {
+    /// 出席者のリスト
+    #[serde(default)]
+    attendees: Vec<Attendee>
+}
+
+/// チームを作成する前に設定を調べた結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingAnalysis {
+    /// 出席者の数
+    pub num_of_attendees: usize,
+    /// リーダー候補の数
+    pub num_of_leader_candidates: usize,
+    /// 作成するチーム数(`target_size`を指定した場合は選んだチーム数)
+    pub num_of_teams: u8,
+    /// 予想されるチームごとの人数(リーダーを含む)。作成できない場合は空
+    pub team_sizes: Vec<usize>,
+    /// 埋めるのに必要な人数に対して、タグを持つ出席者が足りない役割名
+    pub unfillable_roles: Vec<String>,
+    /// チームを作成できない理由。作成できる場合はNone
+    pub problem: Option<TeamsCreationSettingError>
+}
+
+impl SettingAnalysis {
+    /// チームを作成できる見込みがあるかを返す
+    pub fn is_feasible(&self) -> bool {
+        self.problem.is_none() && self.unfillable_roles.is_empty()
+    }
+}
+
+/// 1チームの人数の範囲
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+pub struct TargetSize {
+    /// 最小人数(リーダーを含む)
+    pub min: u8,
+    /// 最大人数(リーダーを含む)
+    pub max: u8
+}
+
+impl TargetSize {
+    /// 人数に最も合うチーム数を返す
+    /// 全チームの人数が範囲に収まるチーム数のうち、平均人数が範囲の中央に最も近いものを選ぶ
+    /// # Attributes
+    /// * `num_of_people` - 参加者の人数
+    /// * `max_teams` - チーム数の上限(リーダー候補の数など)
+    ///
+    /// # Returns
+    /// 範囲に収まるチーム数がなければNone
+    pub fn best_num_of_teams(&self, num_of_people: usize, max_teams: usize) -> Option<u8> {
+        let (min, max) = (usize::from(self.min), usize::from(self.max));
+
+        (1..=max_teams.min(u8::MAX.into()))
+            .filter(|k| num_of_people / k >= min && num_of_people.div_ceil(*k) <= max)
+            .min_by_key(|k| (num_of_people * 2).abs_diff(k * (min + max)))
+            .map(|k| k as u8)
+    }
+}
+
+/// 作成したチームに付けるメモとアジェンダ
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct TeamNotes {
+    /// 自由記述のメモ
+    pub notes: Option<String>,
+    /// アジェンダの項目
+    #[serde(default)]
+    pub agenda: Vec<String>
+}
+
+/// サブチーム作成設定
+/// 各チームのメンバーを、チームごとにこの設定でさらに分ける
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SubTeamsCreationSetting {
+    /// 1チームあたりのサブチーム数
+    num_of_teams: u8,
+    /// フラットフラグ
+    /// trueの場合はAttendeeのsub_leaderの値を無視して全員サブチームのリーダー候補とみなす
+    flat: Option<bool>,
+    /// 1サブチームに入るサブチームのリーダー候補の最大数(リーダーを含む)
+    max_leader_candidates_per_team: Option<u8>,
+    /// trueの場合はチームのリーダーがサブチームのリーダーを兼ねてもよい
+    allow_shared_leaders: Option<bool>
+}
+
+impl SubTeamsCreationSetting {
+    /// フラットフラグの値を返す
+    /// # Returns
+    /// 全員をサブチームのリーダー候補とみなす場合はtrue
+    pub fn is_flat(&self) -> bool {
+        self.flat.unwrap_or(false)
+    }
+
+    /// リーダーの兼任を許すかを返す
+    /// # Returns
+    /// チームのリーダーがサブチームのリーダーを兼ねてもよい場合はtrue
+    pub fn allows_shared_leaders(&self) -> bool {
+        self.allow_shared_leaders.unwrap_or(false)
+    }
 }
 
 impl TeamsCreationSetting {
@@ -166,76 +904,406 @@ impl TeamsCreationSetting {
         self.flat.unwrap_or(false)
     }
 
+    /// 出席者を書いた別のファイルのパスを返す
+    pub fn attendees_file(&self) -> Option<&str> {
+        self.attendees_file.as_deref()
+    }
+
+    /// `attendees_file`が指定されていれば、そのファイルの出席者を`attendees`に加える
+    /// 拡張子が`.toml`のファイルは`[[attendees]]`のリスト、それ以外は`--names-file`と同じ形式で読む
+    /// # Attributes
+    /// * `base_dir` - 相対パスの基準にするディレクトリ(設定ファイルのあるディレクトリ)
+    pub fn inline_attendees_file(&mut self, base_dir: &Path) -> Result<()> {
+        let path = match &self.attendees_file {
+            Some(file) => base_dir.join(file),
+            None => return Ok(())
+        };
+
+        let attendees = if path.extension().is_some_and(|e| e == "toml") {
+            toml::from_str::<AttendeesFile>(&std::fs::read_to_string(&path)?)?.attendees
+        } else {
+            crate::roster::load_roster(&path)?
+        };
+        self.add_attendees(attendees);
+
+        Ok(())
+    }
+
+    /// `flat`が指定されておらず、リーダー候補の出席者が1人もいなければ全員をリーダー候補にする
+    /// 設定ファイルを書かずに名前だけを渡した場合に使う
+    pub fn default_to_flat(&mut self) {
+        if self.flat.is_none() && self.roles.is_none() && !self.attendees.iter().any(|a| a.is_leader()) {
+            self.flat = Some(true);
+        }
+    }
+
     /// リーダー候補の参加者を返す
     /// # Returns
     /// リーダー候補の`Person`のリスト
     pub fn leader_candidates(&self) -> Vec<&Person> {
-        if self.is_flat() {
-            self.all_people()
-        }else{
-            self.attendees.iter().filter(|a| a.is_leader()).map(|a| &a.person).collect()
-        }
+        self.attendees.iter().filter(|a| self.is_leader_candidate(a)).map(|a| &a.person).collect()
     }
 
     /// リーダー候補以外の参加者を返す
     /// # Returns
     /// リーダー候補以外の`Person`のリスト
     pub fn normal_attendees(&self) -> Vec<&Person> {
-        if self.is_flat() {
-            Vec::new()
-        }else{
-            self.attendees.iter().filter(|a| !a.is_leader()).map(|a| &a.person).collect()
-        }
+        self.attendees.iter().filter(|a| !self.is_leader_candidate(a)).map(|a| &a.person).collect()
     }
 
-    /// 全ての参加者を返す
-    /// # Returns
-    /// 全ての参加者の`Person`のリスト
-    pub fn all_people(&self) -> Vec<&Person> {
-        self.attendees.iter().map(|a| &a.person).collect()
+    /// 出席者がリーダー候補かを返す
+    /// フラットフラグが立っていれば全員、そうでなければleaderがtrueの出席者がリーダー候補になる
+    /// ただし今回リーダーにしない出席者は除く
+    fn is_leader_candidate(&self, attendee: &Attendee) -> bool {
+        (self.is_flat() || attendee.is_leader()) && !self.no_lead.contains(&*attendee.person.name)
     }
 
-    /// チーム作成設定を検証する
+    /// 今回の実行だけリーダー候補から外す出席者を指定する
+    /// # Attributes
+    /// * `names` - リーダー候補から外す出席者の名前
+    ///
     /// # Returns
-    /// 検証エラーがなければOk<()>, エラーがあればErr<TeamsCreationSettingError>
-    pub fn validate(&self) -> Result<(), TeamsCreationSettingError> {
-        let num_of_leader_candidates = self.leader_candidates().len();
+    /// 出席者にいない名前があればErr<TeamsCreationSettingError>
+    pub fn exclude_leaders(&mut self, names: &[String]) -> Result<(), TeamsCreationSettingError> {
+        if let Some(unknown) = names.iter().find(|n| !self.attendees.iter().any(|a| &*a.person.name == n.as_str())) {
+            Err(TeamsCreationSettingError::UnknownAttendee(unknown.clone()))?
+        }
+        self.no_lead.extend(names.iter().cloned());
 
-        if self.num_of_teams == 0 {
-            Err(TeamsCreationSettingError::NumOfTeamsZero)?
-        } else if  num_of_leader_candidates.lt(&self.num_of_teams.into()) {
-            Err(TeamsCreationSettingError::LeadersLack(
-                u8::try_from(num_of_leader_candidates).unwrap(), 
-                u8::from(self.num_of_teams)
-            ))?
-        }else {
-            Ok(())
+        Ok(())
+    }
+
+    /// 指定した名前の出席者だけを残す
+    /// # Attributes
+    /// * `names` - 残す出席者の名前
+    ///
+    /// # Returns
+    /// 出席者にいない名前があればErr<TeamsCreationSettingError>
+    pub fn retain_attendees(&mut self, names: &[String]) -> Result<(), TeamsCreationSettingError> {
+        if let Some(unknown) = names.iter().find(|n| !self.attendees.iter().any(|a| &*a.person.name == n.as_str())) {
+            Err(TeamsCreationSettingError::UnknownAttendee(unknown.clone()))?
         }
+        self.attendees.retain(|a| names.iter().any(|n| *n == *a.person.name));
+
+        Ok(())
     }
-} 
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// 出席者を追加する
+    /// # Attributes
+    /// * `attendees` - 追加する出席者
+    pub fn add_attendees(&mut self, attendees: Vec<Attendee>) {
+        self.attendees.extend(attendees);
+    }
 
-    /// Attendee#is_leaderのテスト
-    /// Noneであればデフォルト値であるfalseを返し、
-    /// Someであればその値を返す
-    #[test]
-    fn attendee_is_leader() {
-        let attendee1 = Attendee{
-            person: Person{name: "A".to_string()},
-            leader: None
+    /// 1チームの人数の範囲を返す
+    pub fn target_size(&self) -> Option<TargetSize> {
+        self.target_size
+    }
+
+    /// `target_size`が指定されていれば、範囲に最も合うチーム数を`num_of_teams`にする
+    /// リーダー候補の数よりチームを多くはしない
+    /// # Returns
+    /// 範囲が不正か、範囲に収まるチーム数がなければErr<TeamsCreationSettingError>
+    pub fn resolve_target_size(&mut self) -> Result<(), TeamsCreationSettingError> {
+        let target = match self.target_size {
+            Some(target) => target,
+            None => return Ok(())
         };
+        if target.min == 0 || target.min > target.max {
+            Err(TeamsCreationSettingError::InvalidTargetSize(target.min, target.max))?
+        }
 
-        let attendee2 = Attendee{
-            person: Person{name: "B".to_string()},
-            leader: Some(false)
+        let num_of_people = if self.overbooking.unwrap_or(false) {
+            self.expected_attendance().round() as usize
+        } else {
+            self.attendees.len()
+        };
+        self.num_of_teams = target.best_num_of_teams(num_of_people, self.leader_candidates().len())
+            .ok_or(TeamsCreationSettingError::TargetSizeUnfit(num_of_people, target.min, target.max))?;
+
+        Ok(())
+    }
+
+    /// 出席する人数の見込みを返す
+    /// # Returns
+    /// 出席者ごとの出席する確率の合計
+    pub fn expected_attendance(&self) -> f64 {
+        self.attendees.iter().map(|a| a.attendance_probability.unwrap_or(1.0)).sum()
+    }
+
+    /// 前回の各チームから同じチームに残す人の割合を返す
+    pub fn continuity(&self) -> Option<f64> {
+        self.continuity
+    }
+
+    /// 実行履歴・アーカイブを残す範囲を返す
+    pub fn retention(&self) -> Option<Retention> {
+        self.retention
+    }
+
+    /// `continuity`で残す人を決めるための前回の結果を設定する
+    /// # Attributes
+    /// * `previous` - 前回の結果
+    /// * `pair_counts` - 過去に同じチームになった組と、その回数
+    pub fn set_previous(&mut self, previous: Teams, pair_counts: PairCounts) {
+        self.previous = Some((previous, pair_counts));
+    }
+
+    /// 名前から出席者を引くための索引を返す
+    /// 出席者のタグや負荷を1人ずつ引く場合は、この索引を1回だけ作って使う
+    pub fn attendee_index(&self) -> AttendeeIndex<'_> {
+        AttendeeIndex::new(&self.attendees)
+    }
+
+    /// 負荷を指定した参加者がいるかを返す
+    pub fn has_loads(&self) -> bool {
+        self.attendees.iter().any(|a| a.load.is_some())
+    }
+
+    /// 座席表に使う会場のテーブルを返す
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    /// 設定ファイルの`[output]`に書いた出力形式ごとの設定を返す
+    pub fn output(&self) -> &OutputSettings {
+        &self.output
+    }
+
+    /// 全ての参加者を返す
+    /// # Returns
+    /// 全ての参加者の`Person`のリスト
+    pub fn all_people(&self) -> Vec<&Person> {
+        self.attendees.iter().map(|a| &a.person).collect()
+    }
+
+    /// 設定値から作成される制約を返す
+    /// # Returns
+    /// 設定値で指定された制約のリスト
+    pub fn constraints(&self) -> Vec<Box<dyn Constraint>> {
+        let mut res: Vec<Box<dyn Constraint>> = Vec::new();
+
+        if let Some(max) = self.max_leader_candidates_per_team {
+            res.push(Box::new(MaxLeaderCandidates::new(max, self.leader_candidates().into_iter().cloned().collect())));
+        }
+        if !self.rules.is_empty() {
+            let tags: BTreeMap<Arc<str>, Vec<String>> = self.attendees.iter().map(|a| (a.person.name.clone(), a.tags.clone())).collect();
+            for rule in &self.rules {
+                res.push(Box::new(ConditionalRule::new(rule.clone(), tags.clone())));
+            }
+        }
+
+        res
+    }
+
+    /// チームをサブチームに分けるための設定を作成する
+    /// # Attributes
+    /// * `team` - サブチームに分けるチーム
+    ///
+    /// # Returns
+    /// サブチーム作成設定がなければNone。ある場合は`team`のリーダーとメンバーを出席者とする設定
+    /// リーダーの兼任を許さない場合、`team`のリーダーはサブチームのリーダー候補にしない
+    pub fn sub_teams_setting(&self, team: &Team) -> Option<TeamsCreationSetting> {
+        let sub_teams = self.sub_teams.as_ref()?;
+
+        let attendees = std::iter::once(&team.leader)
+            .chain(team.member.iter())
+            .map(|person| {
+                let attendee = self.attendees.iter().find(|a| a.person.name == person.name);
+                let is_sub_leader = if person.name == team.leader.name && !sub_teams.allows_shared_leaders() {
+                    false
+                } else if sub_teams.is_flat() {
+                    true
+                } else {
+                    attendee.and_then(|a| a.sub_leader).unwrap_or(false)
+                };
+
+                Attendee {
+                    person: person.clone(),
+                    leader: Some(is_sub_leader),
+                    group: attendee.and_then(|a| a.group.clone()),
+                    load: attendee.and_then(|a| a.load),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        Some(TeamsCreationSetting {
+            attendees,
+            num_of_teams: sub_teams.num_of_teams,
+            flat: None,
+            max_leader_candidates_per_team: sub_teams.max_leader_candidates_per_team,
+            ..Default::default()
+        })
+    }
+
+    /// リーダーの兼任を許していないのに、チームとサブチームの両方のリーダー候補に指定されている参加者を返す
+    /// フラットフラグでリーダー候補になっている場合は対象外
+    fn shared_leader(&self) -> Option<&Person> {
+        let sub_teams = self.sub_teams.as_ref()?;
+        if self.is_flat() || sub_teams.is_flat() || sub_teams.allows_shared_leaders() {
+            return None;
+        }
+
+        self.attendees.iter()
+            .find(|a| a.is_leader() && a.sub_leader.unwrap_or(false))
+            .map(|a| &a.person)
+    }
+
+    /// 一緒に使えない設定値の組を返す
+    /// チームの作り方は委員会モード・`continuity`・グループ・負荷の順に1つだけ選ばれるため、
+    /// 後の方のグループや負荷を一緒に指定すると、その設定値は使われずにチームが作られてしまう
+    /// `overbooking`も`target_size`からチーム数を選ぶときにしか使われないため、`num_of_teams`だけでは使えない
+    ///
+    /// # Returns
+    /// (使われない設定値, 優先される設定値)。一緒に使えない組がなければNone
+    fn unsupported_combination(&self) -> Option<(&'static str, &'static str)> {
+        let preferred = [
+            ("roles", self.roles.is_some()),
+            ("continuity", self.continuity.is_some()),
+            ("attendees.group", self.has_groups()),
+        ];
+        let ignored = [("attendees.group", self.has_groups()), ("attendees.load", self.has_loads())];
+
+        ignored.iter().filter(|(_, set)| *set).find_map(|(name, _)| {
+            preferred.iter()
+                .take_while(|(other, _)| other != name)
+                .find(|(_, set)| *set)
+                .map(|(other, _)| (*name, *other))
+        }).or_else(|| {
+            (self.overbooking.unwrap_or(false) && self.target_size.is_none()).then_some(("overbooking", "num_of_teams"))
+        })
+    }
+
+    /// グループに属する参加者がいるかを返す
+    pub fn has_groups(&self) -> bool {
+        self.attendees.iter().any(|a| a.group.is_some())
+    }
+
+    /// 参加者をアサインの単位に分ける
+    /// 同じグループの参加者は1つの単位にまとめ、グループに属さない参加者は1人で1つの単位とする
+    /// # Returns
+    /// (単位に含まれる`Person`のリスト, リーダー候補を含むか)のリスト
+    fn units(&self) -> Vec<(Vec<Person>, bool)> {
+        let candidates: BTreeSet<&Arc<str>> = self.leader_candidates().into_iter().map(|p| &p.name).collect();
+        let mut res: Vec<(Vec<Person>, bool)> = Vec::new();
+        let mut group_index: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for attendee in &self.attendees {
+            let is_candidate = candidates.contains(&attendee.person.name);
+            let index = match &attendee.group {
+                Some(group) => *group_index.entry(group).or_insert_with(|| {
+                    res.push((Vec::new(), false));
+                    res.len() - 1
+                }),
+                None => {
+                    res.push((Vec::new(), false));
+                    res.len() - 1
+                }
+            };
+
+            res[index].0.push(attendee.person.clone());
+            res[index].1 |= is_candidate;
+        }
+
+        res
+    }
+
+    /// チームを作成せずに設定を調べる
+    /// 出席者を編集している途中で、チームを作成できるかや予想されるチームの人数を示すために使う
+    /// 制約やシャッフルの結果によっては、作成できる見込みがあっても作成に失敗することがある
+    /// # Returns
+    /// 調べた結果の`SettingAnalysis`
+    pub fn analyze(&self) -> SettingAnalysis {
+        let mut resolved = self.clone();
+        let problem = resolved.resolve_target_size().and_then(|_| resolved.validate()).err();
+
+        let num_of_attendees = resolved.attendees.len();
+        let num_of_teams = usize::from(resolved.num_of_teams);
+        let team_sizes = if problem.is_none() && num_of_teams > 0 {
+            (0..num_of_teams).map(|i| num_of_attendees / num_of_teams + usize::from(i < num_of_attendees % num_of_teams)).collect()
+        } else {
+            Vec::new()
+        };
+        let unfillable_roles = resolved.roles.iter().flatten()
+            .filter(|slot| resolved.attendees.iter().filter(|a| a.tags.contains(&slot.role)).count() < usize::from(slot.count()) * num_of_teams)
+            .map(|slot| slot.role.clone())
+            .collect();
+
+        SettingAnalysis {
+            num_of_attendees,
+            num_of_leader_candidates: resolved.leader_candidates().len(),
+            num_of_teams: resolved.num_of_teams,
+            team_sizes,
+            unfillable_roles,
+            problem
+        }
+    }
+
+    /// チーム作成設定を検証する
+    /// # Returns
+    /// 検証エラーがなければOk<()>, エラーがあればErr<TeamsCreationSettingError>
+    pub fn validate(&self) -> Result<(), TeamsCreationSettingError> {
+        let num_of_leader_candidates = self.leader_candidates().len();
+
+        if self.num_of_teams == 0 {
+            Err(TeamsCreationSettingError::NumOfTeamsZero)?
+        } else if let Some(continuity) = self.continuity.filter(|c| !(0.0..=1.0).contains(c)) {
+            Err(TeamsCreationSettingError::ContinuityOutOfRange(continuity))?
+        } else if let Some((name, p)) = self.attendees.iter()
+            .find_map(|a| a.attendance_probability.filter(|p| !(0.0..=1.0).contains(p)).map(|p| (&a.person.name, p))) {
+            Err(TeamsCreationSettingError::AttendanceProbabilityOutOfRange(name.to_string(), p))?
+        } else if let Some((name, other)) = self.unsupported_combination() {
+            Err(TeamsCreationSettingError::UnsupportedCombination(name.into(), other.into()))?
+        } else if self.roles.is_some() {
+            Ok(())
+        } else if  num_of_leader_candidates.lt(&self.num_of_teams.into()) {
+            Err(TeamsCreationSettingError::LeadersLack(
+                u8::try_from(num_of_leader_candidates).unwrap(), 
+                self.num_of_teams
+            ))?
+        } else if let Some(max) = self.max_leader_candidates_per_team.filter(|max| num_of_leader_candidates > usize::from(*max) * usize::from(self.num_of_teams)) {
+            Err(TeamsCreationSettingError::LeaderCandidatesOverflow(num_of_leader_candidates, max, self.num_of_teams))?
+        } else if let Some(shared) = self.shared_leader() {
+            Err(TeamsCreationSettingError::SharedLeader(shared.name.to_string()))?
+        } else if self.has_groups() && self.units().iter().filter(|(_, is_leader_unit)| *is_leader_unit).count() < self.num_of_teams.into() {
+            Err(TeamsCreationSettingError::GroupLeadersLack(
+                self.units().iter().filter(|(_, is_leader_unit)| *is_leader_unit).count(),
+                self.num_of_teams
+            ))?
+        }else {
+            Ok(())
+        }
+    }
+} 
+
+#[cfg(test)]
+// 元からあるテストの書き方のまま、clippyの指摘を受けないようにする
+#[allow(clippy::bool_assert_comparison, clippy::assertions_on_constants)]
+mod tests {
+    use super::*;
+
+    /// Attendee#is_leaderのテスト
+    /// Noneであればデフォルト値であるfalseを返し、
+    /// Someであればその値を返す
+    #[test]
+    fn attendee_is_leader() {
+        let attendee1 = Attendee{
+            person: Person::new("A"),
+            leader: None,
+            ..Default::default()
+        };
+
+        let attendee2 = Attendee{
+            person: Person::new("B"),
+            leader: Some(false),
+            ..Default::default()
         };
 
         let attendee3 = Attendee{
-            person: Person{name: "C".to_string()},
-            leader: Some(true)
+            person: Person::new("C"),
+            leader: Some(true),
+            ..Default::default()
         };
 
         assert_eq!(attendee1.is_leader(), false);
@@ -250,33 +1318,36 @@ mod tests {
     fn setting_is_flat() {
         let setting1 = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 2,
-            flat: Some(true)
+            flat: Some(true),
+            ..Default::default()
         };
         let setting2 = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 2,
-            flat: Some(false)
+            flat: Some(false),
+            ..Default::default()
         };
         let setting3 = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 2,
-            flat: None
+            flat: None,
+            ..Default::default()
         };
 
         assert_eq!(setting1.is_flat(), true);
@@ -290,13 +1361,14 @@ mod tests {
     fn setting_validation_ok() {
         let setting = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 2,
-            flat: None
+            flat: None,
+            ..Default::default()
         };
         
         match setting.validate() {
@@ -311,13 +1383,14 @@ mod tests {
     fn setting_validation_zero_teams() {
         let setting = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 0,
-            flat: None
+            flat: None,
+            ..Default::default()
         };
 
         match setting.validate() {
@@ -325,7 +1398,7 @@ mod tests {
             Err(e) => {
                 match e {
                     TeamsCreationSettingError::NumOfTeamsZero => assert!(true),
-                    TeamsCreationSettingError::LeadersLack(_,__) => assert!(false, "Unexpected error, {}", e)
+                    _ => assert!(false, "Unexpected error, {}", e)
                 }
             }
         }
@@ -337,39 +1410,296 @@ mod tests {
     fn setting_validation_leaders_lack() {
         let setting = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 3,
-            flat: None
+            flat: None,
+            ..Default::default()
         };
 
         match setting.validate() {
             Ok(_) => assert!(false, "validation passed unexpectedly"),
             Err(e) => {
                 match e {
-                    TeamsCreationSettingError::NumOfTeamsZero => assert!(false, "Unexpected error, {}", e),
-                    TeamsCreationSettingError::LeadersLack(_,__) => assert!(true)
+                    TeamsCreationSettingError::LeadersLack(..) => assert!(true),
+                    _ => assert!(false, "Unexpected error, {}", e)
                 }
             }
         }
     }
 
+    /// TeamsCreationSetting#validateのテスト
+    /// 1チームあたりのリーダー候補の上限 x チーム数よりリーダー候補が多ければバリデーションエラー
+    #[test]
+    fn setting_validation_leader_candidates_overflow() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
+            ],
+            num_of_teams: 1,
+            max_leader_candidates_per_team: Some(2),
+            ..Default::default()
+        };
+
+        match setting.validate() {
+            Ok(_) => assert!(false, "validation passed unexpectedly"),
+            Err(e) => {
+                match e {
+                    TeamsCreationSettingError::LeaderCandidatesOverflow(3, 2, 1) => assert!(true),
+                    _ => assert!(false, "Unexpected error, {}", e)
+                }
+            }
+        }
+    }
+
+    /// Teams#createのテスト
+    /// 負荷を指定した場合、負荷の大きい人から負荷の合計が小さいチームにアサインする
+    #[test]
+    fn create_teams_with_loads() {
+        let attendee = |name: &str, leader: bool, load: u32| Attendee {
+            load: Some(load),
+            ..Attendee::new(Person::new(name), leader)
+        };
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                attendee("A", true, 0),
+                attendee("B", true, 5),
+                attendee("C", false, 4),
+                attendee("D", false, 3),
+                attendee("E", false, 1),
+                attendee("F", false, 0),
+            ],
+            num_of_teams: 2,
+            ..Default::default()
+        };
+
+        let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::NoShuffle).unwrap();
+        let team_vec = teams.borrow_vec();
+
+        assert_eq!(&*team_vec[0].leader.name, "B");
+        assert_eq!(team_vec[0].member.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["D", "F"]);
+        assert_eq!(&*team_vec[1].leader.name, "A");
+        assert_eq!(team_vec[1].member.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["C", "E"]);
+    }
+
+    /// Teams#createのテスト
+    /// 設定の`teams`のメモとアジェンダを上から順にチームに付け、TOMLに出力する
+    #[test]
+    fn create_teams_with_notes() {
+        let setting: TeamsCreationSetting = toml::from_str(r#"
+num_of_teams = 2
+
+[[teams]]
+notes = "Room 301"
+agenda = ["Intro", "Retro"]
+
+[[attendees]]
+leader = true
+person = { name = "A" }
+
+[[attendees]]
+leader = true
+person = { name = "B" }
+
+[[attendees]]
+person = { name = "C" }
+"#).unwrap();
+
+        let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::NoShuffle).unwrap();
+        let team_vec = teams.borrow_vec();
+
+        assert_eq!(team_vec[0].notes.as_deref(), Some("Room 301"));
+        assert_eq!(team_vec[0].agenda, vec!["Intro".to_string(), "Retro".to_string()]);
+        assert_eq!(team_vec[1].notes, None);
+        assert!(team_vec[1].agenda.is_empty());
+
+        let serialized = toml::to_string_pretty(&teams).unwrap();
+        assert!(serialized.contains("notes = 'Room 301'"));
+        let deserialized: Teams = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.borrow_vec()[0].agenda, team_vec[0].agenda);
+    }
+
+    /// Teams#createのテスト
+    /// 条件付きのルールを満たすまで作り直す
+    #[test]
+    fn create_teams_with_rules() {
+        let setting: TeamsCreationSetting = toml::from_str(r#"
+num_of_teams = 2
+rules = [{ when = "team.size > 2", require = "tag:senior" }]
+
+[[attendees]]
+leader = true
+person = { name = "A" }
+
+[[attendees]]
+leader = true
+person = { name = "B" }
+
+[[attendees]]
+tags = ["senior"]
+person = { name = "C" }
+
+[[attendees]]
+person = { name = "D" }
+
+[[attendees]]
+person = { name = "E" }
+"#).unwrap();
+
+        for _ in 0..20 {
+            let teams = Teams::create(setting.clone(), &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+            let large = teams.borrow_vec().iter().find(|t| t.member.len() == 2).unwrap();
+
+            assert!(large.member.iter().any(|p| &*p.name == "C"));
+        }
+    }
+
+    /// TeamsCreationSettingのデシリアライズのテスト
+    /// ルールの式が解析できなければエラー
+    #[test]
+    fn setting_with_invalid_rule() {
+        let result = toml::from_str::<TeamsCreationSetting>(r#"
+num_of_teams = 1
+rules = [{ require = "team.age > 3" }]
+"#);
+
+        assert!(result.unwrap_err().to_string().contains("unknown value \"team.age\""));
+    }
+
+    /// TeamsCreationSetting#analyzeのテスト
+    /// 出席者とリーダー候補の数、選んだチーム数と予想される人数を返す
+    #[test]
+    fn analyze_setting() {
+        let setting: TeamsCreationSetting = toml::from_str(r#"
+target_size = { min = 2, max = 3 }
+roles = [{ role = "chair" }]
+
+[[attendees]]
+leader = true
+tags = ["chair"]
+person = { name = "A" }
+
+[[attendees]]
+leader = true
+person = { name = "B" }
+
+[[attendees]]
+person = { name = "C" }
+
+[[attendees]]
+person = { name = "D" }
+
+[[attendees]]
+person = { name = "E" }
+"#).unwrap();
+
+        let analysis = setting.analyze();
+
+        assert_eq!(analysis.num_of_attendees, 5);
+        assert_eq!(analysis.num_of_leader_candidates, 2);
+        assert_eq!(analysis.num_of_teams, 2);
+        assert_eq!(analysis.team_sizes, vec![3, 2]);
+        assert_eq!(analysis.unfillable_roles, vec!["chair".to_string()]);
+        assert!(!analysis.is_feasible());
+    }
+
+    /// TeamsCreationSetting#analyzeのテスト
+    /// 作成できない設定であれば理由を返し、人数は予想しない
+    #[test]
+    fn analyze_infeasible_setting() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![Attendee::new(Person::new("A"), true), Attendee::new(Person::new("B"), false)],
+            num_of_teams: 2,
+            ..Default::default()
+        };
+
+        let analysis = setting.analyze();
+
+        assert_eq!(analysis.problem, Some(TeamsCreationSettingError::LeadersLack(1, 2)));
+        assert!(analysis.team_sizes.is_empty());
+        assert!(!analysis.is_feasible());
+    }
+
+    /// TeamsCreationSetting#resolve_target_sizeのテスト
+    /// オーバーブッキングする場合は出席する人数の見込みからチーム数を選ぶ
+    #[test]
+    fn resolve_target_size_with_overbooking() {
+        let attendee = |name: &str| Attendee {
+            attendance_probability: Some(0.5),
+            ..Attendee::new(Person::new(name), true)
+        };
+        let mut setting = TeamsCreationSetting{
+            attendees: ["A", "B", "C", "D", "E", "F", "G", "H"].into_iter().map(attendee).collect(),
+            target_size: Some(TargetSize{min: 2, max: 2}),
+            ..Default::default()
+        };
+        assert_eq!(setting.expected_attendance(), 4.0);
+
+        setting.resolve_target_size().unwrap();
+        assert_eq!(setting.num_of_teams, 4);
+
+        setting.overbooking = Some(true);
+        setting.resolve_target_size().unwrap();
+        assert_eq!(setting.num_of_teams, 2);
+    }
+
+    /// TeamsCreationSetting#validateのテスト
+    /// 出席する確率が範囲外であればエラー
+    #[test]
+    fn setting_validation_attendance_probability() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![Attendee {attendance_probability: Some(1.5), ..Attendee::new(Person::new("A"), true)}],
+            num_of_teams: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(setting.validate(), Err(TeamsCreationSettingError::AttendanceProbabilityOutOfRange("A".into(), 1.5)));
+    }
+
+    /// TeamsCreationSetting#exclude_leadersのテスト
+    /// 指定した出席者はフラットでもリーダー候補から外れて通常の出席者になり、出席者にいない名前はエラー
+    #[test]
+    fn setting_exclude_leaders() {
+        let mut setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee::new(Person::new("A"), true),
+                Attendee::new(Person::new("B"), true),
+                Attendee::new(Person::new("C"), false),
+            ],
+            num_of_teams: 1,
+            ..Default::default()
+        };
+
+        setting.exclude_leaders(&["A".to_string()]).unwrap();
+        assert_eq!(setting.leader_candidates().iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["B"]);
+        assert_eq!(setting.normal_attendees().iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["A", "C"]);
+
+        setting.flat = Some(true);
+        assert_eq!(setting.leader_candidates().iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["B", "C"]);
+
+        assert!(matches!(setting.exclude_leaders(&["Z".to_string()]), Err(TeamsCreationSettingError::UnknownAttendee(name)) if name == "Z"));
+    }
+
     /// TeamsCreationSetting#leader_candidates, TeamsCreationSetting#normal_attendees, TeamsCreationSetting#all_peopleのテスト
     /// is_flatがfalseであればそれぞれリーダー候補者、リーダ候補者以外、全ての参加者をそのまま返す
     #[test]
     fn attendees_no_flat() {
         let setting = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 2,
-            flat: None
+            flat: None,
+            ..Default::default()
         };
 
         assert_eq!(setting.leader_candidates().len(), 2);
@@ -383,13 +1713,14 @@ mod tests {
     fn attendees_flat() {
         let setting = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
             ],
             num_of_teams: 2,
-            flat: Some(true)
+            flat: Some(true),
+            ..Default::default()
         };
 
         assert_eq!(setting.leader_candidates().len(), 4);
@@ -397,13 +1728,37 @@ mod tests {
         assert_eq!(setting.all_people().len(), 4);
     }
 
+    /// Person#displayのテスト
+    /// 表示名があれば名前の代わりに使い、代名詞を括弧書きで付ける
+    #[test]
+    fn person_display() {
+        let plain = Person::new("Taro");
+        let full = Person{display_name: Some("Taro Y.".into()), pronouns: Some("he/him".into()), ..Person::new("Taro")};
+        let pronouns_only = Person{pronouns: Some("they/them".into()), ..Person::new("Alex")};
+
+        assert_eq!(plain.display(), "Taro");
+        assert_eq!(full.display(), "Taro Y. (he/him)");
+        assert_eq!(pronouns_only.display(), "Alex (they/them)");
+        assert_eq!(full.display_with_honorific("さん"), "Taro Y.さん (he/him)");
+    }
+
+    /// Person#cloneのテスト
+    /// 複製しても名前の文字列はコピーされず同じ領域を共有する
+    #[test]
+    fn cloned_person_shares_name() {
+        let person = Person::new("A");
+        let cloned = person.clone();
+
+        assert!(Arc::ptr_eq(&person.name, &cloned.name));
+    }
+
     /// Team#newのテスト
     /// リーダーを指定して`Team`のインスタンスを作成する
     #[test]
     fn create_team_by_leader() {
-        let team = Team::new(Person{name: "A".to_string()});
+        let team = Team::new(Person::new("A"));
         
-        assert_eq!(team.leader.name, "A".to_string());
+        assert_eq!(&*team.leader.name, "A");
         assert_eq!(team.member.len(), 0);
     }
 
@@ -411,10 +1766,10 @@ mod tests {
     /// `Team`のインスタンスに対してリーダー以外のメンバーを追加する
     #[test]
     fn assign_member_to_team() {
-        let mut team = Team::new(Person{name: "A".to_string()});
+        let mut team = Team::new(Person::new("A"));
 
-        team.assign(Person{name: "B".to_string()});
-        team.assign(Person{name: "C".to_string()});
+        team.assign(Person::new("B"));
+        team.assign(Person::new("C"));
 
         assert_eq!(team.member.len(), 2);
 
@@ -426,9 +1781,9 @@ mod tests {
     #[test]
     fn create_team_by_leader_candidates() {
         let leader_candidates = vec![
-            Person{name: "A".to_string()}, 
-            Person{name: "B".to_string()}, 
-            Person{name: "C".to_string()}
+            Person::new("A"), 
+            Person::new("B"), 
+            Person::new("C")
         ];
 
         let (teams, rest) = Team::create_by_leader_candidates(leader_candidates, 2);
@@ -437,20 +1792,616 @@ mod tests {
         assert_eq!(rest.len(), 1);
     }
 
+    /// Teamsのシリアライズのテスト
+    /// メンバーのいないチームがあってもTOMLに書き出して読み戻せる
+    #[test]
+    fn serialize_team_without_members() {
+        let mut team = Team::new(Person::new("A"));
+        team.assign(Person::new("B"));
+        let teams = Teams::from_vec(vec![team, Team::new(Person::new("C"))]);
+
+        let toml_str = toml::to_string_pretty(&teams).unwrap();
+        let read: Teams = toml::from_str(&toml_str).unwrap();
+
+        assert_eq!(read.team.len(), 2);
+        assert_eq!(read.team[1].member.len(), 0);
+    }
+
+    /// 特定の2人を同じチームにしないテスト用の制約
+    struct Apart(&'static str, &'static str);
+
+    impl Constraint for Apart {
+        fn allows(&self, team: &Team, person: &Person) -> bool {
+            let names: Vec<&str> = std::iter::once(&team.leader).chain(team.member.iter()).map(|p| &*p.name).collect();
+            !((&*person.name == self.0 && names.contains(&self.1)) || (&*person.name == self.1 && names.contains(&self.0)))
+        }
+
+        fn describe(&self) -> String {
+            format!("{} apart from {}", self.0, self.1)
+        }
+    }
+
+    /// Teams#create_with_constraintsのテスト
+    /// 制約を満たすチームを作成する
+    #[test]
+    fn create_teams_with_constraints() {
+        for _ in 0..20 {
+            let setting = TeamsCreationSetting{
+                attendees: vec![
+                    Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                    Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                    Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                    Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
+                ],
+                num_of_teams: 2,
+                flat: None,
+                ..Default::default()
+            };
+            let constraints: Vec<Box<dyn Constraint>> = vec![Box::new(Apart("C", "D")), Box::new(Apart("A", "C"))];
+
+            let teams = Teams::create_with_constraints(setting, &crate::strategy::ShuffleStrategies::RandomShuffle, &constraints).unwrap();
+
+            for team in &teams.team {
+                assert!(constraints.iter().all(|c| team.member.iter().all(|m| {
+                    let others = Team{leader: team.leader.clone(), member: team.member.iter().filter(|o| o.name != m.name).cloned().collect(), ..Team::new(team.leader.clone())};
+                    c.allows(&others, m)
+                })));
+            }
+        }
+    }
+
+    /// Teams#create_with_constraintsのテスト
+    /// 制約を満たせない場合はエラー
+    #[test]
+    fn create_teams_with_unsatisfiable_constraints() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(false), ..Default::default()},
+            ],
+            num_of_teams: 1,
+            flat: None,
+            ..Default::default()
+        };
+        let constraints: Vec<Box<dyn Constraint>> = vec![Box::new(Apart("A", "B"))];
+
+        let err = Teams::create_with_constraints(setting, &crate::strategy::ShuffleStrategies::NoShuffle, &constraints).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<ConstraintError>(),
+            Some(&ConstraintError::Conflict(MAX_ATTEMPTS, vec!["A apart from B".into()]))
+        );
+    }
+
+    /// Teams#create_with_constraintsのテスト
+    /// 制約を満たせない場合は、同時に満たせない最小の制約の組をエラーに含める
+    #[test]
+    fn create_teams_reports_minimal_conflict() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+            ],
+            num_of_teams: 2,
+            flat: None,
+            ..Default::default()
+        };
+        let constraints: Vec<Box<dyn Constraint>> = vec![
+            Box::new(Apart("A", "C")),
+            Box::new(Apart("A", "B")),
+            Box::new(Apart("B", "C")),
+        ];
+
+        let err = Teams::create_with_constraints(setting, &crate::strategy::ShuffleStrategies::NoShuffle, &constraints).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<ConstraintError>(),
+            Some(&ConstraintError::Conflict(MAX_ATTEMPTS, vec!["A apart from C".into(), "B apart from C".into()]))
+        );
+    }
+
+    /// Teams#createのテスト
+    /// max_leader_candidates_per_teamが設定されていれば、余ったリーダー候補を1チームに集めない
+    #[test]
+    fn create_teams_with_max_leader_candidates() {
+        for _ in 0..20 {
+            let setting = TeamsCreationSetting{
+                attendees: vec![
+                    Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                    Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                    Attendee{person: Person::new("C"), leader: Some(true), ..Default::default()},
+                    Attendee{person: Person::new("D"), leader: Some(true), ..Default::default()},
+                    Attendee{person: Person::new("E"), leader: Some(false), ..Default::default()},
+                    Attendee{person: Person::new("F"), leader: Some(false), ..Default::default()},
+                ],
+                num_of_teams: 2,
+                max_leader_candidates_per_team: Some(2),
+                ..Default::default()
+            };
+
+            let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+
+            for team in &teams.team {
+                let candidates = team.member.iter().filter(|m| ["A", "B", "C", "D"].contains(&&*m.name)).count() + 1;
+                assert_eq!(candidates, 2);
+            }
+        }
+    }
+
+    /// Teams#createのテスト
+    /// sub_teamsが設定されていれば、各チームのリーダーとメンバーをちょうど1回ずつサブチームに分ける
+    #[test]
+    fn create_teams_with_sub_teams() {
+        let names = ["A", "B", "C", "D", "E", "F", "G", "H", "I", "J"];
+        let setting = TeamsCreationSetting{
+            attendees: names.iter().enumerate().map(|(i, name)| Attendee{
+                person: Person::new(*name),
+                leader: Some(i < 2),
+                ..Default::default()
+            }).collect(),
+            num_of_teams: 2,
+            sub_teams: Some(SubTeamsCreationSetting{num_of_teams: 2, flat: Some(true), ..Default::default()}),
+            ..Default::default()
+        };
+
+        let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+
+        for team in &teams.team {
+            assert_eq!(team.sub_team.len(), 2);
+
+            let mut people: Vec<&str> = std::iter::once(&team.leader).chain(team.member.iter()).map(|p| &*p.name).collect();
+            let mut sub_people: Vec<&str> = team.sub_team.iter()
+                .flat_map(|t| std::iter::once(&t.leader).chain(t.member.iter()))
+                .map(|p| &*p.name)
+                .collect();
+            people.sort();
+            sub_people.sort();
+            assert_eq!(people, sub_people);
+        }
+    }
+
+    /// Teams#createのテスト
+    /// サブチームのリーダー候補はsub_leaderで決まり、足りなければエラー
+    #[test]
+    fn create_teams_with_sub_leaders_lack() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), sub_leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(false), sub_leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), sub_leader: Some(false), ..Default::default()},
+            ],
+            num_of_teams: 1,
+            sub_teams: Some(SubTeamsCreationSetting{num_of_teams: 2, ..Default::default()}),
+            ..Default::default()
+        };
+
+        let result = Teams::create(setting, &crate::strategy::ShuffleStrategies::NoShuffle);
+
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<TeamsCreationSettingError>(),
+            Some(TeamsCreationSettingError::LeadersLack(1, 2))
+        ));
+    }
+
+    /// Teams#createのテスト
+    /// リーダーの兼任を許さなければ、チームのリーダーはサブチームのリーダーにならない
+    #[test]
+    fn create_teams_without_shared_leaders() {
+        for _ in 0..20 {
+            let setting = TeamsCreationSetting{
+                attendees: vec![
+                    Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                    Attendee{person: Person::new("B"), ..Default::default()},
+                    Attendee{person: Person::new("C"), ..Default::default()},
+                ],
+                num_of_teams: 1,
+                sub_teams: Some(SubTeamsCreationSetting{num_of_teams: 2, flat: Some(true), ..Default::default()}),
+                ..Default::default()
+            };
+
+            let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+
+            assert!(teams.team[0].sub_team.iter().all(|t| &*t.leader.name != "A"));
+        }
+    }
+
+    /// TeamsCreationSetting#validateのテスト
+    /// リーダーの兼任を許していなければ、leaderとsub_leaderの両方がtrueの参加者がいるとエラー
+    #[test]
+    fn setting_validation_shared_leader() {
+        let attendees = || vec![
+            Attendee{person: Person::new("A"), leader: Some(true), sub_leader: Some(true), ..Default::default()},
+            Attendee{person: Person::new("B"), leader: Some(false), sub_leader: Some(true), ..Default::default()},
+        ];
+        let setting = TeamsCreationSetting{
+            attendees: attendees(),
+            num_of_teams: 1,
+            sub_teams: Some(SubTeamsCreationSetting{num_of_teams: 1, ..Default::default()}),
+            ..Default::default()
+        };
+        let allowed = TeamsCreationSetting{
+            attendees: attendees(),
+            num_of_teams: 1,
+            sub_teams: Some(SubTeamsCreationSetting{num_of_teams: 1, allow_shared_leaders: Some(true), ..Default::default()}),
+            ..Default::default()
+        };
+
+        assert!(matches!(setting.validate(), Err(TeamsCreationSettingError::SharedLeader(name)) if name == "A"));
+        assert!(allowed.validate().is_ok());
+    }
+
+    /// Teams#createのテスト
+    /// rolesが設定されていれば、各チームの役割枠をタグを持つ参加者で埋め、残りをメンバーにする
+    #[test]
+    fn create_committees() {
+        let tagged = |name: &str, tags: &[&str]| Attendee{
+            person: Person::new(name),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                tagged("A", &["chair"]),
+                tagged("B", &["chair", "reviewer"]),
+                tagged("C", &["note-taker"]),
+                tagged("D", &["note-taker", "reviewer"]),
+                tagged("E", &["reviewer"]),
+                tagged("F", &["reviewer"]),
+                tagged("G", &[]),
+            ],
+            num_of_teams: 2,
+            roles: Some(vec![RoleSlot::new("chair", 1), RoleSlot::new("note-taker", 1), RoleSlot::new("reviewer", 1)]),
+            ..Default::default()
+        };
+
+        let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+
+        for team in &teams.team {
+            assert_eq!(team.roles["chair"], vec![team.leader.name.clone()]);
+            assert_eq!(team.roles["note-taker"].len(), 1);
+            assert_eq!(team.roles["reviewer"].len(), 1);
+        }
+        assert_eq!(teams.team.iter().map(|t| t.member.len() + 1).sum::<usize>(), 7);
+    }
+
+    /// Teams#createのテスト
+    /// 役割枠を埋められなければ、埋められなかった枠を含むエラー
+    #[test]
+    fn create_committees_with_unfillable_slot() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), tags: vec!["chair".to_string()], ..Default::default()},
+                Attendee{person: Person::new("B"), tags: vec!["chair".to_string()], ..Default::default()},
+            ],
+            num_of_teams: 1,
+            roles: Some(vec![RoleSlot::new("chair", 1), RoleSlot::new("note-taker", 1)]),
+            ..Default::default()
+        };
+
+        let result = Teams::create(setting, &crate::strategy::ShuffleStrategies::NoShuffle);
+
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<committee::CommitteeError>(),
+            Some(&committee::CommitteeError::UnfillableSlot(1, "note-taker".to_string()))
+        );
+    }
+
+    fn continuity_setting(continuity: f64) -> TeamsCreationSetting {
+        let mut previous_a = Team::new(Person::new("A"));
+        for name in ["C", "D", "E"] {
+            previous_a.assign(Person::new(name));
+        }
+        let mut previous_b = Team::new(Person::new("B"));
+        for name in ["F", "G", "H"] {
+            previous_b.assign(Person::new(name));
+        }
+
+        let mut setting = TeamsCreationSetting{
+            attendees: ["A", "B", "C", "D", "E", "F", "G", "H"].iter().map(|name| Attendee{
+                person: Person::new(*name),
+                leader: Some(*name == "A" || *name == "B"),
+                ..Default::default()
+            }).collect(),
+            num_of_teams: 2,
+            continuity: Some(continuity),
+            ..Default::default()
+        };
+        setting.set_previous(Teams::from_vec(vec![previous_a, previous_b]), PairCounts::new());
+        setting
+    }
+
+    /// Teams#createのテスト
+    /// continuityが1.0であれば前回のチームをそのまま残す
+    #[test]
+    fn create_teams_with_full_continuity() {
+        let teams = Teams::create(continuity_setting(1.0), &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+
+        let mut names: Vec<&str> = teams.team[0].member.iter().map(|p| &*p.name).collect();
+        names.sort();
+        assert_eq!(&*teams.team[0].leader.name, "A");
+        assert_eq!(names, vec!["C", "D", "E"]);
+    }
+
+    /// Teams#createのテスト
+    /// continuityが0.5であれば前回の各チームの半数以上が同じチームに残る
+    #[test]
+    fn create_teams_with_half_continuity() {
+        for _ in 0..20 {
+            let teams = Teams::create(continuity_setting(0.5), &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+
+            for (team, previous) in teams.team.iter().zip([["A", "C", "D", "E"], ["B", "F", "G", "H"]]) {
+                let stayed = std::iter::once(&team.leader).chain(team.member.iter()).filter(|p| previous.contains(&&*p.name)).count();
+                assert!(stayed >= 2);
+                assert_eq!(team.member.len(), 3);
+            }
+        }
+    }
+
+    /// Teams#createのテスト
+    /// 過去に同じチームになった回数が多い人から入れ替える
+    #[test]
+    fn create_teams_with_continuity_rotates_long_pairs() {
+        let mut setting = continuity_setting(0.5);
+        let (previous, _) = setting.previous.take().unwrap();
+        let mut pair_counts = PairCounts::new();
+        pair_counts.insert(("A".into(), "C".into()), 5);
+        pair_counts.insert(("A".into(), "D".into()), 5);
+        setting.set_previous(previous, pair_counts);
+
+        let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::NoShuffle).unwrap();
+
+        let names: Vec<&str> = teams.team[0].member.iter().map(|p| &*p.name).collect();
+        assert!(names.contains(&"E"));
+    }
+
+    /// Teams#createのテスト
+    /// 同じチームになった回数が同じ人の入れ替えは、出席者の並び順が変わっても同じシード値なら同じ結果になる
+    #[test]
+    fn create_teams_with_continuity_ignores_roster_order() {
+        for seed in 0..10 {
+            let setting = continuity_setting(0.5);
+            let mut reversed = continuity_setting(0.5);
+            reversed.attendees.reverse();
+
+            let teams = Teams::create(setting, &crate::strategy::SeededShuffle::new(seed)).unwrap();
+            let reversed_teams = Teams::create(reversed, &crate::strategy::SeededShuffle::new(seed)).unwrap();
+
+            assert_eq!(toml::to_string(&teams).unwrap(), toml::to_string(&reversed_teams).unwrap());
+        }
+    }
+
+    /// TeamsCreationSetting#default_to_flatのテスト
+    /// リーダー候補が1人もいない場合だけ全員をリーダー候補にする
+    #[test]
+    fn default_to_flat_without_leaders() {
+        let mut setting = TeamsCreationSetting{
+            attendees: vec![Attendee::new(Person::new("A"), false), Attendee::new(Person::new("B"), false)],
+            ..Default::default()
+        };
+        setting.default_to_flat();
+        assert!(setting.is_flat());
+
+        let mut setting = TeamsCreationSetting{
+            attendees: vec![Attendee::new(Person::new("A"), true), Attendee::new(Person::new("B"), false)],
+            ..Default::default()
+        };
+        setting.default_to_flat();
+        assert!(!setting.is_flat());
+    }
+
+    /// TeamsCreationSetting#validateのテスト
+    /// continuityが0.0〜1.0の範囲外であればエラー
+    #[test]
+    fn setting_validation_continuity_out_of_range() {
+        assert!(matches!(continuity_setting(1.5).validate(), Err(TeamsCreationSettingError::ContinuityOutOfRange(_))));
+        assert!(continuity_setting(0.0).validate().is_ok());
+    }
+
+    /// Teams#createのテスト
+    /// 同じグループの参加者は必ず同じチームになり、チームの人数はグループ単位で揃える
+    #[test]
+    fn create_teams_with_groups() {
+        for _ in 0..20 {
+            let attendee = |name: &str, leader: bool, group: Option<&str>| Attendee{
+                person: Person::new(name),
+                leader: Some(leader),
+                group: group.map(String::from),
+                ..Default::default()
+            };
+            let setting = TeamsCreationSetting{
+                attendees: vec![
+                    attendee("A", true, Some("family")),
+                    attendee("B", false, Some("family")),
+                    attendee("C", true, None),
+                    attendee("D", false, Some("pair")),
+                    attendee("E", false, Some("pair")),
+                    attendee("F", false, None),
+                ],
+                num_of_teams: 2,
+                ..Default::default()
+            };
+
+            let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
+
+            let team_of = |name: &str| teams.team.iter().position(|t| std::iter::once(&t.leader).chain(t.member.iter()).any(|p| &*p.name == name)).unwrap();
+            assert_eq!(team_of("A"), team_of("B"));
+            assert_eq!(team_of("D"), team_of("E"));
+            assert_eq!(teams.team.iter().map(|t| t.member.len()).collect::<Vec<_>>(), vec![2, 2]);
+        }
+    }
+
+    /// TeamsCreationSetting#validateのテスト
+    /// リーダー候補のいるグループがチーム数より少なければエラー
+    #[test]
+    fn setting_validation_group_leaders_lack() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), group: Some("family".to_string()), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), group: Some("family".to_string()), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+            ],
+            num_of_teams: 2,
+            ..Default::default()
+        };
+
+        assert!(matches!(setting.validate(), Err(TeamsCreationSettingError::GroupLeadersLack(1, 2))));
+    }
+
+    /// TeamsCreationSetting#validateのテスト
+    /// グループと委員会モード・continuityは一緒に使えない
+    #[test]
+    fn setting_validation_groups_with_roles_or_continuity() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), group: Some("family".to_string()), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), group: Some("family".to_string()), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(true), ..Default::default()},
+            ],
+            num_of_teams: 2,
+            ..Default::default()
+        };
+        let with_continuity = TeamsCreationSetting{continuity: Some(0.5), ..setting.clone()};
+        let with_roles = TeamsCreationSetting{roles: Some(vec![RoleSlot::new("chair", 1)]), ..setting.clone()};
+
+        assert!(setting.validate().is_ok());
+        assert_eq!(
+            with_continuity.validate(),
+            Err(TeamsCreationSettingError::UnsupportedCombination("attendees.group".into(), "continuity".into()))
+        );
+        assert_eq!(
+            with_roles.validate(),
+            Err(TeamsCreationSettingError::UnsupportedCombination("attendees.group".into(), "roles".into()))
+        );
+    }
+
+    /// TeamsCreationSetting#validateのテスト
+    /// 負荷と委員会モード・continuity・グループは一緒に使えない
+    #[test]
+    fn setting_validation_loads_with_other_modes() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), load: Some(2), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+            ],
+            num_of_teams: 2,
+            ..Default::default()
+        };
+        let mut with_groups = setting.clone();
+        with_groups.attendees[2].group = Some("pair".to_string());
+        let combination = |other: &str| Err(TeamsCreationSettingError::UnsupportedCombination("attendees.load".into(), other.into()));
+
+        assert!(setting.validate().is_ok());
+        assert_eq!(TeamsCreationSetting{continuity: Some(0.5), ..setting.clone()}.validate(), combination("continuity"));
+        assert_eq!(TeamsCreationSetting{roles: Some(vec![RoleSlot::new("chair", 1)]), ..setting.clone()}.validate(), combination("roles"));
+        assert_eq!(with_groups.validate(), combination("attendees.group"));
+    }
+
+    /// TeamsCreationSetting#validateのテスト
+    /// overbookingはtarget_sizeと一緒にしか使えない
+    #[test]
+    fn setting_validation_overbooking_without_target_size() {
+        let setting = TeamsCreationSetting{
+            attendees: vec![
+                Attendee{person: Person::new("A"), leader: Some(true), attendance_probability: Some(0.5), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+            ],
+            num_of_teams: 2,
+            overbooking: Some(true),
+            ..Default::default()
+        };
+        let with_target_size = TeamsCreationSetting{target_size: Some(TargetSize{min: 1, max: 3}), ..setting.clone()};
+
+        assert_eq!(
+            setting.validate(),
+            Err(TeamsCreationSettingError::UnsupportedCombination("overbooking".into(), "num_of_teams".into()))
+        );
+        assert!(with_target_size.validate().is_ok());
+        assert!(TeamsCreationSetting{overbooking: Some(false), ..setting}.validate().is_ok());
+    }
+
+    /// TargetSize#best_num_of_teamsのテスト
+    /// 全チームが範囲に収まるチーム数のうち、平均人数が範囲の中央に最も近いものを選ぶ
+    #[test]
+    fn best_num_of_teams() {
+        let target = TargetSize{min: 3, max: 5};
+
+        assert_eq!(target.best_num_of_teams(20, 20), Some(5));
+        assert_eq!(target.best_num_of_teams(18, 20), Some(4));
+        assert_eq!(target.best_num_of_teams(18, 3), None);
+        assert_eq!(target.best_num_of_teams(2, 2), None);
+    }
+
+    /// TeamsCreationSetting#resolve_target_sizeのテスト
+    /// target_sizeからチーム数を決め、範囲が不正ならエラー
+    #[test]
+    fn resolve_target_size() {
+        let attendees = || (0..10).map(|i| Attendee::new(Person::new(i.to_string()), true)).collect();
+        let mut setting = TeamsCreationSetting{
+            attendees: attendees(),
+            target_size: Some(TargetSize{min: 2, max: 3}),
+            ..Default::default()
+        };
+        let mut invalid = TeamsCreationSetting{
+            attendees: attendees(),
+            target_size: Some(TargetSize{min: 4, max: 3}),
+            ..Default::default()
+        };
+
+        setting.resolve_target_size().unwrap();
+
+        assert_eq!(setting.num_of_teams, 4);
+        assert!(matches!(invalid.resolve_target_size(), Err(TeamsCreationSettingError::InvalidTargetSize(4, 3))));
+    }
+
+    /// Teamのシリアライズのテスト
+    /// サブチームはチームの中に入れ子で出力し、サブチームがなければ出力しない
+    #[test]
+    fn serialize_team_with_sub_teams() {
+        let mut team = Team::new(Person::new("A"));
+        team.assign(Person::new("B"));
+        assert!(!toml::to_string(&team).unwrap().contains("sub_team"));
+
+        team.sub_team = vec![Team::new(Person::new("A")), Team::new(Person::new("B"))];
+        let serialized = toml::to_string(&Teams::from_vec(vec![team])).unwrap();
+        let deserialized: Teams = toml::from_str(&serialized).unwrap();
+
+        assert!(serialized.contains("[[team.sub_team]]"));
+        assert_eq!(&*deserialized.team[0].sub_team[1].leader.name, "B");
+    }
+
+    /// Teams#sort_membersのテスト
+    /// 各チームのメンバーを照合順序に従って並べ替える
+    #[test]
+    fn sort_team_members() {
+        let mut team = Team::new(Person::new("A"));
+        team.assign(Person::new("C"));
+        team.assign(Person::new("B"));
+        let mut teams = Teams::from_vec(vec![team]);
+
+        teams.sort_members(Collation::Bytes);
+
+        let names: Vec<&str> = teams.team[0].member.iter().map(|p| &*p.name).collect();
+        assert_eq!(names, vec!["B", "C"]);
+    }
+
     /// Teams#createのテスト
     /// `TeamsCreationSetting`の内容をもとに複数のチームを作成し、リーダーとリーダー以外のメンバーを設定して返す
     #[test]
     fn create_teams_by_setting() {
         let setting = TeamsCreationSetting{
             attendees: vec![
-                Attendee{person: Person{name: String::from("A")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("B")}, leader: Some(true)},
-                Attendee{person: Person{name: String::from("C")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("D")}, leader: Some(false)},
-                Attendee{person: Person{name: String::from("E")}, leader: Some(true)},
+                Attendee{person: Person::new("A"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("B"), leader: Some(true), ..Default::default()},
+                Attendee{person: Person::new("C"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("D"), leader: Some(false), ..Default::default()},
+                Attendee{person: Person::new("E"), leader: Some(true), ..Default::default()},
             ],
             num_of_teams: 2,
-            flat: Some(false)
+            flat: Some(false),
+            ..Default::default()
         };
 
         let teams = Teams::create(setting, &crate::strategy::ShuffleStrategies::RandomShuffle).unwrap();
@@ -464,4 +2415,24 @@ mod tests {
         assert_eq!(team2.member.len(), 1); //1 leader, 1 memberß
 
     }
+
+    /// AttendeeIndexのテスト
+    /// 名前からタグと負荷を引き、同じ名前が複数あれば先に書かれた方を、いなければ空と0を返す
+    #[test]
+    fn attendee_index_lookup() {
+        let attendees = vec![
+            Attendee{person: Person::new("A"), tags: vec!["chair".to_string()], load: Some(2), ..Default::default()},
+            Attendee{person: Person::new("A"), tags: vec!["other".to_string()], load: Some(5), ..Default::default()},
+            Attendee{person: Person::new("B"), ..Default::default()},
+        ];
+
+        let index = AttendeeIndex::new(&attendees);
+
+        assert_eq!(index.tags_of("A"), ["chair".to_string()]);
+        assert_eq!(index.load_of("A"), 2);
+        assert!(index.tags_of("B").is_empty());
+        assert_eq!(index.load_of("B"), 0);
+        assert!(index.tags_of("Z").is_empty());
+        assert_eq!(index.load_of("Z"), 0);
+    }
 }
\ No newline at end of file
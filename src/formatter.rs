@@ -0,0 +1,239 @@
+mod codeowners;
+mod org;
+mod people;
+mod seating;
+mod summary;
+mod svg;
+
+use std::num::NonZeroU8;
+use std::str::FromStr;
+use anyhow::Result;
+use serde::Deserialize;
+use thiserror::Error;
+use crate::domain::{Person, Teams};
+use crate::locale::Locale;
+use crate::seating::Table;
+
+/// 出力形式に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum OutputFormatError {
+    /// 未知の出力形式
+    #[error("unknown output format {0}")]
+    UnknownFormat(String)
+}
+
+/// 各出力形式に共通の出力オプション
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatOptions {
+    /// ラベルの言語
+    pub locale: Locale,
+    /// 座席表に使う会場のテーブル
+    pub tables: Vec<Table>,
+    /// 人が読む出力で名前に付ける敬称(例: さん)
+    pub honorific: Option<String>,
+    /// リーダーをメンバーの一覧にも含めるかどうか
+    /// 全員を1つの一覧に並べる出力(codeowners・座席表・1人ずつの割り当て)には影響しない
+    pub leader_in_members: bool,
+    /// 設定ファイルの`[output]`に書いた出力形式ごとの設定
+    pub output: OutputSettings
+}
+
+/// 設定ファイルの`[output]`に書く出力形式ごとの設定
+/// 毎回コマンドライン引数で指定しなくてよいよう、出力形式の細かい調整を設定ファイルに書いておける
+/// 書き間違いに気づけるよう、設定のない出力形式や未知の設定値はエラーにする
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSettings {
+    /// `[output.org]` org-mode形式の設定
+    #[serde(default)]
+    pub org: OrgOutput,
+    /// `[output.summary]` 1人1行の文章の設定
+    #[serde(default)]
+    pub summary: SummaryOutput
+}
+
+/// org-mode形式の設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrgOutput {
+    /// チームの見出しのレベル(`*`の数)。省略時は1
+    /// 既存のorgファイルの見出しの下に貼り付ける場合に深くする
+    pub heading_level: Option<NonZeroU8>
+}
+
+impl OrgOutput {
+    /// チームの見出しの`*`
+    pub fn heading(&self) -> String {
+        "*".repeat(self.heading_level.map_or(1, |l| l.get() as usize))
+    }
+}
+
+/// 1人1行の文章の設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryOutput {
+    /// チームのメモを文末に付けるかどうか。省略時はtrue
+    pub notes: Option<bool>
+}
+
+impl SummaryOutput {
+    /// チームのメモを文末に付ける場合はtrue
+    pub fn includes_notes(&self) -> bool {
+        self.notes.unwrap_or(true)
+    }
+}
+
+impl FormatOptions {
+    /// 人が読む出力に使う名前を返す
+    /// 表示名・代名詞に加えて、敬称が指定されていれば名前に付ける
+    /// # Attributes
+    /// * `person` - 人物
+    pub fn display(&self, person: &Person) -> String {
+        person.display_with_honorific(self.honorific.as_deref().unwrap_or(""))
+    }
+}
+
+/// 作成したチームの出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    /// TOML形式(デフォルト)
+    #[default]
+    Toml,
+    /// Emacs org-mode形式
+    Org,
+    /// チームごとのカードを並べたSVG画像
+    Svg,
+    /// CODEOWNERSの断片
+    Codeowners,
+    /// 会場のテーブル配置に合わせたテキストの座席表
+    Seating,
+    /// 1席1行のCSVの座席表
+    SeatingCsv,
+    /// 名前をキーにした1人ずつの割り当てのJSON
+    PeopleJson,
+    /// 1人1行の割り当てのCSV
+    PeopleCsv,
+    /// 1人1行の短い文章。スクリーンリーダーやダイレクトメッセージ向け
+    Summary
+}
+
+impl OutputFormat {
+    /// 作成したチームをこの出力形式の文字列にする
+    /// # Attributes
+    /// * `teams` - 作成したチーム
+    /// * `options` - 出力オプション
+    ///
+    /// # Returns
+    /// Ok(出力する文字列)
+    pub fn render(&self, teams: &Teams, options: &FormatOptions) -> Result<String> {
+        let with_leaders;
+        let teams = if options.leader_in_members && matches!(self, Self::Toml | Self::Org | Self::Svg) {
+            with_leaders = teams.with_leader_in_members();
+            &with_leaders
+        } else {
+            teams
+        };
+
+        match self {
+            Self::Toml => Ok(toml::to_string_pretty(teams)?),
+            Self::Org => Ok(org::render(teams, options)),
+            Self::Svg => Ok(svg::render(teams, options)),
+            Self::Codeowners => Ok(codeowners::render(teams, options)),
+            Self::Seating => Ok(seating::render_grid(teams, options)?),
+            Self::SeatingCsv => Ok(seating::render_csv(teams, options)?),
+            Self::PeopleJson => Ok(people::render_json(teams, options)),
+            Self::PeopleCsv => Ok(people::render_csv(teams, options)),
+            Self::Summary => Ok(summary::render(teams, options))
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = OutputFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toml" => Ok(Self::Toml),
+            "org" => Ok(Self::Org),
+            "svg" => Ok(Self::Svg),
+            "codeowners" => Ok(Self::Codeowners),
+            "seating" => Ok(Self::Seating),
+            "seating-csv" => Ok(Self::SeatingCsv),
+            "people-json" => Ok(Self::PeopleJson),
+            "people-csv" => Ok(Self::PeopleCsv),
+            "summary" => Ok(Self::Summary),
+            _ => Err(OutputFormatError::UnknownFormat(s.to_string()))
+        }
+    }
+}
+
+/// CSVのフィールドをエスケープする
+/// # Attributes
+/// * `s` - エスケープする文字列
+fn escape_csv(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// OutputFormat#from_strのテスト
+    /// 形式名から出力形式を得る。未知の形式名はエラー
+    #[test]
+    fn parse_output_format() {
+        assert_eq!("toml".parse(), Ok(OutputFormat::Toml));
+        assert_eq!("org".parse(), Ok(OutputFormat::Org));
+        assert_eq!("svg".parse(), Ok(OutputFormat::Svg));
+        assert_eq!("codeowners".parse(), Ok(OutputFormat::Codeowners));
+        assert_eq!("seating".parse(), Ok(OutputFormat::Seating));
+        assert_eq!("seating-csv".parse(), Ok(OutputFormat::SeatingCsv));
+        assert_eq!("people-json".parse(), Ok(OutputFormat::PeopleJson));
+        assert_eq!("people-csv".parse(), Ok(OutputFormat::PeopleCsv));
+        assert_eq!("summary".parse(), Ok(OutputFormat::Summary));
+        assert_eq!("xml".parse::<OutputFormat>(), Err(OutputFormatError::UnknownFormat("xml".into())));
+    }
+
+    /// OutputSettingsのテスト
+    /// 出力形式ごとの表を読み、省略した設定値は既定値になる。見出しのレベルに0は指定できない
+    #[test]
+    fn parse_output_settings() {
+        let output: OutputSettings = toml::from_str("[org]\nheading_level = 2\n").unwrap();
+
+        assert_eq!(output.org.heading(), "**");
+        assert!(output.summary.includes_notes());
+        assert_eq!(OutputSettings::default().org.heading(), "*");
+        assert!(toml::from_str::<OutputSettings>("[org]\nheading_level = 0\n").is_err());
+    }
+
+    /// OutputSettingsのテスト
+    /// 設定のない出力形式の表や未知の設定値は、書き間違いとしてエラーにする
+    #[test]
+    fn parse_output_settings_rejects_unknown() {
+        assert!(toml::from_str::<OutputSettings>("[markdown]\nheading_level = 2\n").is_err());
+        assert!(toml::from_str::<OutputSettings>("[orgg]\nheading_level = 2\n").is_err());
+        assert!(toml::from_str::<OutputSettings>("[org]\nheading_levle = 2\n").is_err());
+        assert!(toml::from_str::<OutputSettings>("[summary]\nnote = false\n").is_err());
+    }
+
+    /// OutputFormat#renderのテスト
+    /// leader_in_membersを指定すればリーダーをメンバーの一覧にも含め、全員を並べる出力は変えない
+    #[test]
+    fn render_leader_in_members() {
+        let mut team = crate::domain::Team::new(Person::new("A"));
+        team.assign(Person::new("B"));
+        let teams = Teams::from_vec(vec![team]);
+        let options = FormatOptions { leader_in_members: true, ..Default::default() };
+
+        assert_eq!(OutputFormat::Org.render(&teams, &options).unwrap(), "* Team 1\nLeader: A\n- [ ] A\n- [ ] B\n");
+        assert!(OutputFormat::Toml.render(&teams, &options).unwrap().contains("[[team.member]]\nname = 'A'"));
+        assert_eq!(
+            OutputFormat::PeopleCsv.render(&teams, &options).unwrap(),
+            OutputFormat::PeopleCsv.render(&teams, &FormatOptions::default()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::domain::{Person, Teams};
+use super::FormatOptions;
+
+/// 作成したチームをCODEOWNERSの断片にする
+/// チームごとに`/team-N/`のパスとリーダー・メンバーのアカウント名を並べる
+/// メモとアジェンダは複数行であっても全ての行をコメントとして書く
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// CODEOWNERS形式の文字列
+pub fn render(teams: &Teams, options: &FormatOptions) -> String {
+    let mut res = String::new();
+
+    for (i, team) in teams.borrow_vec().iter().enumerate() {
+        let owners: Vec<String> = std::iter::once(&team.leader)
+            .chain(team.member.iter())
+            .map(owner)
+            .collect();
+
+        res.push_str(&format!("# {}\n", options.locale.team_name(i + 1)));
+        // 複数行のメモやアジェンダの2行目以降がオーナーの行として読まれないよう、全ての行をコメントにする
+        if let Some(notes) = &team.notes {
+            res.push_str(&comment(notes, "# ", "# "));
+        }
+        for item in &team.agenda {
+            res.push_str(&comment(item, "# - ", "#   "));
+        }
+        res.push_str(&format!("/team-{}/ {}\n", i + 1, owners.join(" ")));
+    }
+
+    res
+}
+
+/// 複数行の文字列を1行ずつコメントにする
+/// # Attributes
+/// * `text` - 文字列
+/// * `first` - 1行目の前に付ける文字列
+/// * `rest` - 2行目以降の前に付ける文字列
+fn comment(text: &str, first: &str, rest: &str) -> String {
+    text.lines().enumerate().map(|(i, line)| format!("{}{}\n", if i == 0 { first } else { rest }, line)).collect()
+}
+
+/// CODEOWNERSのオーナー表記にする
+/// `handle`があればそれを、なければ空白を`-`に置き換えた名前を使う
+/// # Attributes
+/// * `person` - オーナーにする`Person`
+fn owner(person: &Person) -> String {
+    match &person.handle {
+        Some(handle) => format!("@{}", handle.trim_start_matches('@')),
+        None => format!("@{}", person.name.split_whitespace().collect::<Vec<&str>>().join("-"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Team;
+
+    /// renderのテスト
+    /// チームごとにパスとオーナーを1行で出力する
+    #[test]
+    fn render_codeowners() {
+        let mut team1 = Team::new(Person{handle: Some("alice".into()), ..Person::new("Alice")});
+        team1.assign(Person::new("Bob Smith"));
+        let team2 = Team::new(Person{handle: Some("@carol".into()), ..Person::new("Carol")});
+
+        assert_eq!(
+            render(&Teams::from_vec(vec![team1, team2]), &FormatOptions::default()),
+            "# Team 1\n/team-1/ @alice @Bob-Smith\n# Team 2\n/team-2/ @carol\n"
+        );
+    }
+
+    /// renderのテスト
+    /// メモとアジェンダはコメントとして出力する
+    #[test]
+    fn render_codeowners_notes() {
+        let mut team = Team::new(Person::new("Alice"));
+        team.notes = Some("Owns the API".into());
+        team.agenda = vec!["Triage".into()];
+
+        assert_eq!(
+            render(&Teams::from_vec(vec![team]), &FormatOptions::default()),
+            "# Team 1\n# Owns the API\n# - Triage\n/team-1/ @Alice\n"
+        );
+    }
+
+    /// renderのテスト
+    /// 複数行のメモとアジェンダは全ての行をコメントにし、オーナーの行を増やさない
+    #[test]
+    fn render_codeowners_multiline_notes() {
+        let mut team = Team::new(Person::new("Alice"));
+        team.notes = Some("Owns the API\n/secrets/ @mallory\n".into());
+        team.agenda = vec!["Triage\n* @mallory".into()];
+
+        let res = render(&Teams::from_vec(vec![team]), &FormatOptions::default());
+
+        assert_eq!(res, "# Team 1\n# Owns the API\n# /secrets/ @mallory\n# - Triage\n#   * @mallory\n/team-1/ @Alice\n");
+        assert_eq!(res.lines().filter(|l| !l.starts_with('#')).count(), 1);
+    }
+}
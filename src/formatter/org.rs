@@ -0,0 +1,144 @@
+use crate::domain::Teams;
+use super::FormatOptions;
+
+/// 作成したチームをorg-mode形式にする
+/// チームごとに見出し(レベルは`[output.org]`の`heading_level`)を作り、メモとアジェンダがあれば書いた後にリーダーとメンバーのチェックボックスを並べる
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// org-mode形式の文字列
+pub fn render(teams: &Teams, options: &FormatOptions) -> String {
+    let labels = options.locale.labels();
+    let heading = options.output.org.heading();
+    let mut res = String::new();
+
+    for (i, team) in teams.borrow_vec().iter().enumerate() {
+        res.push_str(&format!("{} {}\n", heading, options.locale.team_name(i + 1)));
+        if let Some(notes) = &team.notes {
+            res.push_str(&format!("{}: {}\n", labels.notes, indent(notes)));
+        }
+        if !team.agenda.is_empty() {
+            res.push_str(&format!("{}:\n", labels.agenda));
+            for (j, item) in team.agenda.iter().enumerate() {
+                res.push_str(&format!("{}. {}\n", j + 1, indent(item)));
+            }
+        }
+        res.push_str(&format!("{}: {}\n", labels.leader, options.display(&team.leader)));
+        for member in &team.member {
+            res.push_str(&format!("- [ ] {}\n", options.display(member)));
+        }
+    }
+
+    res
+}
+
+/// 複数行の文字列の2行目以降を字下げする
+/// 行頭の`*`が見出しとして読まれないようにする
+fn indent(text: &str) -> String {
+    text.lines().collect::<Vec<&str>>().join("\n  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+    use crate::locale::Locale;
+
+    /// renderのテスト
+    /// チームごとの見出しとメンバーのチェックボックスを出力する
+    #[test]
+    fn render_org() {
+        let mut team1 = Team::new(Person::new("A"));
+        team1.assign(Person::new("B"));
+        team1.assign(Person::new("C"));
+        let team2 = Team::new(Person::new("D"));
+
+        let teams = Teams::from_vec(vec![team1, team2]);
+
+        assert_eq!(
+            render(&teams, &FormatOptions::default()),
+            "* Team 1\nLeader: A\n- [ ] B\n- [ ] C\n* Team 2\nLeader: D\n"
+        );
+    }
+
+    /// renderのテスト
+    /// 複数行のメモとアジェンダの2行目以降を字下げし、見出しを増やさない
+    #[test]
+    fn render_org_multiline_notes() {
+        let mut team = Team::new(Person::new("A"));
+        team.notes = Some("Room 3\n* not a heading".into());
+        team.agenda = vec!["Intro\n* still intro".into()];
+
+        let res = render(&Teams::from_vec(vec![team]), &FormatOptions::default());
+
+        assert_eq!(res, "* Team 1\nNotes: Room 3\n  * not a heading\nAgenda:\n1. Intro\n  * still intro\nLeader: A\n");
+        assert_eq!(res.lines().filter(|l| l.starts_with('*')).count(), 1);
+    }
+
+    /// renderのテスト
+    /// ロケールに従って見出しとラベルを出力する
+    #[test]
+    fn render_org_ja() {
+        let mut team = Team::new(Person::new("A"));
+        team.assign(Person::new("B"));
+
+        assert_eq!(
+            render(&Teams::from_vec(vec![team]), &FormatOptions {locale: Locale::Ja, ..Default::default()}),
+            "* チーム1\nリーダー: A\n- [ ] B\n"
+        );
+    }
+
+    /// renderのテスト
+    /// 表示名と代名詞があれば名前の代わりに出力する
+    #[test]
+    fn render_org_display_name() {
+        let mut team = Team::new(Person{display_name: Some("Alexandra".into()), ..Person::new("Alex")});
+        team.assign(Person{pronouns: Some("she/her".into()), ..Person::new("B")});
+
+        assert_eq!(
+            render(&Teams::from_vec(vec![team]), &FormatOptions::default()),
+            "* Team 1\nLeader: Alexandra\n- [ ] B (she/her)\n"
+        );
+    }
+
+    /// renderのテスト
+    /// 敬称が指定されていれば名前に付ける
+    #[test]
+    fn render_org_honorific() {
+        let mut team = Team::new(Person::new("山田"));
+        team.assign(Person::new("佐藤"));
+
+        assert_eq!(
+            render(&Teams::from_vec(vec![team]), &FormatOptions {locale: Locale::Ja, honorific: Some("さん".into()), ..Default::default()}),
+            "* チーム1\nリーダー: 山田さん\n- [ ] 佐藤さん\n"
+        );
+    }
+
+    /// renderのテスト
+    /// メモとアジェンダがあればリーダーの前に出力する
+    #[test]
+    fn render_org_notes() {
+        let mut team = Team::new(Person::new("A"));
+        team.notes = Some("Room 301".into());
+        team.agenda = vec!["Intro".into(), "Retro".into()];
+
+        assert_eq!(
+            render(&Teams::from_vec(vec![team]), &FormatOptions::default()),
+            "* Team 1\nNotes: Room 301\nAgenda:\n1. Intro\n2. Retro\nLeader: A\n"
+        );
+    }
+
+    /// renderのテスト
+    /// [output.org]のheading_levelに従って見出しを深くする
+    #[test]
+    fn render_org_heading_level() {
+        let output: crate::formatter::OutputSettings = toml::from_str("[org]\nheading_level = 3").unwrap();
+
+        assert_eq!(
+            render(&Teams::from_vec(vec![Team::new(Person::new("A"))]), &FormatOptions {output, ..Default::default()}),
+            "*** Team 1\nLeader: A\n"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::domain::{Person, Teams};
+use super::{escape_csv, FormatOptions};
+
+/// 1人分の割り当て
+struct Assignment<'a> {
+    /// 人物
+    person: &'a Person,
+    /// チーム番号(1から)
+    team: usize,
+    /// 役割。委員会モードでは割り当てた役割名、それ以外は`leader`か`member`
+    role: String,
+    /// サブチーム番号(1から)。2階層で作成していなければNone
+    sub_team: Option<usize>
+}
+
+/// チームの並び順(リーダー、メンバーの順)に全員の割り当てを並べる
+fn assignments(teams: &Teams) -> Vec<Assignment<'_>> {
+    let mut res = Vec::new();
+
+    for (i, team) in teams.borrow_vec().iter().enumerate() {
+        let people = std::iter::once((&team.leader, true)).chain(team.member.iter().map(|m| (m, false)));
+        for (person, leader) in people {
+            let roles: Vec<&str> = team.roles.iter()
+                .filter(|(_, names)| names.contains(&person.name))
+                .map(|(role, _)| role.as_str())
+                .collect();
+            let role = match (roles.is_empty(), leader) {
+                (false, _) => roles.join("; "),
+                (true, true) => "leader".to_string(),
+                (true, false) => "member".to_string()
+            };
+            let sub_team = team.sub_team.iter()
+                .position(|s| std::iter::once(&s.leader).chain(s.member.iter()).any(|p| p.name == person.name))
+                .map(|j| j + 1);
+
+            res.push(Assignment { person, team: i + 1, role, sub_team });
+        }
+    }
+
+    res
+}
+
+/// 作成したチームを名前をキーにしたJSONにする
+/// 出席や名札の印刷など、人ごとに処理するスクリプトに渡す
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// `{"名前": {"team": チーム番号, "role": 役割, ...}}`形式のJSON
+pub fn render_json(teams: &Teams, options: &FormatOptions) -> String {
+    let entries: Vec<String> = assignments(teams).iter()
+        .map(|a| format!(
+            "  {}: {{\"team\": {}, \"role\": {}, \"sub_team\": {}, \"display_name\": {}}}",
+            json_string(&a.person.name), a.team, json_string(&a.role),
+            a.sub_team.map_or("null".to_string(), |s| s.to_string()), json_string(&options.display(a.person))
+        ))
+        .collect();
+
+    if entries.is_empty() {
+        return "{}\n".to_string();
+    }
+    format!("{{\n{}\n}}\n", entries.join(",\n"))
+}
+
+/// 作成したチームを1人1行のCSVにする
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// 1人1行のCSV
+pub fn render_csv(teams: &Teams, options: &FormatOptions) -> String {
+    let mut res = String::from("name,display_name,team,role,sub_team\n");
+
+    for a in assignments(teams) {
+        res.push_str(&format!(
+            "{},{},{},{},{}\n",
+            escape_csv(&a.person.name), escape_csv(&options.display(a.person)), a.team, escape_csv(&a.role),
+            a.sub_team.map_or(String::new(), |s| s.to_string())
+        ));
+    }
+
+    res
+}
+
+/// JSONの文字列リテラルにする
+/// # Attributes
+/// * `s` - 文字列
+fn json_string(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c)
+        }
+    }
+    res.push('"');
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Team;
+
+    fn teams() -> Teams {
+        let mut team1 = Team::new(Person::new("A"));
+        team1.assign(Person::new("B \"Bee\""));
+        team1.assign(Person::new("C"));
+        team1.sub_team = vec![
+            Team{member: vec![Person::new("C")], ..Team::new(Person::new("A"))},
+            Team::new(Person::new("B \"Bee\"")),
+        ];
+        let mut team2 = Team::new(Person::new("D, Jr."));
+        team2.assign(Person::new("E"));
+        team2.roles.insert("scribe".into(), vec!["E".into()]);
+
+        Teams::from_vec(vec![team1, team2])
+    }
+
+    /// render_jsonのテスト
+    /// 名前をキーにチーム番号・役割・サブチーム番号を出力する
+    #[test]
+    fn render_people_json() {
+        assert_eq!(
+            render_json(&teams(), &FormatOptions::default()),
+            concat!(
+                "{\n",
+                "  \"A\": {\"team\": 1, \"role\": \"leader\", \"sub_team\": 1, \"display_name\": \"A\"},\n",
+                "  \"B \\\"Bee\\\"\": {\"team\": 1, \"role\": \"member\", \"sub_team\": 2, \"display_name\": \"B \\\"Bee\\\"\"},\n",
+                "  \"C\": {\"team\": 1, \"role\": \"member\", \"sub_team\": 1, \"display_name\": \"C\"},\n",
+                "  \"D, Jr.\": {\"team\": 2, \"role\": \"leader\", \"sub_team\": null, \"display_name\": \"D, Jr.\"},\n",
+                "  \"E\": {\"team\": 2, \"role\": \"scribe\", \"sub_team\": null, \"display_name\": \"E\"}\n",
+                "}\n"
+            )
+        );
+        assert_eq!(render_json(&Teams::from_vec(vec![]), &FormatOptions::default()), "{}\n");
+    }
+
+    /// render_csvのテスト
+    /// 1人1行で出力し、カンマや引用符を含む名前はクオートする
+    #[test]
+    fn render_people_csv() {
+        assert_eq!(
+            render_csv(&teams(), &FormatOptions { honorific: Some("さん".into()), ..Default::default() }),
+            concat!(
+                "name,display_name,team,role,sub_team\n",
+                "A,Aさん,1,leader,1\n",
+                "\"B \"\"Bee\"\"\",\"B \"\"Bee\"\"さん\",1,member,2\n",
+                "C,Cさん,1,member,1\n",
+                "\"D, Jr.\",\"D, Jr.さん\",2,leader,\n",
+                "E,Eさん,2,scribe,\n"
+            )
+        );
+    }
+}
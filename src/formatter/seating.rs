@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use crate::domain::Teams;
+use crate::seating::{assign_seats, SeatingError};
+use super::{escape_csv, FormatOptions};
+
+/// 作成したチームを会場のグリッドに並べたテキストの座席表にする
+/// テーブルごとにチーム名とメモ・アジェンダ、席番号順の名前を書き、リーダーには★を付ける
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// Ok(テキストの座席表)
+pub fn render_grid(teams: &Teams, options: &FormatOptions) -> Result<String, SeatingError> {
+    let seats = assign_seats(teams, &options.tables)?;
+
+    let team_vec = teams.borrow_vec();
+    let mut cells: BTreeMap<(u8, u8), Vec<String>> = BTreeMap::new();
+    for seat in &seats {
+        let cell = cells.entry((seat.y, seat.x)).or_insert_with(|| {
+            let team = &team_vec[seat.team - 1];
+            std::iter::once(options.locale.team_name(seat.team))
+                .chain(team.notes.iter().cloned())
+                .chain(team.agenda.iter().map(|item| format!("- {}", item)))
+                .collect()
+        });
+        cell.push(format!("{}. {}{}", seat.seat, options.display(&seat.person), if seat.leader { " ★" } else { "" }));
+    }
+
+    let max_x = cells.keys().map(|(_, x)| *x).max().unwrap_or(0);
+    let max_y = cells.keys().map(|(y, _)| *y).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..=max_x)
+        .map(|x| cells.iter().filter(|((_, cx), _)| *cx == x).flat_map(|(_, lines)| lines.iter().map(|l| l.chars().count())).max().unwrap_or(0))
+        .collect();
+
+    let mut rows = Vec::new();
+    for y in 0..=max_y {
+        let height = cells.iter().filter(|((cy, _), _)| *cy == y).map(|(_, lines)| lines.len()).max().unwrap_or(0);
+        let mut row = String::new();
+
+        for line in 0..height {
+            let texts: Vec<&str> = (0..=max_x)
+                .map(|x| cells.get(&(y, x)).and_then(|lines| lines.get(line)).map(String::as_str).unwrap_or(""))
+                .collect();
+            // 右端の空欄は区切りごと省く
+            let used = texts.iter().rposition(|t| !t.is_empty()).map_or(0, |i| i + 1);
+            let columns: Vec<String> = texts[..used].iter().zip(&widths)
+                .map(|(text, width)| format!("{}{}", text, " ".repeat(width - text.chars().count())))
+                .collect();
+            row.push_str(columns.join(" | ").trim_end());
+            row.push('\n');
+        }
+        rows.push(row);
+    }
+
+    Ok(rows.join("\n"))
+}
+
+/// 作成したチームの座席をCSVにする
+/// チームのメモとアジェンダは各席の行に繰り返し書く
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// Ok(1席1行のCSV)
+pub fn render_csv(teams: &Teams, options: &FormatOptions) -> Result<String, SeatingError> {
+    let team_vec = teams.borrow_vec();
+    let mut res = String::from("team,table_x,table_y,seat,name,display_name,leader,notes,agenda\n");
+
+    for seat in assign_seats(teams, &options.tables)? {
+        let team = &team_vec[seat.team - 1];
+        res.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            seat.team, seat.x, seat.y, seat.seat, escape_csv(&seat.person.name), escape_csv(&options.display(&seat.person)), seat.leader,
+            escape_csv(team.notes.as_deref().unwrap_or("")), escape_csv(&team.agenda.join("; "))
+        ));
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+    use crate::seating::Table;
+
+    fn teams() -> Teams {
+        let mut team1 = Team::new(Person::new("A"));
+        team1.notes = Some("Window".into());
+        team1.agenda = vec!["Intro".into(), "Q&A".into()];
+        team1.assign(Person{pronouns: Some("he/him".into()), ..Person::new("Bob")});
+        let team2 = Team::new(Person::new("C, Jr."));
+        let team3 = Team::new(Person::new("D"));
+
+        Teams::from_vec(vec![team1, team2, team3])
+    }
+
+    fn options() -> FormatOptions {
+        FormatOptions {
+            tables: vec![
+                Table{x: 0, y: 0, seats: 4},
+                Table{x: 1, y: 0, seats: 4},
+                Table{x: 1, y: 1, seats: 4},
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// render_gridのテスト
+    /// テーブルの位置に合わせてチームを並べる
+    #[test]
+    fn render_seating_grid() {
+        assert_eq!(
+            render_grid(&teams(), &options()).unwrap(),
+            concat!(
+                "Team 1          | Team 2\n",
+                "Window          | 1. C, Jr. ★\n",
+                "- Intro\n",
+                "- Q&A\n",
+                "1. A ★\n",
+                "2. Bob (he/him)\n",
+                "\n",
+                "                | Team 3\n",
+                "                | 1. D ★\n"
+            )
+        );
+    }
+
+    /// render_csvのテスト
+    /// 1席1行で出力し、カンマを含む名前はクオートする
+    #[test]
+    fn render_seating_csv() {
+        assert_eq!(
+            render_csv(&teams(), &options()).unwrap(),
+            concat!(
+                "team,table_x,table_y,seat,name,display_name,leader,notes,agenda\n",
+                "1,0,0,1,A,A,true,Window,Intro; Q&A\n",
+                "1,0,0,2,Bob,Bob (he/him),false,Window,Intro; Q&A\n",
+                "2,1,0,1,\"C, Jr.\",\"C, Jr.\",true,,\n",
+                "3,1,1,1,D,D,true,,\n"
+            )
+        );
+    }
+
+    /// render_gridのテスト
+    /// テーブルがなければエラー
+    #[test]
+    fn render_seating_without_tables() {
+        assert_eq!(render_grid(&teams(), &FormatOptions::default()), Err(SeatingError::NoTables));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::domain::Teams;
+use crate::locale::Locale;
+use super::FormatOptions;
+
+/// 作成したチームを1人1行の短い文章にする
+/// スクリーンリーダーで読み上げたり、そのままダイレクトメッセージに貼り付けたりできるよう、表や記号を使わない
+/// チームのメモは`[output.summary]`の`notes`がfalseでなければ文末に付ける
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// チームの並び順(リーダー、メンバーの順)に1人1行の文章
+pub fn render(teams: &Teams, options: &FormatOptions) -> String {
+    let labels = options.locale.labels();
+    let mut res = String::new();
+
+    for (i, team) in teams.borrow_vec().iter().enumerate() {
+        let team_name = options.locale.team_name(i + 1);
+        let people: Vec<String> = std::iter::once(&team.leader).chain(team.member.iter()).map(|p| options.display(p)).collect();
+
+        for (j, name) in people.iter().enumerate() {
+            let others: Vec<&str> = people[1..].iter().enumerate().filter(|(k, _)| k + 1 != j).map(|(_, n)| n.as_str()).collect();
+            let mut line = match (options.locale, j == 0) {
+                (Locale::En, true) => format!("{}, you lead {}", name, team_name),
+                (Locale::En, false) => format!("{}, you are in {}, led by {}", name, team_name, people[0]),
+                (Locale::Ja, true) => format!("{}、あなたは{}のリーダー", name, team_name),
+                (Locale::Ja, false) => format!("{}、あなたは{}で、リーダーは{}", name, team_name, people[0])
+            };
+            match (options.locale, others.is_empty()) {
+                (_, true) => {},
+                (Locale::En, false) => line.push_str(&format!(", with {}", join_en(&others))),
+                (Locale::Ja, false) => line.push_str(&format!("{}メンバーは{}", if j == 0 { "で、" } else { "、" }, others.join("、")))
+            }
+            line.push_str(match options.locale {
+                Locale::En => ".",
+                Locale::Ja => "です。"
+            });
+            if let Some(notes) = team.notes.as_ref().filter(|_| options.output.summary.includes_notes()) {
+                line.push_str(&format!(" {}: {}", labels.notes, notes));
+            }
+
+            res.push_str(&line);
+            res.push('\n');
+        }
+    }
+
+    res
+}
+
+/// 英語の文章で名前を並べる(`A`, `A and B`, `A, B and C`)
+fn join_en(names: &[&str]) -> String {
+    match names.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+
+    fn teams() -> Teams {
+        let mut team1 = Team::new(Person::new("Ken"));
+        team1.assign(Person::new("Alice"));
+        team1.assign(Person::new("Bob"));
+        team1.assign(Person::new("Carol"));
+        team1.notes = Some("Room 3".into());
+        let mut team2 = Team::new(Person::new("Dan"));
+        team2.assign(Person::new("Eve"));
+
+        Teams::from_vec(vec![team1, team2])
+    }
+
+    /// renderのテスト
+    /// 1人1行で、チーム・リーダー・同じチームの人・メモを文章にする
+    #[test]
+    fn render_summary() {
+        assert_eq!(
+            render(&teams(), &FormatOptions::default()),
+            concat!(
+                "Ken, you lead Team 1, with Alice, Bob and Carol. Notes: Room 3\n",
+                "Alice, you are in Team 1, led by Ken, with Bob and Carol. Notes: Room 3\n",
+                "Bob, you are in Team 1, led by Ken, with Alice and Carol. Notes: Room 3\n",
+                "Carol, you are in Team 1, led by Ken, with Alice and Bob. Notes: Room 3\n",
+                "Dan, you lead Team 2, with Eve.\n",
+                "Eve, you are in Team 2, led by Dan.\n"
+            )
+        );
+        assert_eq!(render(&Teams::from_vec(vec![Team::new(Person::new("Solo"))]), &FormatOptions::default()), "Solo, you lead Team 1.\n");
+    }
+
+    /// renderのテスト
+    /// 日本語では敬称を付けた名前で文章にする
+    #[test]
+    fn render_summary_ja() {
+        let options = FormatOptions { locale: Locale::Ja, honorific: Some("さん".into()), ..Default::default() };
+
+        let res = render(&teams(), &options);
+        let lines: Vec<&str> = res.lines().collect();
+
+        assert_eq!(lines[0], "Kenさん、あなたはチーム1のリーダーで、メンバーはAliceさん、Bobさん、Carolさんです。 メモ: Room 3");
+        assert_eq!(lines[1], "Aliceさん、あなたはチーム1で、リーダーはKenさん、メンバーはBobさん、Carolさんです。 メモ: Room 3");
+        assert_eq!(lines[5], "Eveさん、あなたはチーム2で、リーダーはDanさんです。");
+    }
+
+    /// renderのテスト
+    /// [output.summary]のnotesがfalseであればメモを付けない
+    #[test]
+    fn render_summary_without_notes() {
+        let output: crate::formatter::OutputSettings = toml::from_str("[summary]\nnotes = false").unwrap();
+
+        let res = render(&teams(), &FormatOptions {output, ..Default::default()});
+
+        assert_eq!(res.lines().next(), Some("Ken, you lead Team 1, with Alice, Bob and Carol."));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::domain::{Team, Teams};
+use super::FormatOptions;
+
+/// カードの幅
+const CARD_WIDTH: usize = 220;
+/// カードの間隔
+const CARD_MARGIN: usize = 20;
+/// 1行の高さ
+const LINE_HEIGHT: usize = 24;
+/// 横に並べるカードの最大数
+const CARDS_PER_ROW: usize = 4;
+
+/// 作成したチームをSVG画像にする
+/// チームごとにチーム名・リーダー・メンバーと、あればメモ・アジェンダを書いたカードを横に並べる
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `options` - 出力オプション
+///
+/// # Returns
+/// SVG形式の文字列
+pub fn render(teams: &Teams, options: &FormatOptions) -> String {
+    let team_vec = teams.borrow_vec();
+    let max_lines = team_vec.iter().map(|t| t.member.len() + 2 + notes_lines(t).len()).max().unwrap_or(0);
+    let card_height = (max_lines + 1) * LINE_HEIGHT;
+    let columns = team_vec.len().clamp(1, CARDS_PER_ROW);
+    let rows = team_vec.len().div_ceil(CARDS_PER_ROW).max(1);
+    let width = columns * (CARD_WIDTH + CARD_MARGIN) + CARD_MARGIN;
+    let height = rows * (card_height + CARD_MARGIN) + CARD_MARGIN;
+
+    let mut res = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" font-size=\"14\">\n",
+        width, height, width, height
+    );
+
+    for (i, team) in team_vec.iter().enumerate() {
+        let x = CARD_MARGIN + (i % CARDS_PER_ROW) * (CARD_WIDTH + CARD_MARGIN);
+        let y = CARD_MARGIN + (i / CARDS_PER_ROW) * (card_height + CARD_MARGIN);
+        let text_x = x + 12;
+
+        res.push_str("  <g>\n");
+        res.push_str(&format!(
+            "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"8\" fill=\"#ffffff\" stroke=\"#333333\"/>\n",
+            x, y, CARD_WIDTH, card_height
+        ));
+        res.push_str(&format!(
+            "    <text x=\"{}\" y=\"{}\" font-weight=\"bold\" font-size=\"16\">{}</text>\n",
+            text_x, y + LINE_HEIGHT, escape(&options.locale.team_name(i + 1))
+        ));
+        res.push_str(&format!(
+            "    <text x=\"{}\" y=\"{}\">★ {}</text>\n",
+            text_x, y + LINE_HEIGHT * 2, escape(&options.display(&team.leader))
+        ));
+        for (j, member) in team.member.iter().enumerate() {
+            res.push_str(&format!(
+                "    <text x=\"{}\" y=\"{}\">{}</text>\n",
+                text_x, y + LINE_HEIGHT * (j + 3), escape(&options.display(member))
+            ));
+        }
+        for (j, line) in notes_lines(team).iter().enumerate() {
+            res.push_str(&format!(
+                "    <text x=\"{}\" y=\"{}\" fill=\"#666666\">{}</text>\n",
+                text_x, y + LINE_HEIGHT * (team.member.len() + j + 3), escape(line)
+            ));
+        }
+        res.push_str("  </g>\n");
+    }
+
+    res.push_str("</svg>\n");
+    res
+}
+
+/// カードのメンバーの後ろに書くメモとアジェンダの行
+/// # Attributes
+/// * `team` - チーム
+fn notes_lines(team: &Team) -> Vec<String> {
+    team.notes.iter().cloned()
+        .chain(team.agenda.iter().map(|item| format!("・{}", item)))
+        .collect()
+}
+
+/// XMLの特殊文字をエスケープする
+/// # Attributes
+/// * `s` - エスケープする文字列
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Person;
+
+    /// renderのテスト
+    /// チームごとにカードを描き、名前はエスケープして出力する
+    #[test]
+    fn render_svg() {
+        let mut team1 = Team::new(Person::new("A"));
+        team1.assign(Person::new("B & C"));
+        let team2 = Team::new(Person::new("D"));
+
+        let svg = render(&Teams::from_vec(vec![team1, team2]), &FormatOptions::default());
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect ").count(), 2);
+        assert!(svg.contains(">Team 1</text>"));
+        assert!(svg.contains(">Team 2</text>"));
+        assert!(svg.contains(">★ A</text>"));
+        assert!(svg.contains(">B &amp; C</text>"));
+    }
+
+    /// renderのテスト
+    /// メモとアジェンダがあればメンバーの後ろに書く
+    #[test]
+    fn render_svg_notes() {
+        let mut team = Team::new(Person::new("A"));
+        team.notes = Some("Room <301>".into());
+        team.agenda = vec!["Intro".into()];
+
+        let svg = render(&Teams::from_vec(vec![team]), &FormatOptions::default());
+
+        assert!(svg.contains(">Room &lt;301&gt;</text>"));
+        assert!(svg.contains(">・Intro</text>"));
+    }
+}
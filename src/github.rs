@@ -0,0 +1,105 @@
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use thiserror::Error;
+
+/// GitHub CLIのコマンド名
+const GH: &str = "gh";
+
+/// GitHubへの投稿に関するエラー
+#[derive(Debug, Error)]
+pub enum GithubError {
+    /// ghコマンドが見つからない
+    #[error("gh command not found: install the GitHub CLI (https://cli.github.com) and run `gh auth login`")]
+    GhMissing,
+    /// ghコマンドが失敗した
+    #[error("gh {0} failed: {1}")]
+    Gh(String, String)
+}
+
+/// 結果を投稿する先
+#[derive(Debug, Clone, PartialEq)]
+pub enum GithubTarget {
+    /// 指定したタイトルでissueを作成する
+    NewIssue(String),
+    /// 指定した番号のissueにコメントする
+    IssueComment(u64)
+}
+
+impl GithubTarget {
+    /// 投稿に使うghコマンドの引数を返す。本文は標準入力から渡す
+    pub fn gh_args(&self) -> Vec<String> {
+        match self {
+            GithubTarget::NewIssue(title) => vec!["issue".into(), "create".into(), "--title".into(), title.clone(), "--body-file".into(), "-".into()],
+            GithubTarget::IssueComment(number) => vec!["issue".into(), "comment".into(), number.to_string(), "--body-file".into(), "-".into()]
+        }
+    }
+}
+
+/// ghコマンドで結果をGitHubのissueに投稿する
+/// 対象のリポジトリはghと同じく、カレントディレクトリのリポジトリか環境変数`GH_REPO`で決まる
+/// # Attributes
+/// * `target` - 投稿する先
+/// * `body` - 投稿する本文
+///
+/// # Returns
+/// Ok(ghが出力した、作成したissueやコメントのURL)
+pub fn post(target: &GithubTarget, body: &str) -> Result<String, GithubError> {
+    gh(GH, &target.gh_args(), body)
+}
+
+/// ghコマンドを実行し、本文を標準入力に渡す
+fn gh(program: &str, args: &[String], body: &str) -> Result<String, GithubError> {
+    let name = args.iter().take(2).cloned().collect::<Vec<String>>().join(" ");
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => GithubError::GhMissing,
+            _ => GithubError::Gh(name.clone(), e.to_string())
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body.as_bytes()).map_err(|e| GithubError::Gh(name.clone(), e.to_string()))?;
+    }
+    let output = child.wait_with_output().map_err(|e| GithubError::Gh(name.clone(), e.to_string()))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(GithubError::Gh(name, String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// GithubTarget#gh_argsのテスト
+    /// issueの作成ではタイトルを、コメントではissueの番号を指定し、本文は標準入力から読ませる
+    #[test]
+    fn github_target_gh_args() {
+        assert_eq!(
+            GithubTarget::NewIssue("Sprint 12 teams".into()).gh_args(),
+            vec!["issue", "create", "--title", "Sprint 12 teams", "--body-file", "-"]
+        );
+        assert_eq!(GithubTarget::IssueComment(42).gh_args(), vec!["issue", "comment", "42", "--body-file", "-"]);
+    }
+
+    /// ghのテスト
+    /// 本文を標準入力に渡し、標準出力を返す
+    #[test]
+    fn gh_pipes_body() {
+        assert_eq!(gh("cat", &[], "team A\nteam B\n").unwrap(), "team A\nteam B");
+    }
+
+    /// ghのテスト
+    /// コマンドが見つからなければGhMissing、失敗すればGh
+    #[test]
+    fn gh_errors() {
+        assert!(matches!(gh("guccicci-no-such-gh", &GithubTarget::IssueComment(1).gh_args(), ""), Err(GithubError::GhMissing)));
+        assert!(matches!(gh("false", &GithubTarget::IssueComment(1).gh_args(), ""), Err(GithubError::Gh(name, _)) if name == "issue comment"));
+    }
+}
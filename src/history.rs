@@ -0,0 +1,491 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::domain::Teams;
+use crate::hash::sha256_hex;
+
+/// 同じチームになった2人の組(名前順)ごとの回数
+pub type PairCounts = BTreeMap<(Arc<str>, Arc<str>), usize>;
+
+/// 1回分の実行記録
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RunRecord {
+    /// 実行ID
+    pub run_id: String,
+    /// 実行時刻(UNIX秒)
+    pub timestamp: u64,
+    /// 実行に使ったシード値
+    /// TOMLの整数はi64の範囲までなので文字列として保存する
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "seed_string")]
+    pub seed: Option<u64>,
+    /// 誰がどのように実行したかの記録
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<AuditEntry>,
+    /// 作成されたチーム
+    pub teams: Teams
+}
+
+/// 実行者・実行内容の監査記録
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuditEntry {
+    /// 実行したユーザー
+    pub user: String,
+    /// 実行したホスト
+    pub host: String,
+    /// コマンドライン引数
+    pub args: Vec<String>,
+    /// 設定ファイルの内容のSHA-256ハッシュ値
+    pub setting_hash: String,
+    /// 結果(TOML)のSHA-256ハッシュ値
+    pub result_hash: String
+}
+
+impl AuditEntry {
+    /// 実行環境から監査記録を作成する
+    /// ユーザーは環境変数`USER`(`USERNAME`)から、ホストは環境変数`HOSTNAME`か`/etc/hostname`から取得する
+    /// # Attributes
+    /// * `args` - コマンドライン引数
+    /// * `setting` - 設定ファイルの内容
+    /// * `teams` - 作成されたチーム
+    pub fn capture(args: Vec<String>, setting: &str, teams: &Teams) -> Result<AuditEntry> {
+        let user = env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_else(|_| "unknown".to_string());
+        let host = env::var("HOSTNAME").ok()
+            .or_else(|| fs::read_to_string("/etc/hostname").ok().map(|h| h.trim().to_string()))
+            .filter(|h| !h.is_empty())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        Ok(AuditEntry {
+            user,
+            host,
+            args,
+            setting_hash: sha256_hex(setting.as_bytes()),
+            result_hash: result_hash(teams)?
+        })
+    }
+}
+
+/// 結果のハッシュ値を求める
+/// 結果をTOMLにした文字列のSHA-256ハッシュ値
+/// # Attributes
+/// * `teams` - 作成されたチーム
+pub fn result_hash(teams: &Teams) -> Result<String> {
+    Ok(sha256_hex(toml::to_string_pretty(teams)?.as_bytes()))
+}
+
+/// 実行の再現に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum ReplayError {
+    /// 実行履歴に実行IDが見つからない
+    #[error("run {0} is not found in the history")]
+    RunNotFound(String),
+    /// シード値が記録されていない
+    #[error("run {0} has no recorded seed")]
+    SeedMissing(String),
+    /// 設定ファイルが記録時と異なる
+    #[error("setting hash {1} does not match the recorded hash {0}")]
+    SettingMismatch(String, String),
+    /// 再実行した結果が記録と異なる
+    #[error("replayed result hash {1} does not match the recorded hash {0}")]
+    ResultMismatch(String, String)
+}
+
+impl RunRecord {
+    /// 記録されたシード値を返す
+    /// # Returns
+    /// Ok(シード値)、記録されていなければErr<ReplayError>
+    pub fn replay_seed(&self) -> Result<u64, ReplayError> {
+        self.seed.ok_or_else(|| ReplayError::SeedMissing(self.run_id.clone()))
+    }
+
+    /// 設定ファイルが記録時と同じものか確かめる
+    /// 監査記録がなければ確かめようがないので常にOk
+    /// # Attributes
+    /// * `setting` - 設定ファイルの内容
+    pub fn verify_setting(&self, setting: &str) -> Result<(), ReplayError> {
+        match &self.audit {
+            Some(audit) if audit.setting_hash != sha256_hex(setting.as_bytes()) => {
+                Err(ReplayError::SettingMismatch(audit.setting_hash.clone(), sha256_hex(setting.as_bytes())))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /// 再実行した結果が記録と1バイトも違わないか確かめる
+    /// # Attributes
+    /// * `teams` - 再実行した結果
+    pub fn verify_result(&self, teams: &Teams) -> Result<(), ReplayError> {
+        let expected = self.audit.as_ref()
+            .map(|a| a.result_hash.clone())
+            .unwrap_or_else(|| result_hash(&self.teams).unwrap_or_default());
+        let actual = result_hash(teams).unwrap_or_default();
+
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(ReplayError::ResultMismatch(expected, actual))
+        }
+    }
+
+    /// 同じチームになった2人の組を返す
+    /// 組の2人は名前順に並べる
+    pub fn pairs(&self) -> Vec<(Arc<str>, Arc<str>)> {
+        self.teams.borrow_vec().iter().flat_map(|t| t.pairs()).collect()
+    }
+}
+
+/// シード値を文字列として読み書きする
+mod seed_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S: Serializer>(seed: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match seed {
+            Some(seed) => serializer.serialize_str(&seed.to_string()),
+            None => serializer.serialize_none()
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// 1か月とみなす秒数(30日)
+pub const SECONDS_PER_MONTH: u64 = 30 * 24 * 60 * 60;
+
+/// 実行履歴・アーカイブを残す範囲
+/// 両方を指定した場合は両方に当てはまる実行だけを残す
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct Retention {
+    /// 新しい方から残す実行の数。記録した直後に全て消してしまわないよう0は指定できない
+    pub keep_runs: Option<NonZeroUsize>,
+    /// 残す期間(月)。30日を1か月として数える。0は指定できない
+    pub keep_months: Option<NonZeroU64>
+}
+
+impl Retention {
+    /// 残す範囲が指定されていればtrue
+    pub fn is_set(&self) -> bool {
+        self.keep_runs.is_some() || self.keep_months.is_some()
+    }
+
+    /// 実行時刻のリスト(古い順)のうち、残す範囲から外れるものを返す
+    /// # Attributes
+    /// * `timestamps` - 実行時刻(UNIX秒)のリスト(古い順)
+    /// * `now` - 現在時刻(UNIX秒)
+    ///
+    /// # Returns
+    /// 削除する実行のインデックス(古い順)
+    pub fn expired(&self, timestamps: &[u64], now: u64) -> Vec<usize> {
+        let keep_from = timestamps.len().saturating_sub(self.keep_runs.map_or(usize::MAX, NonZeroUsize::get));
+        let since = self.keep_months.map(|m| now.saturating_sub(m.get().saturating_mul(SECONDS_PER_MONTH)));
+
+        timestamps.iter().enumerate()
+            .filter(|(i, t)| *i < keep_from || since.is_some_and(|since| **t < since))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// 実行履歴の保存先
+/// 独自のデータベースに履歴を保存したい場合はこのtraitを実装する
+/// 複数のスレッドで共有できるよう`Send + Sync`を要求する
+pub trait HistoryStore: Send + Sync {
+    /// 実行記録を追加する
+    /// # Attributes
+    /// * `run` - 追加する実行記録
+    fn append_run(&mut self, run: RunRecord) -> Result<()>;
+
+    /// 指定した時刻以降に同じチームになった組と、その回数を返す
+    /// # Attributes
+    /// * `since` - この時刻(UNIX秒)以降の実行を対象にする
+    fn pairs_since(&self, since: u64) -> Result<PairCounts>;
+
+    /// 人物ごとのリーダーになった回数を返す
+    fn leader_counts(&self) -> Result<BTreeMap<Arc<str>, usize>>;
+
+    /// 直近の実行記録を返す
+    /// # Attributes
+    /// * `n` - 返す実行記録の数
+    ///
+    /// # Returns
+    /// 直近`n`回分の実行記録(古い順)
+    fn recent_runs(&self, n: usize) -> Result<Vec<RunRecord>>;
+
+    /// 実行IDから実行記録を探す
+    /// # Attributes
+    /// * `run_id` - 実行ID
+    fn find_run(&self, run_id: &str) -> Result<Option<RunRecord>>;
+
+    /// 指定した実行より前の実行記録を返す
+    /// 実行を再現するときに、その実行の時点の履歴だけを使うためのもの
+    /// # Attributes
+    /// * `run_id` - 実行ID
+    ///
+    /// # Returns
+    /// 指定した実行より前の実行記録(古い順)。指定した実行がなければ全ての実行記録
+    fn runs_before(&self, run_id: &str) -> Result<Vec<RunRecord>> {
+        let mut runs = self.recent_runs(usize::MAX)?;
+        runs.truncate(runs.iter().position(|r| r.run_id == run_id).unwrap_or(runs.len()));
+
+        Ok(runs)
+    }
+
+    /// 残す範囲から外れた実行記録を削除する
+    /// # Attributes
+    /// * `retention` - 残す範囲
+    /// * `now` - 現在時刻(UNIX秒)
+    ///
+    /// # Returns
+    /// 削除した実行ID(古い順)
+    fn prune(&mut self, retention: &Retention, now: u64) -> Result<Vec<String>>;
+}
+
+/// ファイルに保存される実行履歴の中身
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct HistoryFile {
+    /// 実行記録のリスト(古い順)
+    #[serde(default)]
+    run: Vec<RunRecord>
+}
+
+/// TOMLファイルに実行履歴を保存する`HistoryStore`
+pub struct FileHistoryStore {
+    /// 履歴ファイルのパス
+    path: PathBuf
+}
+
+impl FileHistoryStore {
+    /// 履歴ファイルのパスを指定して作成する
+    /// ファイルがなければ最初の`append_run`で作成する
+    /// # Attributes
+    /// * `path` - 履歴ファイルのパス
+    pub fn new(path: impl Into<PathBuf>) -> FileHistoryStore {
+        FileHistoryStore { path: path.into() }
+    }
+
+    /// 履歴ファイルを読み込む。ファイルがなければ空の履歴とみなす
+    fn load(&self) -> Result<HistoryFile> {
+        if !self.path.exists() {
+            return Ok(HistoryFile::default());
+        }
+
+        Ok(toml::from_str(&fs::read_to_string(&self.path)?)?)
+    }
+}
+
+impl HistoryStore for FileHistoryStore {
+    fn append_run(&mut self, run: RunRecord) -> Result<()> {
+        let mut history = self.load()?;
+        history.run.push(run);
+        fs::write(&self.path, toml::to_string_pretty(&history)?)?;
+
+        Ok(())
+    }
+
+    fn pairs_since(&self, since: u64) -> Result<PairCounts> {
+        let mut res = BTreeMap::new();
+
+        for run in self.load()?.run.iter().filter(|r| r.timestamp >= since) {
+            for pair in run.pairs() {
+                *res.entry(pair).or_insert(0) += 1;
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn leader_counts(&self) -> Result<BTreeMap<Arc<str>, usize>> {
+        let mut res = BTreeMap::new();
+
+        for run in self.load()?.run {
+            for team in run.teams.borrow_vec() {
+                *res.entry(team.leader.name.clone()).or_insert(0) += 1;
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn recent_runs(&self, n: usize) -> Result<Vec<RunRecord>> {
+        let mut runs = self.load()?.run;
+        let skip = runs.len().saturating_sub(n);
+
+        Ok(runs.split_off(skip))
+    }
+
+    fn find_run(&self, run_id: &str) -> Result<Option<RunRecord>> {
+        Ok(self.load()?.run.into_iter().find(|r| r.run_id == run_id))
+    }
+
+    fn prune(&mut self, retention: &Retention, now: u64) -> Result<Vec<String>> {
+        let mut history = self.load()?;
+        let timestamps: Vec<u64> = history.run.iter().map(|r| r.timestamp).collect();
+        let expired = retention.expired(&timestamps, now);
+        if expired.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut res = Vec::new();
+        let mut i = 0;
+        history.run.retain(|r| {
+            let keep = !expired.contains(&i);
+            if !keep {
+                res.push(r.run_id.clone());
+            }
+            i += 1;
+            keep
+        });
+        fs::write(&self.path, toml::to_string_pretty(&history)?)?;
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+
+    fn record(run_id: &str, timestamp: u64, v: &[(&str, &[&str])]) -> RunRecord {
+        RunRecord {
+            run_id: run_id.to_string(),
+            timestamp,
+            seed: Some(1),
+            audit: None,
+            teams: Teams::from_vec(v.iter().map(|(leader, members)| {
+                let mut team = Team::new(Person::new(*leader));
+                for m in members.iter() {
+                    team.assign(Person::new(*m));
+                }
+                team
+            }).collect())
+        }
+    }
+
+    /// RunRecord#pairsのテスト
+    /// 同じチームの2人の組を名前順で返す
+    #[test]
+    fn run_record_pairs() {
+        let run = record("1", 0, &[("B", &["A", "C"]), ("D", &[])]);
+
+        let pairs = run.pairs();
+        let pairs: Vec<(&str, &str)> = pairs.iter().map(|(a, b)| (&**a, &**b)).collect();
+        assert_eq!(pairs, vec![("A", "B"), ("B", "C"), ("A", "C")]);
+    }
+
+    /// AuditEntry#captureのテスト
+    /// 設定と結果のハッシュ値を記録し、結果が同じであれば同じハッシュ値になる
+    #[test]
+    fn capture_audit_entry() {
+        let run = record("1", 0, &[("A", &["B"])]);
+
+        let audit = AuditEntry::capture(vec!["setting.toml".into()], "num_of_teams = 1", &run.teams).unwrap();
+
+        assert_eq!(audit.args, vec!["setting.toml".to_string()]);
+        assert_eq!(audit.setting_hash, sha256_hex(b"num_of_teams = 1"));
+        assert_eq!(audit.result_hash, result_hash(&record("2", 0, &[("A", &["B"])]).teams).unwrap());
+        assert_ne!(audit.result_hash, result_hash(&record("3", 0, &[("B", &["A"])]).teams).unwrap());
+    }
+
+    /// RunRecord#verify_setting, RunRecord#verify_resultのテスト
+    /// 設定と結果が記録と同じであればOk、異なればそれぞれのエラー
+    #[test]
+    fn verify_replay() {
+        let run = record("1", 0, &[("A", &["B"])]);
+        let audit = AuditEntry::capture(vec![], "num_of_teams = 1", &run.teams).unwrap();
+        let run = RunRecord {audit: Some(audit), ..run};
+
+        assert_eq!(run.replay_seed(), Ok(1));
+        assert_eq!(run.verify_setting("num_of_teams = 1"), Ok(()));
+        assert!(matches!(run.verify_setting("num_of_teams = 2"), Err(ReplayError::SettingMismatch(..))));
+        assert_eq!(run.verify_result(&record("2", 0, &[("A", &["B"])]).teams), Ok(()));
+        assert!(matches!(run.verify_result(&record("2", 0, &[("B", &["A"])]).teams), Err(ReplayError::ResultMismatch(..))));
+        assert_eq!(RunRecord {seed: None, ..run}.replay_seed(), Err(ReplayError::SeedMissing("1".into())));
+    }
+
+    /// FileHistoryStoreのテスト
+    /// 追加した実行記録をファイルに保存し、組とリーダー回数を集計でき、指定した実行より前の記録を取り出せる
+    #[test]
+    fn file_history_store() {
+        let path = std::env::temp_dir().join(format!("guccicci-history-{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut store = FileHistoryStore::new(&path);
+
+        store.append_run(RunRecord {seed: Some(u64::MAX), ..record("1", 100, &[("A", &["B"]), ("C", &["D"])])}).unwrap();
+        let second = record("2", 200, &[("A", &["B"]), ("D", &["C"])]);
+        let audit = AuditEntry::capture(vec!["setting.toml".into()], "", &second.teams).unwrap();
+        store.append_run(RunRecord {audit: Some(audit.clone()), ..second}).unwrap();
+
+        let all = store.pairs_since(0).unwrap();
+        let recent = store.pairs_since(150).unwrap();
+        let leaders = store.leader_counts().unwrap();
+
+        assert_eq!(all.get(&("A".into(), "B".into())), Some(&2));
+        assert_eq!(all.get(&("C".into(), "D".into())), Some(&2));
+        assert_eq!(recent.get(&("A".into(), "B".into())), Some(&1));
+        assert_eq!(leaders.get("A"), Some(&2));
+        assert_eq!(leaders.get("C"), Some(&1));
+        assert_eq!(leaders.get("D"), Some(&1));
+        assert_eq!(store.load().unwrap().run[0].seed, Some(u64::MAX));
+        assert_eq!(store.load().unwrap().run[1].audit, Some(audit));
+        assert_eq!(store.find_run("2").unwrap().map(|r| r.timestamp), Some(200));
+        assert!(store.find_run("3").unwrap().is_none());
+        assert_eq!(store.recent_runs(1).unwrap().iter().map(|r| r.run_id.as_str()).collect::<Vec<&str>>(), vec!["2"]);
+        assert_eq!(store.recent_runs(5).unwrap().len(), 2);
+        assert_eq!(store.runs_before("2").unwrap().iter().map(|r| r.run_id.as_str()).collect::<Vec<&str>>(), vec!["1"]);
+        assert!(store.runs_before("1").unwrap().is_empty());
+        assert_eq!(store.runs_before("3").unwrap().len(), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Retention#expiredのテスト
+    /// 件数と期間の両方に当てはまる実行だけを残す
+    #[test]
+    fn retention_expired() {
+        let now = 10 * SECONDS_PER_MONTH;
+        let timestamps = [now - 5 * SECONDS_PER_MONTH, now - 2 * SECONDS_PER_MONTH, now - SECONDS_PER_MONTH, now];
+
+        assert_eq!(Retention { keep_runs: NonZeroUsize::new(2), keep_months: None }.expired(&timestamps, now), vec![0, 1]);
+        assert_eq!(Retention { keep_runs: None, keep_months: NonZeroU64::new(3) }.expired(&timestamps, now), vec![0]);
+        assert_eq!(Retention { keep_runs: NonZeroUsize::new(3), keep_months: NonZeroU64::new(1) }.expired(&timestamps, now), vec![0, 1]);
+        assert_eq!(Retention::default().expired(&timestamps, now), Vec::<usize>::new());
+        assert!(!Retention::default().is_set());
+    }
+
+    /// Retentionのテスト
+    /// 設定ファイルのkeep_runs・keep_monthsに0を書くとエラー
+    #[test]
+    fn retention_rejects_zero() {
+        assert!(toml::from_str::<Retention>("keep_runs = 0").is_err());
+        assert!(toml::from_str::<Retention>("keep_months = 0").is_err());
+        assert_eq!(toml::from_str::<Retention>("keep_runs = 5").unwrap().keep_runs, NonZeroUsize::new(5));
+    }
+
+    /// FileHistoryStore#pruneのテスト
+    /// 残す範囲から外れた実行記録をファイルから削除する
+    #[test]
+    fn file_history_store_prune() {
+        let path = std::env::temp_dir().join(format!("guccicci-history-prune-{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut store = FileHistoryStore::new(&path);
+        for (i, t) in [100, 200, 300].iter().enumerate() {
+            store.append_run(record(&(i + 1).to_string(), *t, &[("A", &["B"])])).unwrap();
+        }
+
+        assert_eq!(store.prune(&Retention { keep_runs: NonZeroUsize::new(1), keep_months: None }, 300).unwrap(), vec!["1".to_string(), "2".to_string()]);
+        assert_eq!(store.recent_runs(usize::MAX).unwrap().iter().map(|r| r.run_id.as_str()).collect::<Vec<&str>>(), vec!["3"]);
+        assert!(store.prune(&Retention { keep_runs: NonZeroUsize::new(1), keep_months: None }, 300).unwrap().is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{BufRead, Write};
+use anyhow::Result;
+use thiserror::Error;
+use crate::cli::InlineAttendee;
+
+/// 対話モードに関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum InteractiveError {
+    /// 入力が途中で終わった
+    #[error("input ended before all questions were answered")]
+    InputEnded,
+    /// 名前が1つも入力されなかった
+    #[error("enter at least one name")]
+    NoAttendees
+}
+
+/// 対話モードで入力された内容
+#[derive(Debug, PartialEq)]
+pub struct Answers {
+    /// 入力された出席者
+    pub attendees: Vec<InlineAttendee>,
+    /// 入力されたチーム数。`ask_teams`がfalseの場合はNone
+    pub num_of_teams: Option<u8>
+}
+
+/// 名前・リーダー候補・チーム数を順に尋ねる
+/// 名前は1行に1人で、空行で入力を終える。リーダー候補は名前をカンマ区切りで入力し、空行であれば全員をリーダー候補にする
+/// 答えが不正な場合は同じ質問を繰り返す
+/// # Attributes
+/// * `input` - 答えを読む入力
+/// * `output` - 質問を書く出力
+/// * `ask_teams` - チーム数を尋ねるかどうか(`--teams`を指定した場合はfalse)
+///
+/// # Returns
+/// Ok(入力された内容)
+pub fn ask(input: &mut impl BufRead, output: &mut impl Write, ask_teams: bool) -> Result<Answers> {
+    writeln!(output, "Enter names one per line (empty line to finish):")?;
+    let mut names: Vec<String> = Vec::new();
+    loop {
+        let name = read_answer(input, output, "> ")?;
+        if name.is_empty() {
+            break;
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        Err(InteractiveError::NoAttendees)?
+    }
+
+    let leaders: Vec<String> = loop {
+        let answer = read_answer(input, output, "Who can lead? Names separated by commas (empty for everyone): ")?;
+        let leaders: Vec<String> = answer.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
+        match leaders.iter().find(|l| !names.contains(l)) {
+            Some(unknown) => writeln!(output, "{} is not in the names above.", unknown)?,
+            None => break leaders
+        }
+    };
+
+    let num_of_teams = if ask_teams {
+        loop {
+            let answer = read_answer(input, output, "How many teams? ")?;
+            match answer.parse::<u8>() {
+                Ok(n) if n >= 1 => break Some(n),
+                _ => writeln!(output, "Enter a number from 1 to 255.")?
+            }
+        }
+    } else {
+        None
+    };
+
+    let attendees = names.into_iter()
+        .map(|name| InlineAttendee { leader: leaders.contains(&name), name })
+        .collect();
+
+    Ok(Answers { attendees, num_of_teams })
+}
+
+/// 質問を書いて1行の答えを読む
+/// # Returns
+/// Ok(前後の空白を除いた答え)。入力が終わっていればErr<InteractiveError>
+fn read_answer(input: &mut impl BufRead, output: &mut impl Write, question: &str) -> Result<String> {
+    write!(output, "{}", question)?;
+    output.flush()?;
+
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        Err(InteractiveError::InputEnded)?
+    }
+
+    Ok(line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// askのテスト
+    /// 名前・リーダー候補・チーム数を読み、不正な答えは尋ね直す
+    #[test]
+    fn ask_attendees() {
+        let mut output = Vec::new();
+        let answers = ask(&mut Cursor::new("Taro\nHanako\n\nJiro\nHanako\n0\n2\n"), &mut output, true).unwrap();
+
+        assert_eq!(answers, Answers {
+            attendees: vec![
+                InlineAttendee { name: "Taro".into(), leader: false },
+                InlineAttendee { name: "Hanako".into(), leader: true },
+            ],
+            num_of_teams: Some(2)
+        });
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Jiro is not in the names above."));
+        assert!(output.contains("Enter a number from 1 to 255."));
+    }
+
+    /// askのテスト
+    /// --teamsを指定した場合はチーム数を尋ねず、入力が途中で終わればエラー
+    #[test]
+    fn ask_without_teams() {
+        let answers = ask(&mut Cursor::new("Taro\n\n\n"), &mut Vec::new(), false).unwrap();
+        assert_eq!(answers.num_of_teams, None);
+        assert!(!answers.attendees[0].leader);
+
+        let err = ask(&mut Cursor::new("Taro\n"), &mut Vec::new(), true).unwrap_err();
+        assert_eq!(err.downcast_ref::<InteractiveError>(), Some(&InteractiveError::InputEnded));
+        let err = ask(&mut Cursor::new("\n"), &mut Vec::new(), true).unwrap_err();
+        assert_eq!(err.downcast_ref::<InteractiveError>(), Some(&InteractiveError::NoAttendees));
+    }
+}
@@ -1,9 +1,128 @@
+pub mod archive;
+pub mod batch;
+pub mod cache;
+pub mod cli;
+pub mod collation;
+pub mod committee;
+pub mod constraint;
 pub mod domain;
+pub mod formatter;
+pub mod github;
+pub mod hash;
+pub mod history;
+pub mod interactive;
+pub mod locale;
+pub mod log;
+pub mod novelty;
+pub mod pipeline;
+pub mod plan;
+pub mod reroll;
+pub mod roster;
+pub mod seating;
 pub mod strategy;
+pub mod template;
+pub mod warning;
+pub mod watch;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
+use std::fs;
+use std::path::Path;
 use anyhow::Result;
+use thiserror::Error;
+use constraint::Constraint;
 use domain::{Teams, TeamsCreationSetting};
-use strategy::ShuffleStrategies;
+use reroll::{reroll, split, TeamSelector};
+use strategy::{SeededShuffle, ShuffleStrategies};
+use warning::Warning;
+
+/// 設定ファイルの形式に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum SettingFormatError {
+    /// 設定値を上書きする環境変数の値が不正
+    #[error("invalid value for environment variable {0}: {1}")]
+    InvalidEnvValue(String, String)
+}
+
+/// 設定値を上書きする環境変数の接頭辞
+pub const ENV_PREFIX: &str = "GUCCICCI_";
+
+/// 環境変数で上書きできる設定値
+/// `GUCCICCI_NUM_OF_TEAMS`のように、接頭辞の後に設定値の名前を大文字で書く
+pub const ENV_OVERRIDES: &[&str] = &["num_of_teams", "flat", "max_leader_candidates_per_team", "overbooking", "continuity"];
+
+/// 設定ファイルを読み込む
+/// 拡張子によらずTOMLとして読む(YAMLには対応していない)。`attendees_file`の出席者も読み込む
+/// # Attributes
+/// * `path` - 設定ファイルのパス
+/// # Return
+/// Ok(チーム作成設定)
+pub fn load_setting(path: impl AsRef<Path>) -> Result<TeamsCreationSetting> {
+    let path = path.as_ref();
+    let mut setting: TeamsCreationSetting = toml::from_str(&fs::read_to_string(path)?)?;
+    setting.inline_attendees_file(path.parent().unwrap_or(Path::new("")))?;
+
+    Ok(setting)
+}
+
+/// 複数の設定ファイルの内容を前から順にまとめる
+/// 両方にある配列(`attendees`・`tables`など)は前の内容の後ろに後の内容をつなげ、
+/// 両方にあるテーブル(`[output]`・`[retention]`など)は中の設定値ごとに同じようにまとめ、それ以外の設定値は後の内容で上書きする
+/// # Attributes
+/// * `contents` - 設定ファイルの内容(TOML)のリスト
+/// # Return
+/// Ok(まとめた設定ファイルの内容)
+pub fn merge_settings(contents: &[String]) -> Result<String> {
+    let mut merged = toml::value::Table::new();
+
+    for content in contents {
+        merge_table(&mut merged, toml::from_str(content)?);
+    }
+
+    // `Value`であれば配列のテーブルより前に値を出力する
+    Ok(toml::to_string(&toml::Value::Table(merged))?)
+}
+
+/// `table`の設定値を`base`にまとめる。まとめ方は`merge_settings`と同じ
+fn merge_table(base: &mut toml::value::Table, table: toml::value::Table) {
+    for (key, value) in table {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Array(base)), toml::Value::Array(values)) => base.extend(values),
+            (Some(toml::Value::Table(base)), toml::Value::Table(table)) => merge_table(base, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// 環境変数から設定値の上書きを作る
+/// `ENV_OVERRIDES`にない名前の環境変数は無視する
+/// # Attributes
+/// * `vars` - (環境変数名, 値)のリスト
+/// # Return
+/// Ok(上書きする設定値(TOML))。上書きする環境変数がなければNone
+pub fn env_overrides(vars: impl IntoIterator<Item = (String, String)>) -> Result<Option<String>> {
+    let mut table = toml::value::Table::new();
+
+    for (name, raw) in vars {
+        let key = match name.strip_prefix(ENV_PREFIX).map(str::to_ascii_lowercase) {
+            Some(key) if ENV_OVERRIDES.contains(&key.as_str()) => key,
+            _ => continue
+        };
+        let value = match key.as_str() {
+            "flat" | "overbooking" => raw.trim().parse().ok().map(toml::Value::Boolean),
+            "continuity" => raw.trim().parse().ok().map(toml::Value::Float),
+            _ => raw.trim().parse().ok().map(toml::Value::Integer)
+        };
+        table.insert(key, value.ok_or_else(|| SettingFormatError::InvalidEnvValue(name.clone(), raw.clone()))?);
+    }
+
+    if table.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(toml::to_string(&toml::Value::Table(table))?))
+}
 
 /// チーム作成を実行する
 /// # Attributes
@@ -16,3 +135,203 @@ pub fn run(setting: TeamsCreationSetting ) -> Result<Teams> {
     Ok(teams)
 }
 
+/// シード値を指定してチーム作成を実行する
+/// 同じ設定・同じシード値であれば常に同じチームが作成される
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `seed` - シード値
+/// # Return
+/// Ok(作成されたチーム)
+pub fn run_with_seed(setting: TeamsCreationSetting, seed: u64) -> Result<Teams> {
+    let teams = Teams::create(setting, &SeededShuffle::new(seed))?;
+
+    Ok(teams)
+}
+
+/// 制約を満たすチーム作成を実行する
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `seed` - シード値。Noneであれば毎回ランダム
+/// * `constraints` - 必ず守る制約
+/// # Return
+/// Ok(作成されたチーム)
+pub fn run_with_constraints(setting: TeamsCreationSetting, seed: Option<u64>, constraints: &[Box<dyn Constraint>]) -> Result<Teams> {
+    let teams = match seed {
+        Some(seed) => Teams::create_with_constraints(setting, &SeededShuffle::new(seed), constraints)?,
+        None => Teams::create_with_constraints(setting, &ShuffleStrategies::RandomShuffle, constraints)?
+    };
+
+    Ok(teams)
+}
+
+/// 制約を満たすチーム作成を実行し、警告もあわせて返す
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `seed` - シード値。Noneであれば毎回ランダム
+/// * `constraints` - 必ず守る制約
+/// # Return
+/// Ok((作成されたチーム, 実行を止めるほどではない問題のリスト))
+pub fn run_with_warnings(setting: TeamsCreationSetting, seed: Option<u64>, constraints: &[Box<dyn Constraint>]) -> Result<(Teams, Vec<Warning>)> {
+    let checked = setting.clone();
+    let teams = run_with_constraints(setting, seed, constraints)?;
+    let warnings = warning::check(&checked, &teams);
+
+    Ok((teams, warnings))
+}
+
+/// 前回の結果のうち1チームだけを作り直す
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `previous` - 前回の結果
+/// * `selector` - 作り直すチーム
+/// * `seed` - シード値。Noneであれば毎回ランダム
+/// # Return
+/// Ok(1チームだけ作り直した結果)
+pub fn run_reroll(setting: &TeamsCreationSetting, previous: &Teams, selector: &TeamSelector, seed: Option<u64>) -> Result<Teams> {
+    match seed {
+        Some(seed) => reroll(previous, selector, setting, &SeededShuffle::new(seed)),
+        None => reroll(previous, selector, setting, &ShuffleStrategies::RandomShuffle)
+    }
+}
+
+/// 結果の1チームを複数のチームに分割する
+/// # Attributes
+/// * `previous` - 結果
+/// * `selector` - 分割するチーム
+/// * `into` - 分割後のチーム数
+/// * `setting` - チーム作成設定。指定されていればリーダー候補だけを新しいリーダーにする
+/// * `seed` - シード値。Noneであれば毎回ランダム
+/// # Return
+/// Ok(1チームを分割した結果)
+pub fn run_split(previous: &Teams, selector: &TeamSelector, into: u8, setting: Option<&TeamsCreationSetting>, seed: Option<u64>) -> Result<Teams> {
+    match seed {
+        Some(seed) => split(previous, selector, into, setting, &SeededShuffle::new(seed)),
+        None => split(previous, selector, into, setting, &ShuffleStrategies::RandomShuffle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTING: &str = r#"
+num_of_teams = 2
+
+[[attendees]]
+leader = true
+person = { name = "A" }
+
+[[attendees]]
+leader = true
+person = { name = "B" }
+
+[[attendees]]
+person = { name = "C" }
+
+[[attendees]]
+person = { name = "D" }
+
+[[attendees]]
+person = { name = "E" }
+"#;
+
+    /// merge_settingsのテスト
+    /// 配列はつなげ、それ以外は後の設定ファイルで上書きする
+    #[test]
+    fn merge_setting_files() {
+        let attendees = "num_of_teams = 1\n\n[[attendees]]\nleader = true\nperson = { name = \"A\" }\n".to_string();
+        let event = "num_of_teams = 2\n\n[[attendees]]\nleader = true\nperson = { name = \"B\" }\n".to_string();
+
+        let merged: TeamsCreationSetting = toml::from_str(&merge_settings(&[attendees, event]).unwrap()).unwrap();
+
+        assert_eq!(merged.all_people().iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["A", "B"]);
+        assert!(merged.validate().is_ok());
+        assert_eq!(merged.analyze().num_of_teams, 2);
+    }
+
+    /// merge_settingsのテスト
+    /// 両方にあるテーブルは置き換えずに中の設定値ごとにまとめる
+    #[test]
+    fn merge_setting_files_nested_tables() {
+        let base = "num_of_teams = 1\n\n[output.org]\nheading_level = 2\n\n[retention]\nkeep_runs = 10\nkeep_months = 6\n\n[[attendees]]\nleader = true\nperson = { name = \"A\" }\n".to_string();
+        let event = "[output.summary]\nnotes = false\n\n[retention]\nkeep_runs = 3\n\n[[attendees]]\nperson = { name = \"B\" }\n".to_string();
+
+        let merged: TeamsCreationSetting = toml::from_str(&merge_settings(&[base, event]).unwrap()).unwrap();
+
+        assert_eq!(merged.output().org.heading(), "**");
+        assert!(!merged.output().summary.includes_notes());
+        assert_eq!(merged.retention().and_then(|r| r.keep_runs).map(|n| n.get()), Some(3));
+        assert_eq!(merged.retention().and_then(|r| r.keep_months).map(|n| n.get()), Some(6));
+        assert_eq!(merged.all_people().iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["A", "B"]);
+    }
+
+    /// load_settingのテスト
+    /// attendees_fileの出席者を設定ファイルからの相対パスで読み込んで加える
+    #[test]
+    fn load_setting_with_attendees_file() {
+        let dir = std::env::temp_dir().join(format!("guccicci-attendees-file-{}", std::process::id()));
+        fs::create_dir_all(dir.join("roster")).unwrap();
+        fs::write(dir.join("roster/people.toml"), "[[attendees]]\nleader = true\nperson = { name = \"B\" }\n").unwrap();
+        fs::write(dir.join("roster/names.txt"), "C*\nD\n").unwrap();
+        fs::write(dir.join("setting.toml"), "attendees_file = \"roster/people.toml\"\nnum_of_teams = 2\n\n[[attendees]]\nleader = true\nperson = { name = \"A\" }\n").unwrap();
+        fs::write(dir.join("names.toml"), "attendees_file = \"roster/names.txt\"\nnum_of_teams = 1\n").unwrap();
+
+        let setting = load_setting(dir.join("setting.toml")).unwrap();
+        assert_eq!(setting.all_people().iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["A", "B"]);
+        assert!(setting.validate().is_ok());
+
+        let setting = load_setting(dir.join("names.toml")).unwrap();
+        assert_eq!(setting.leader_candidates().iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["C"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// env_overridesのテスト
+    /// GUCCICCI_で始まる環境変数で設定ファイルの値を上書きし、対象外の環境変数は無視する
+    #[test]
+    fn override_setting_by_env() {
+        let vars = |v: &[(&str, &str)]| v.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<_>>();
+        let file = "num_of_teams = 1\n\n[[attendees]]\nperson = { name = \"A\" }\n\n[[attendees]]\nperson = { name = \"B\" }\n".to_string();
+        let overrides = env_overrides(vars(&[("GUCCICCI_NUM_OF_TEAMS", "2"), ("GUCCICCI_FLAT", "true"), ("GUCCICCI_UPDATE_SNAPSHOTS", "1"), ("HOME", "/root")])).unwrap().unwrap();
+
+        let merged: TeamsCreationSetting = toml::from_str(&merge_settings(&[file, overrides]).unwrap()).unwrap();
+
+        assert_eq!(merged.analyze().num_of_teams, 2);
+        assert!(merged.is_flat());
+        assert_eq!(env_overrides(vars(&[("HOME", "/root")])).unwrap(), None);
+        assert_eq!(
+            env_overrides(vars(&[("GUCCICCI_FLAT", "yes")])).unwrap_err().downcast_ref::<SettingFormatError>(),
+            Some(&SettingFormatError::InvalidEnvValue("GUCCICCI_FLAT".into(), "yes".into()))
+        );
+    }
+
+    /// エンジンの型がスレッド間で共有できること(コンパイルが通ればよい)
+    #[test]
+    fn engine_types_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<TeamsCreationSetting>();
+        assert_send_sync::<Teams>();
+        assert_send_sync::<SeededShuffle>();
+        assert_send_sync::<ShuffleStrategies>();
+        assert_send_sync::<Box<dyn Constraint>>();
+        assert_send_sync::<Box<dyn history::HistoryStore>>();
+        assert_send_sync::<history::FileHistoryStore>();
+        assert_send_sync::<pipeline::Guccicci<'static>>();
+        assert_send_sync::<formatter::FormatOptions>();
+        assert_send_sync::<cache::ResultCache>();
+    }
+
+    /// run_with_seedのテスト
+    /// 同じ設定・同じシード値であれば出力が1バイトも変わらない
+    #[test]
+    fn same_seed_produces_identical_output() {
+        let first = run_with_seed(toml::from_str(SETTING).unwrap(), 42).unwrap();
+        let second = run_with_seed(toml::from_str(SETTING).unwrap(), 42).unwrap();
+
+        assert_eq!(
+            toml::to_string_pretty(&first).unwrap(),
+            toml::to_string_pretty(&second).unwrap()
+        );
+    }
+}
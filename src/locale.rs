@@ -0,0 +1,108 @@
+use std::str::FromStr;
+use thiserror::Error;
+
+/// ロケールに関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum LocaleError {
+    /// 未知のロケール
+    #[error("unknown locale {0}")]
+    UnknownLocale(String)
+}
+
+/// 出力に使う言語
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Locale {
+    /// 英語(デフォルト)
+    #[default]
+    En,
+    /// 日本語
+    Ja
+}
+
+/// 出力に使うラベルのカタログ
+#[derive(Debug, PartialEq)]
+pub struct Labels {
+    /// チーム
+    pub team: &'static str,
+    /// リーダー
+    pub leader: &'static str,
+    /// メンバー
+    pub member: &'static str,
+    /// メモ
+    pub notes: &'static str,
+    /// アジェンダ
+    pub agenda: &'static str
+}
+
+/// 英語のラベル
+const EN: Labels = Labels {
+    team: "Team",
+    leader: "Leader",
+    member: "Member",
+    notes: "Notes",
+    agenda: "Agenda"
+};
+
+/// 日本語のラベル
+const JA: Labels = Labels {
+    team: "チーム",
+    leader: "リーダー",
+    member: "メンバー",
+    notes: "メモ",
+    agenda: "アジェンダ"
+};
+
+impl Locale {
+    /// このロケールのラベルを返す
+    pub fn labels(&self) -> &'static Labels {
+        match self {
+            Self::En => &EN,
+            Self::Ja => &JA
+        }
+    }
+
+    /// チームの表示名を返す
+    /// # Attributes
+    /// * `number` - チームの番号(1始まり)
+    ///
+    /// # Returns
+    /// 英語であれば`Team 1`、日本語であれば`チーム1`
+    pub fn team_name(&self, number: usize) -> String {
+        match self {
+            Self::En => format!("{} {}", EN.team, number),
+            Self::Ja => format!("{}{}", JA.team, number)
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = LocaleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "ja" => Ok(Self::Ja),
+            _ => Err(LocaleError::UnknownLocale(s.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Locale#team_nameのテスト
+    #[test]
+    fn team_name() {
+        assert_eq!(Locale::En.team_name(1), "Team 1");
+        assert_eq!(Locale::Ja.team_name(2), "チーム2");
+    }
+
+    /// Locale#from_strのテスト
+    #[test]
+    fn parse_locale() {
+        assert_eq!("en".parse(), Ok(Locale::En));
+        assert_eq!("ja".parse(), Ok(Locale::Ja));
+        assert_eq!("fr".parse::<Locale>(), Err(LocaleError::UnknownLocale("fr".into())));
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt::Write as _;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// ログ形式に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum LogFormatError {
+    /// 未知のログ形式
+    #[error("unknown log format {0}")]
+    UnknownFormat(String)
+}
+
+/// ログの出力形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// `key=value`を並べたテキスト(logfmt)
+    Text,
+    /// 1行1オブジェクトのJSON
+    Json
+}
+
+impl FromStr for LogFormat {
+    type Err = LogFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(LogFormatError::UnknownFormat(s.to_string()))
+        }
+    }
+}
+
+/// ログのレベル
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error"
+        }
+    }
+}
+
+/// ログに付ける値
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogValue {
+    Str(String),
+    UInt(u64),
+    Bool(bool)
+}
+
+impl From<&str> for LogValue {
+    fn from(v: &str) -> Self {
+        Self::Str(v.to_string())
+    }
+}
+
+impl From<String> for LogValue {
+    fn from(v: String) -> Self {
+        Self::Str(v)
+    }
+}
+
+impl From<u64> for LogValue {
+    fn from(v: u64) -> Self {
+        Self::UInt(v)
+    }
+}
+
+impl From<bool> for LogValue {
+    fn from(v: bool) -> Self {
+        Self::Bool(v)
+    }
+}
+
+/// 標準エラー出力に構造化ログを書き出す
+/// 形式が指定されていなければ何も出力しない
+pub struct Logger {
+    /// ログの出力形式
+    format: Option<LogFormat>
+}
+
+impl Logger {
+    /// ログ形式を指定して作成する
+    /// # Attributes
+    /// * `format` - ログの出力形式。Noneであればログを出力しない
+    pub fn new(format: Option<LogFormat>) -> Logger {
+        Logger { format }
+    }
+
+    /// ログを出力するかどうか
+    pub fn enabled(&self) -> bool {
+        self.format.is_some()
+    }
+
+    /// infoレベルのログを出力する
+    /// # Attributes
+    /// * `msg` - メッセージ
+    /// * `fields` - ログに付ける値
+    pub fn info(&self, msg: &str, fields: &[(&str, LogValue)]) {
+        self.write(Level::Info, msg, fields);
+    }
+
+    /// warnレベルのログを出力する
+    /// # Attributes
+    /// * `msg` - メッセージ
+    /// * `fields` - ログに付ける値
+    pub fn warn(&self, msg: &str, fields: &[(&str, LogValue)]) {
+        self.write(Level::Warn, msg, fields);
+    }
+
+    /// errorレベルのログを出力する
+    /// # Attributes
+    /// * `msg` - メッセージ
+    /// * `fields` - ログに付ける値
+    pub fn error(&self, msg: &str, fields: &[(&str, LogValue)]) {
+        self.write(Level::Error, msg, fields);
+    }
+
+    fn write(&self, level: Level, msg: &str, fields: &[(&str, LogValue)]) {
+        if let Some(format) = self.format {
+            let ts = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+            eprintln!("{}", line(format, ts, level, msg, fields));
+        }
+    }
+}
+
+/// ログ1行分の文字列を作る
+/// # Attributes
+/// * `format` - ログの出力形式
+/// * `ts` - 時刻(UNIXミリ秒)
+/// * `level` - ログのレベル
+/// * `msg` - メッセージ
+/// * `fields` - ログに付ける値
+fn line(format: LogFormat, ts: u64, level: Level, msg: &str, fields: &[(&str, LogValue)]) -> String {
+    let mut all: Vec<(&str, LogValue)> = vec![
+        ("ts", ts.into()),
+        ("level", level.as_str().into()),
+        ("msg", msg.into()),
+    ];
+    all.extend(fields.iter().cloned());
+
+    match format {
+        LogFormat::Text => all.iter()
+            .map(|(k, v)| match v {
+                LogValue::Str(s) if s.is_empty() || s.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') => {
+                    format!("{}={:?}", k, s)
+                },
+                LogValue::Str(s) => format!("{}={}", k, s),
+                LogValue::UInt(n) => format!("{}={}", k, n),
+                LogValue::Bool(b) => format!("{}={}", k, b)
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+        LogFormat::Json => {
+            let body = all.iter()
+                .map(|(k, v)| match v {
+                    LogValue::Str(s) => format!("{}:{}", json_string(k), json_string(s)),
+                    LogValue::UInt(n) => format!("{}:{}", json_string(k), n),
+                    LogValue::Bool(b) => format!("{}:{}", json_string(k), b)
+                })
+                .collect::<Vec<String>>()
+                .join(",");
+            format!("{{{}}}", body)
+        }
+    }
+}
+
+/// JSONの文字列リテラルにする
+/// # Attributes
+/// * `s` - 文字列
+pub(crate) fn json_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(res, "\\u{:04x}", c as u32);
+            },
+            c => res.push(c)
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// lineのテスト
+    /// JSON形式では1行1オブジェクトで値を出力し、文字列はエスケープする
+    #[test]
+    fn json_line() {
+        let fields = [("run_id", LogValue::from("1-a")), ("seed", 42u64.into()), ("ok", true.into()), ("error", "say \"hi\"\n".into())];
+
+        assert_eq!(
+            line(LogFormat::Json, 1000, Level::Info, "run finished", &fields),
+            r#"{"ts":1000,"level":"info","msg":"run finished","run_id":"1-a","seed":42,"ok":true,"error":"say \"hi\"\n"}"#
+        );
+    }
+
+    /// lineのテスト
+    /// テキスト形式ではkey=valueを並べ、空白を含む文字列は引用符で囲む。レベルはinfo・warn・errorのいずれか
+    #[test]
+    fn text_line() {
+        let fields = [("run_id", LogValue::from("1-a")), ("seed", 42u64.into())];
+
+        assert_eq!(
+            line(LogFormat::Text, 1000, Level::Error, "validation failed", &fields),
+            r#"ts=1000 level=error msg="validation failed" run_id=1-a seed=42"#
+        );
+        assert_eq!(
+            line(LogFormat::Text, 1000, Level::Warn, "warning", &[("warning", "attendee A appears more than once".into())]),
+            r#"ts=1000 level=warn msg=warning warning="attendee A appears more than once""#
+        );
+    }
+
+    /// LogFormat#from_strのテスト
+    #[test]
+    fn parse_log_format() {
+        assert_eq!("text".parse(), Ok(LogFormat::Text));
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert_eq!("xml".parse::<LogFormat>(), Err(LogFormatError::UnknownFormat("xml".into())));
+    }
+}
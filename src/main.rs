@@ -2,21 +2,451 @@ extern crate guccicci;
 
 use std::env;
 use std::fs;
-use guccicci::domain::TeamsCreationSetting;
-use guccicci::run;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use guccicci::archive::{archive_result, prune_archive};
+use guccicci::batch::BatchFile;
+use guccicci::cache::ResultCache;
+use guccicci::cli::{shell_quote, CliOptions, Command, STDIN};
+use guccicci::interactive;
+use guccicci::history::{FileHistoryStore, HistoryStore, ReplayError, Retention};
+use guccicci::domain::{Teams, TeamsCreationSetting};
+use guccicci::formatter::FormatOptions;
+use guccicci::github;
+use guccicci::log::{LogValue, Logger};
+use guccicci::novelty::ScoreError;
+use guccicci::pipeline::{Generated, Guccicci, Strategy};
+use guccicci::plan::diff;
+use guccicci::roster::{load_roster, read_roster_text};
+use guccicci::{env_overrides, merge_settings, run_split};
+use guccicci::reroll::{remove_absent, TeamSelector};
+use guccicci::watch::{SettingWatcher, WATCH_INTERVAL};
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// `--min-score`を満たす結果が見つからなかったときの終了コード
+const SCORE_EXIT_CODE: u8 = 3;
 
-    if args.len() < 2 {
-        // 引数に設定ファイル名が指定されていない
-        panic!("argument setting file name is required");
+fn main() -> ExitCode {
+    match run_command() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            if e.downcast_ref::<ScoreError>().is_some() {
+                ExitCode::from(SCORE_EXIT_CODE)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+/// コマンドライン引数に従って実行する
+fn run_command() -> Result<()> {
+    let mut options = CliOptions::parse(env::args().skip(1))?;
+
+    let logger = Logger::new(options.log_format);
+
+    match &options.command {
+        Command::Split { result_path, team, into } => return split_result(&options, result_path, team, *into),
+        Command::Patch { result_path, absent } => return patch_result(&options, result_path, absent),
+        Command::Batch { batch_path } => return run_batch(&options, batch_path),
+        Command::PruneHistory => return prune_history(&options),
+        _ => {}
+    }
+
+    if options.interactive {
+        let answers = interactive::ask(&mut io::stdin().lock(), &mut io::stderr(), options.num_of_teams.is_none())?;
+        options.attendees.extend(answers.attendees);
+        options.num_of_teams = options.num_of_teams.or(answers.num_of_teams);
+    }
+    if options.watch {
+        return watch(&options, &logger);
+    }
+
+    run_setting(&options, &logger)
+}
+
+/// 設定ファイルを読んで、サブコマンドに従ってチームを作成する
+fn run_setting(options: &CliOptions, logger: &Logger) -> Result<()> {
+    let mut contents = options.base_settings.iter().map(|path| read_setting(path)).collect::<Result<Vec<String>>>()?;
+    if !options.setting_path.is_empty() {
+        contents.push(read_setting(&options.setting_path)?);
+    }
+    contents.extend(env_overrides(env::vars())?);
+    contents.extend(options.inline_setting());
+    let mut setting_str = match contents.len() {
+        1 => contents.remove(0),
+        _ => merge_settings(&contents)?
+    };
+    if let Some(template) = options.template {
+        setting_str = template.apply(&setting_str)?;
+    }
+    let mut setting: TeamsCreationSetting = toml::from_str(&setting_str)?;
+    setting.inline_attendees_file(setting_dir(&options.setting_path))?;
+    if let Some(path) = &options.names_file {
+        setting.add_attendees(load_roster(Path::new(path))?);
+    }
+    if options.setting_path.is_empty() {
+        setting.default_to_flat();
+    }
+    setting.exclude_leaders(&options.no_lead)?;
+    setting.resolve_target_size()?;
+    let format_options = FormatOptions { tables: setting.tables().to_vec(), output: setting.output().clone(), ..options.format_options.clone() };
+    let retention = retention(options, Some(&setting));
+
+    match &options.command {
+        Command::Run => {
+            let generated = generate(setting, &setting_str, recorded_seed(options), options, logger, retention)?;
+            record(options, &generated, retention)?;
+            let output = options.format.render(&generated.teams, &format_options)?;
+            print!("{}", output);
+            post(options, &output)?;
+        },
+        Command::Plan { result_path } => {
+            // applyで同じ結果を再現できるようにシード値を決めておく
+            let generated = generate(setting, &setting_str, Some(options.seed.unwrap_or_else(rand::random)), options, logger, None)?;
+            let seed = generated.seed.unwrap_or_default();
+            let previous = load_result(result_path)?;
+            let changes = diff(&previous, &generated.teams);
+
+            if changes.is_empty() {
+                println!("No changes.");
+            }
+            for change in changes {
+                println!("{}", change);
+            }
+            println!();
+            let apply: Vec<String> = options.apply_args(&env::args().skip(1).collect::<Vec<String>>(), seed).iter().map(|a| shell_quote(a)).collect();
+            println!("To apply: guccicci {}", apply.join(" "));
+        },
+        Command::Apply { result_path } => {
+            let generated = generate(setting, &setting_str, recorded_seed(options), options, logger, retention)?;
+            fs::write(result_path, toml::to_string_pretty(&generated.teams)?)?;
+            record(options, &generated, retention)?;
+            let output = options.format.render(&generated.teams, &format_options)?;
+            print!("{}", output);
+            post(options, &output)?;
+        },
+        Command::Replay { run_id, history_path } => {
+            let recorded = FileHistoryStore::new(history_path)
+                .find_run(run_id)?
+                .ok_or_else(|| ReplayError::RunNotFound(run_id.clone()))?;
+            let seed = recorded.replay_seed()?;
+            recorded.verify_setting(&setting_str)?;
+
+            let generated = generate(setting, &setting_str, Some(seed), options, logger, None)?;
+            recorded.verify_result(&generated.teams)?;
+
+            eprintln!("Run {} replayed with seed {}: result matches the record.", run_id, seed);
+            print!("{}", options.format.render(&generated.teams, &format_options)?);
+        },
+        Command::Split { .. } | Command::Patch { .. } | Command::Batch { .. } | Command::PruneHistory => {
+            unreachable!("split, patch, batch and history prune are handled before reading the setting")
+        }
+    }
+
+    Ok(())
+}
+
+/// 設定ファイルが変わるたびにチームを作り直して出力する。Ctrl-Cで止めるまで終わらない
+/// 設定ファイルが不正なときはエラーを表示して次の変更を待つ
+fn watch(options: &CliOptions, logger: &Logger) -> Result<()> {
+    let paths = options.base_settings.iter().chain(std::iter::once(&options.setting_path)).map(PathBuf::from).collect();
+    let mut watcher = SettingWatcher::new(paths);
+
+    loop {
+        if let Err(e) = run_setting(options, logger) {
+            eprintln!("Error: {:?}", e);
+        }
+        eprintln!("Watching {} for changes. Press Ctrl-C to stop.", options.setting_path);
+        watcher.wait(WATCH_INTERVAL);
+        println!();
+    }
+}
+
+/// 結果ファイルの1チームを分割して保存する
+/// 設定ファイルが指定されていれば、そのリーダー候補だけを新しいリーダーにする
+fn split_result(options: &CliOptions, result_path: &str, team: &TeamSelector, into: u8) -> Result<()> {
+    let setting = optional_setting(options)?;
+
+    let res = run_split(&load_result(result_path)?, team, into, setting.as_ref(), options.seed)?;
+    fs::write(result_path, toml::to_string_pretty(&res)?)?;
+    print!("{}", options.format.render(&res, &options.format_options)?);
+
+    Ok(())
+}
+
+/// 結果ファイルから欠席者を除いて保存する
+/// 設定ファイルが指定されていれば、そのリーダー候補だけを新しいリーダーにする
+fn patch_result(options: &CliOptions, result_path: &str, absent: &[String]) -> Result<()> {
+    let setting = optional_setting(options)?;
+
+    let res = remove_absent(&load_result(result_path)?, absent, setting.as_ref())?;
+    fs::write(result_path, toml::to_string_pretty(&res)?)?;
+    print!("{}", options.format.render(&res, &options.format_options)?);
+
+    Ok(())
+}
+
+/// バッチファイルの全ての会のチームを作成し、会ごとの結果のまとめを表示する
+fn run_batch(options: &CliOptions, batch_path: &str) -> Result<()> {
+    let path = Path::new(batch_path);
+    let results = BatchFile::load(path)?.run(path.parent().unwrap_or(Path::new("")), options.seed, options.format, &options.format_options);
+
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(summary) => println!("{}: {} teams, {} people -> {}", name, summary.num_of_teams, summary.num_of_people, summary.output.display()),
+            Err(e) => {
+                failed += 1;
+                println!("{}: failed: {}", name, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} events failed", failed, results.len());
+    }
+
+    Ok(())
+}
+
+/// 実行履歴・アーカイブから残す範囲を外れた実行を削除する
+/// 残す範囲は`--keep-runs`・`--keep-months`、指定されていなければ設定ファイルの`retention`を使う
+fn prune_history(options: &CliOptions) -> Result<()> {
+    let setting = optional_setting(options)?;
+    let retention = match retention(options, setting.as_ref()) {
+        Some(retention) => retention,
+        None => anyhow::bail!("retention is not set: use --keep-runs, --keep-months or [retention] in the setting")
+    };
+
+    prune(options, &retention)
+}
+
+/// 今回の実行で使う残す範囲。`--keep-runs`・`--keep-months`を設定ファイルの`retention`より優先する
+fn retention(options: &CliOptions, setting: Option<&TeamsCreationSetting>) -> Option<Retention> {
+    if options.retention.is_set() {
+        return Some(options.retention);
+    }
+
+    setting.and_then(|s| s.retention()).filter(|r| r.is_set())
+}
+
+/// `--history`・`--archive`から残す範囲を外れた実行を削除し、削除した数を表示する
+fn prune(options: &CliOptions, retention: &Retention) -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    if let Some(path) = &options.history {
+        let removed = FileHistoryStore::new(path).prune(retention, now)?;
+        eprintln!("Pruned {} runs from history {}", removed.len(), path);
+    }
+    if let Some(repo) = &options.archive {
+        let removed = prune_archive(Path::new(repo), retention, now)?;
+        eprintln!("Pruned {} runs from archive {}", removed.len(), repo);
+    }
+
+    Ok(())
+}
+
+/// split・patchで省略できる設定ファイルを読む
+fn optional_setting(options: &CliOptions) -> Result<Option<TeamsCreationSetting>> {
+    if options.setting_path.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&read_setting(&options.setting_path)?)?))
+}
+
+/// 設定ファイルを読む。パスが`-`であれば標準入力から読む
+fn read_setting(path: &str) -> Result<String> {
+    if path == STDIN {
+        let mut res = String::new();
+        io::stdin().read_to_string(&mut res)?;
+        return Ok(res);
+    }
+
+    Ok(fs::read_to_string(path)?)
+}
+
+/// 設定ファイルの相対パスの基準にするディレクトリ
+/// 標準入力から読む場合はカレントディレクトリ
+fn setting_dir(setting_path: &str) -> &Path {
+    Path::new(setting_path).parent().unwrap_or(Path::new(""))
+}
+
+/// 実行に使うシード値を決める
+/// アーカイブ・履歴・ログに記録する場合は後から再現できるよう、指定がなくてもシード値を決めておく
+fn recorded_seed(options: &CliOptions) -> Option<u64> {
+    if options.archive.is_some() || options.history.is_some() || options.log_format.is_some() {
+        Some(options.seed.unwrap_or_else(rand::random))
+    } else {
+        options.seed
+    }
+}
+
+/// `--github-issue`・`--github-comment`が指定されていれば、出力した結果をGitHubのissueに投稿する
+fn post(options: &CliOptions, output: &str) -> Result<()> {
+    if let Some(target) = &options.github {
+        eprintln!("Posted to GitHub: {}", github::post(target, output)?);
     }
 
-    let setting_filename = &args[1];
-    let setting_str = fs::read_to_string(setting_filename).unwrap();
-    let setting: TeamsCreationSetting = toml::from_str(&setting_str).unwrap();
+    Ok(())
+}
+
+/// `--archive`が指定されていれば結果を記録し、`--history`に記録した結果の新鮮さを表示する
+/// 残す範囲が指定されていれば、記録した後に範囲を外れた実行を削除する
+fn record(options: &CliOptions, generated: &Generated, retention: Option<Retention>) -> Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    if let (Some(repo), Some(run_id), Some(seed)) = (&options.archive, &generated.run_id, generated.seed) {
+        archive_result(Path::new(repo), run_id, seed, &toml::to_string_pretty(&generated.teams)?)?;
+    }
+    if let Some(novelty) = &generated.novelty {
+        eprint!("{}", novelty.summary(options.format_options.locale, now));
+    }
+    if let Some(retention) = retention {
+        if let Some(path) = &options.history {
+            eprintln!("Pruned {} runs from history {}", generated.pruned.len(), path);
+        }
+        if let Some(repo) = &options.archive {
+            let removed = prune_archive(Path::new(repo), &retention, now)?;
+            eprintln!("Pruned {} runs from archive {}", removed.len(), repo);
+        }
+    }
+
+    Ok(())
+}
+
+/// 設定とオプションに従ってチームを作成し、ログ・警告・人数を1回だけ出力する
+/// 制約・前回の結果・新鮮さの下限・キャッシュ・履歴への記録は`Guccicci`で行う
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `setting_str` - 設定ファイルの内容
+/// * `seed` - シード値。Noneであれば毎回ランダム
+/// * `options` - コマンドラインオプション
+/// * `logger` - ロガー
+/// * `retention` - 履歴に記録した後に残す範囲
+fn generate(setting: TeamsCreationSetting, setting_str: &str, seed: Option<u64>, options: &CliOptions, logger: &Logger, retention: Option<Retention>) -> Result<Generated> {
+    let started = Instant::now();
+    let mut fields: Vec<(&str, LogValue)> = vec![("setting", options.setting_path.as_str().into())];
+    logger.info("run started", &fields);
+
+    if let Err(e) = setting.validate() {
+        fields.push(("error", e.to_string().into()));
+        logger.error("validation failed", &fields);
+        Err(e)?
+    }
+    logger.info("validation passed", &fields);
+
+    let setting_target_size = setting.target_size();
+    let cacheable = options.avoid_recent_pairs.is_none()
+        && options.max_consecutive_leads.is_none()
+        && options.reroll_team.is_none()
+        && setting.continuity().is_none();
+    let cache_content = match (&options.cache, options.seed) {
+        (Some(_), Some(_)) if cacheable => Some(cache_content(&setting, setting_str, options)?),
+        _ => None
+    };
+
+    // replayの`history_path`は`--history`と同じ
+    let mut store = options.history.as_ref().map(FileHistoryStore::new);
+    let mut pipeline = Guccicci::new().setting(setting).strategy(seed.map_or(Strategy::Random, Strategy::Seeded));
+    if let Some(store) = store.as_mut() {
+        pipeline = pipeline.history(store);
+    }
+    if let Some(n) = options.avoid_recent_pairs {
+        pipeline = pipeline.avoid_recent_pairs(n);
+    }
+    if let Some(max) = options.max_consecutive_leads {
+        pipeline = pipeline.max_consecutive_leads(max);
+    }
+    if let Some(min_score) = options.min_score {
+        pipeline = pipeline.min_score(min_score);
+    }
+    if let Some(collation) = options.sort {
+        pipeline = pipeline.sort_members(collation);
+    }
+    if let (Some(dir), Some(content)) = (&options.cache, cache_content) {
+        pipeline = pipeline.cache(ResultCache::new(dir), content);
+    }
+    pipeline = match &options.command {
+        Command::Plan { result_path } => pipeline.previous(load_result(result_path)?).dry_run(),
+        Command::Apply { result_path } => pipeline.previous(load_result(result_path)?).audit(env::args().skip(1).collect(), setting_str),
+        Command::Replay { run_id, .. } => pipeline.replay(run_id),
+        _ => pipeline.audit(env::args().skip(1).collect(), setting_str)
+    };
+    if let (Some(selector), Command::Plan { result_path } | Command::Apply { result_path }) = (&options.reroll_team, &options.command) {
+        pipeline = pipeline.reroll(load_result(result_path)?, selector.clone());
+    }
+    if let Some(retention) = retention {
+        pipeline = pipeline.retention(retention);
+    }
+    let res = pipeline.generate()?;
+
+    // ログ形式が指定されていれば、ログを読む側が1行ずつ解析できるよう警告と人数もログとして出力する
+    for warning in &res.warnings {
+        if logger.enabled() {
+            logger.warn("warning", &[("warning", warning.to_string().into())]);
+        } else {
+            eprintln!("warning: {}", warning);
+        }
+    }
+    if setting_target_size.is_some() {
+        if logger.enabled() {
+            logger.info("chose teams for target_size", &[("sizes", team_sizes(&res.teams).join(",").into())]);
+        } else {
+            eprintln!("{}", size_summary(&res.teams));
+        }
+    }
+
+    if let (Some(run_id), Some(seed)) = (&res.run_id, res.seed) {
+        fields.push(("run_id", run_id.as_str().into()));
+        fields.push(("seed", seed.into()));
+    }
+    fields.push(("num_of_teams", (res.teams.borrow_vec().len() as u64).into()));
+    fields.push(("duration_ms", (started.elapsed().as_millis() as u64).into()));
+    logger.info("run finished", &fields);
+
+    Ok(res)
+}
+
+/// `--cache`のキーにする、結果に影響する設定の内容
+/// 名簿は拡張子によって読み方が変わるため、内容とあわせてパスもキーに含める
+fn cache_content(setting: &TeamsCreationSetting, setting_str: &str, options: &CliOptions) -> Result<String> {
+    let mut rosters = Vec::new();
+    if let Some(path) = &options.names_file {
+        rosters.push(PathBuf::from(path));
+    }
+    if let Some(file) = setting.attendees_file() {
+        rosters.push(setting_dir(&options.setting_path).join(file));
+    }
+    let mut names = String::new();
+    for path in rosters {
+        names.push_str(&format!("{}\n{}", path.display(), read_roster_text(&path)?));
+    }
+
+    Ok(format!("{}\n{}\n{:?}\n{:?}", setting_str, names, options.sort, options.no_lead))
+}
+
+/// `target_size`から選んだチーム数と、チームごとの人数を表す文字列
+fn size_summary(teams: &Teams) -> String {
+    let sizes = team_sizes(teams);
+
+    format!("Chose {} teams for target_size (sizes: {})", sizes.len(), sizes.join(", "))
+}
+
+/// チームごとの人数
+fn team_sizes(teams: &Teams) -> Vec<String> {
+    teams.borrow_vec().iter().map(|t| (t.member.len() + 1).to_string()).collect()
+}
+
+/// 前回の結果を読み込む。ファイルがなければ空の結果とみなす
+fn load_result(path: &str) -> Result<Teams> {
+    if !Path::new(path).exists() {
+        return Ok(Teams::from_vec(Vec::new()));
+    }
 
-    let res = run(setting).unwrap();
-    print!("{}", toml::to_string_pretty(&res).unwrap());
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
 }
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use thiserror::Error;
+use crate::domain::Teams;
+use crate::history::RunRecord;
+use crate::locale::Locale;
+
+/// 1日の秒数
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// 新鮮さの下限を満たす結果を探すときの最大試行回数
+pub const MIN_SCORE_ATTEMPTS: usize = 100;
+
+/// 新鮮さの下限に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum ScoreError {
+    /// 試行回数内に下限を満たす結果が見つからなかった
+    #[error("best novelty score {0:.2} is below --min-score {1} after {2} attempts")]
+    BelowThreshold(f64, f64, usize)
+}
+
+/// 過去に同じチームになったことのある組
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatedPair {
+    /// 組の2人(名前順)
+    pub pair: (Arc<str>, Arc<str>),
+    /// 最後に同じチームになった時刻(UNIX秒)
+    pub last_met: u64
+}
+
+/// チームごとの新鮮さ
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamNovelty {
+    /// チーム内の組の数
+    pub pairs: usize,
+    /// 一度も同じチームになったことのない組の数
+    pub new_pairs: usize,
+    /// 過去に同じチームになったことのある組
+    pub repeated: Vec<RepeatedPair>
+}
+
+impl TeamNovelty {
+    /// 一度も同じチームになったことのない組の割合
+    /// 組がなければ1.0
+    pub fn ratio(&self) -> f64 {
+        if self.pairs == 0 { 1.0 } else { self.new_pairs as f64 / self.pairs as f64 }
+    }
+}
+
+/// 作成したチームが実行履歴に対してどれだけ新しい組み合わせかの集計
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoveltyReport {
+    /// チームごとの新鮮さ(チームの順)
+    pub teams: Vec<TeamNovelty>
+}
+
+impl NoveltyReport {
+    /// 実行履歴と比べて新鮮さを集計する
+    /// # Attributes
+    /// * `teams` - 作成したチーム
+    /// * `history` - 過去の実行記録
+    pub fn compute(teams: &Teams, history: &[RunRecord]) -> NoveltyReport {
+        let mut last_met: BTreeMap<(Arc<str>, Arc<str>), u64> = BTreeMap::new();
+        for run in history {
+            for pair in run.pairs() {
+                let t = last_met.entry(pair).or_insert(run.timestamp);
+                *t = (*t).max(run.timestamp);
+            }
+        }
+
+        NoveltyReport {
+            teams: teams.borrow_vec().iter().map(|team| {
+                let pairs = team.pairs();
+                let repeated: Vec<RepeatedPair> = pairs.iter()
+                    .filter_map(|p| last_met.get(p).map(|t| RepeatedPair { pair: p.clone(), last_met: *t }))
+                    .collect();
+
+                TeamNovelty {
+                    pairs: pairs.len(),
+                    new_pairs: pairs.len() - repeated.len(),
+                    repeated
+                }
+            }).collect()
+        }
+    }
+
+    /// 全チームを通した、一度も同じチームになったことのない組の割合
+    /// 組がなければ1.0
+    pub fn overall_ratio(&self) -> f64 {
+        let pairs: usize = self.teams.iter().map(|t| t.pairs).sum();
+        let new_pairs: usize = self.teams.iter().map(|t| t.new_pairs).sum();
+
+        if pairs == 0 { 1.0 } else { new_pairs as f64 / pairs as f64 }
+    }
+
+    /// 人が読むための集計結果を返す
+    /// # Attributes
+    /// * `locale` - チーム名の言語
+    /// * `now` - 現在時刻(UNIX秒)。最後に同じチームになってからの日数に使う
+    pub fn summary(&self, locale: Locale, now: u64) -> String {
+        let mut res = String::new();
+
+        let _ = writeln!(res, "Novelty: {:.0}% new pairs overall", self.overall_ratio() * 100.0);
+        for (i, team) in self.teams.iter().enumerate() {
+            let _ = writeln!(
+                res, "  {}: {:.0}% new pairs ({}/{})",
+                locale.team_name(i + 1), team.ratio() * 100.0, team.new_pairs, team.pairs
+            );
+            for repeated in &team.repeated {
+                let _ = writeln!(
+                    res, "    {} & {}: last together {} days ago",
+                    repeated.pair.0, repeated.pair.1, now.saturating_sub(repeated.last_met) / DAY_SECS
+                );
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+
+    fn teams(v: &[(&str, &[&str])]) -> Teams {
+        Teams::from_vec(v.iter().map(|(leader, members)| {
+            let mut team = Team::new(Person::new(*leader));
+            for m in members.iter() {
+                team.assign(Person::new(*m));
+            }
+            team
+        }).collect())
+    }
+
+    fn run(timestamp: u64, v: &[(&str, &[&str])]) -> RunRecord {
+        RunRecord { run_id: timestamp.to_string(), timestamp, seed: None, audit: None, teams: teams(v) }
+    }
+
+    /// NoveltyReport#computeのテスト
+    /// チームごとに新しい組の数と、過去に組んだ組の最後の時刻を集計する
+    #[test]
+    fn compute_novelty() {
+        let history = vec![
+            run(DAY_SECS, &[("A", &["B"]), ("C", &["D"])]),
+            run(DAY_SECS * 3, &[("A", &["B", "C"])]),
+        ];
+
+        let report = NoveltyReport::compute(&teams(&[("A", &["B", "D"]), ("C", &["E"])]), &history);
+
+        assert_eq!(report.teams[0].pairs, 3);
+        assert_eq!(report.teams[0].new_pairs, 2);
+        assert_eq!(report.teams[0].repeated, vec![RepeatedPair { pair: ("A".into(), "B".into()), last_met: DAY_SECS * 3 }]);
+        assert_eq!(report.teams[1].ratio(), 1.0);
+        assert_eq!(report.overall_ratio(), 0.75);
+    }
+
+    /// NoveltyReport#summaryのテスト
+    #[test]
+    fn novelty_summary() {
+        let history = vec![run(0, &[("A", &["B"])])];
+
+        let report = NoveltyReport::compute(&teams(&[("A", &["B"]), ("C", &["D"])]), &history);
+
+        assert_eq!(
+            report.summary(Locale::En, DAY_SECS * 7),
+            "Novelty: 50% new pairs overall\n  Team 1: 0% new pairs (0/1)\n    A & B: last together 7 days ago\n  Team 2: 100% new pairs (1/1)\n"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use crate::domain::Teams;
+
+/// チームの中での人物の位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    /// チームの番号(1始まり)
+    pub team: usize,
+    /// リーダーかどうか
+    pub leader: bool
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.leader {
+            write!(f, "Team {} (leader)", self.team)
+        } else {
+            write!(f, "Team {}", self.team)
+        }
+    }
+}
+
+/// 前回の結果からの変更
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    /// 別のチーム・役割に移る
+    Moved { name: Arc<str>, from: Placement, to: Placement },
+    /// 前回の結果にいなかった人物が加わる
+    Added { name: Arc<str>, to: Placement },
+    /// 前回の結果にいた人物がいなくなる
+    Removed { name: Arc<str>, from: Placement }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Moved { name, from, to } => write!(f, "~ {}: {} -> {}", name, from, to),
+            Self::Added { name, to } => write!(f, "+ {}: {}", name, to),
+            Self::Removed { name, from } => write!(f, "- {}: {}", name, from)
+        }
+    }
+}
+
+/// 人物の名前から位置を引けるようにする
+/// # Attributes
+/// * `teams` - チームの集約
+fn placements(teams: &Teams) -> BTreeMap<Arc<str>, Placement> {
+    let mut res = BTreeMap::new();
+
+    for (i, team) in teams.borrow_vec().iter().enumerate() {
+        res.insert(team.leader.name.clone(), Placement { team: i + 1, leader: true });
+        for member in &team.member {
+            res.insert(member.name.clone(), Placement { team: i + 1, leader: false });
+        }
+    }
+
+    res
+}
+
+/// 前回の結果と新しい結果を比べ、人物ごとの変更を返す
+/// 人物は名前で突き合わせ、変更のない人物は含めない
+/// # Attributes
+/// * `previous` - 前回の結果
+/// * `next` - 新しい結果
+///
+/// # Returns
+/// 名前順に並んだ変更のリスト
+pub fn diff(previous: &Teams, next: &Teams) -> Vec<Change> {
+    let before = placements(previous);
+    let after = placements(next);
+    let mut res = Vec::new();
+
+    for (name, to) in &after {
+        match before.get(name) {
+            Some(from) if from != to => res.push(Change::Moved { name: name.clone(), from: *from, to: *to }),
+            Some(_) => {},
+            None => res.push(Change::Added { name: name.clone(), to: *to })
+        }
+    }
+    for (name, from) in &before {
+        if !after.contains_key(name) {
+            res.push(Change::Removed { name: name.clone(), from: *from });
+        }
+    }
+
+    res.sort_by(|a, b| change_name(a).cmp(change_name(b)));
+    res
+}
+
+/// 変更の対象人物の名前を返す
+fn change_name(change: &Change) -> &str {
+    match change {
+        Change::Moved { name, .. } | Change::Added { name, .. } | Change::Removed { name, .. } => name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+
+    fn teams(v: &[(&str, &[&str])]) -> Teams {
+        Teams::from_vec(v.iter().map(|(leader, members)| {
+            let mut team = Team::new(Person::new(*leader));
+            for m in members.iter() {
+                team.assign(Person::new(*m));
+            }
+            team
+        }).collect())
+    }
+
+    /// diffのテスト
+    /// 移動・追加・削除を名前順に返し、変わらない人物は含めない
+    #[test]
+    fn diff_results() {
+        let previous = teams(&[("A", &["B", "C"]), ("D", &["E", "F"])]);
+        let next = teams(&[("A", &["B", "E"]), ("C", &["D", "G"])]);
+
+        let changes: Vec<String> = diff(&previous, &next).iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(changes, vec![
+            "~ C: Team 1 -> Team 2 (leader)",
+            "~ D: Team 2 (leader) -> Team 2",
+            "~ E: Team 2 -> Team 1",
+            "- F: Team 2",
+            "+ G: Team 2",
+        ]);
+    }
+
+    /// diffのテスト
+    /// 同じ結果同士であれば変更なし
+    #[test]
+    fn diff_same_results() {
+        let previous = teams(&[("A", &["B"]), ("C", &["D"])]);
+        let next = teams(&[("A", &["B"]), ("C", &["D"])]);
+
+        assert!(diff(&previous, &next).is_empty());
+    }
+}
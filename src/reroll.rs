@@ -0,0 +1,332 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use anyhow::Result;
+use thiserror::Error;
+use crate::domain::{Person, Team, Teams, TeamsCreationSetting, VecShuffleStrategy};
+
+/// チームの作り直しに関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum RerollError {
+    /// 指定したチームが前回の結果にない
+    #[error("team {0} is not found in the previous result")]
+    TeamNotFound(String),
+    /// 作り直すチームに入れる人の中にリーダー候補がいない
+    #[error("no leader candidate is left for team {0}")]
+    LeaderMissing(usize),
+    /// 分割するチームのメンバーに新しいチームのリーダー候補が足りない
+    #[error("team {0} needs {1} more leader candidates among its members to be split")]
+    SplitLeadersLack(usize, usize),
+    /// 欠席者として指定した人が結果にいない
+    #[error("{0} is not found in the result")]
+    PersonNotFound(String)
+}
+
+/// 作り直すチームの指定
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeamSelector {
+    /// チームの番号(1始まり)
+    Index(usize),
+    /// チームにいる人の名前
+    Name(String)
+}
+
+impl TeamSelector {
+    /// 前回の結果から指定されたチームの位置を探す
+    /// # Attributes
+    /// * `teams` - 前回の結果
+    ///
+    /// # Returns
+    /// 見つかればSome(0始まりの位置)
+    pub fn position(&self, teams: &Teams) -> Option<usize> {
+        match self {
+            Self::Index(n) => n.checked_sub(1).filter(|i| *i < teams.borrow_vec().len()),
+            Self::Name(name) => teams.borrow_vec().iter()
+                .position(|t| std::iter::once(&t.leader).chain(t.member.iter()).any(|p| &*p.name == name))
+        }
+    }
+}
+
+impl FromStr for TeamSelector {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(n) => Self::Index(n),
+            Err(_) => Self::Name(s.to_string())
+        })
+    }
+}
+
+/// 見つからなかったときのエラーを作る
+fn not_found(selector: &TeamSelector) -> RerollError {
+    RerollError::TeamNotFound(match selector {
+        TeamSelector::Index(n) => n.to_string(),
+        TeamSelector::Name(name) => name.clone()
+    })
+}
+
+/// 前回の結果のうち1チームだけを作り直す
+/// 他のチームはそのまま残し、作り直すチームの人と前回の結果にいない参加者からリーダーとメンバーを選び直す
+/// 出席者から外れた人は作り直すチームから除く。チームのメモとアジェンダはそのまま残す
+/// # Attributes
+/// * `previous` - 前回の結果
+/// * `selector` - 作り直すチーム
+/// * `setting` - チーム作成設定
+/// * `shuffle_strategy` - `Vec`のshuffleの仕方
+///
+/// # Returns
+/// Ok(1チームだけ作り直した結果)
+pub fn reroll(previous: &Teams, selector: &TeamSelector, setting: &TeamsCreationSetting, shuffle_strategy: &impl VecShuffleStrategy) -> Result<Teams> {
+    let index = selector.position(previous).ok_or_else(|| not_found(selector))?;
+
+    let pinned: BTreeSet<&Arc<str>> = previous.borrow_vec().iter().enumerate()
+        .filter(|(i, _)| *i != index)
+        .flat_map(|(_, t)| std::iter::once(&t.leader).chain(t.member.iter()))
+        .map(|p| &p.name)
+        .collect();
+    let candidates: BTreeSet<&Arc<str>> = setting.leader_candidates().into_iter().map(|p| &p.name).collect();
+
+    let mut pool: Vec<Person> = setting.all_people().into_iter().filter(|p| !pinned.contains(&p.name)).cloned().collect();
+    shuffle_strategy.shuffle(&mut pool)?;
+
+    let leader_index = pool.iter().position(|p| candidates.contains(&p.name)).ok_or(RerollError::LeaderMissing(index + 1))?;
+    let mut team = Team::new(pool.remove(leader_index));
+    for member in pool {
+        team.assign(member);
+    }
+
+    let mut team_vec = previous.borrow_vec().clone();
+    team.notes = team_vec[index].notes.take();
+    team.agenda = std::mem::take(&mut team_vec[index].agenda);
+    team_vec[index] = team;
+
+    Ok(Teams::from_vec(team_vec))
+}
+
+/// 結果のうち1チームを複数のチームに分割する
+/// 元のリーダーは最初のチームのリーダーのまま、残りのチームのリーダーをメンバーから選び、メンバーを均等に分ける
+/// 分割したチームは元のチームの位置に並べ、他のチームはそのまま残す
+/// # Attributes
+/// * `previous` - 結果
+/// * `selector` - 分割するチーム
+/// * `into` - 分割後のチーム数
+/// * `setting` - チーム作成設定。指定されていればリーダー候補だけを新しいリーダーにし、なければ誰でもリーダーにする
+/// * `shuffle_strategy` - `Vec`のshuffleの仕方
+///
+/// # Returns
+/// Ok(1チームを分割した結果)
+pub fn split(previous: &Teams, selector: &TeamSelector, into: u8, setting: Option<&TeamsCreationSetting>, shuffle_strategy: &impl VecShuffleStrategy) -> Result<Teams> {
+    let index = selector.position(previous).ok_or_else(|| not_found(selector))?;
+    let target = &previous.borrow_vec()[index];
+
+    let is_candidate = |p: &Person| setting.is_none_or(|s| s.leader_candidates().iter().any(|c| c.name == p.name));
+    let (mut eligible, mut rest): (Vec<Person>, Vec<Person>) = target.member.iter().cloned().partition(|p| is_candidate(p));
+    shuffle_strategy.shuffle(&mut eligible)?;
+
+    let needed = usize::from(into) - 1;
+    if eligible.len() < needed {
+        Err(RerollError::SplitLeadersLack(index + 1, needed - eligible.len()))?
+    }
+    rest.extend(eligible.split_off(needed));
+    shuffle_strategy.shuffle(&mut rest)?;
+
+    let mut new_teams: Vec<Team> = std::iter::once(target.leader.clone()).chain(eligible).map(Team::new).collect();
+    let num_of_new_teams = new_teams.len();
+    for (i, member) in rest.into_iter().enumerate() {
+        new_teams[i % num_of_new_teams].assign(member);
+    }
+
+    let mut team_vec = previous.borrow_vec().clone();
+    team_vec.splice(index..=index, new_teams);
+
+    Ok(Teams::from_vec(team_vec))
+}
+
+/// 実際の出席がわかった後に、結果から欠席者を除く
+/// リーダーが欠席したチームはメンバーから新しいリーダーを選び、全員が欠席したチームはなくす
+/// その後、人数の差が最大1人になるまで人数の多いチームから少ないチームへメンバーを移す
+/// # Attributes
+/// * `previous` - 結果
+/// * `absent` - 欠席者の名前
+/// * `setting` - チーム作成設定。指定されていればリーダー候補だけを新しいリーダーにし、なければ誰でもリーダーにする
+///
+/// # Returns
+/// Ok(欠席者を除いた結果)
+pub fn remove_absent(previous: &Teams, absent: &[String], setting: Option<&TeamsCreationSetting>) -> Result<Teams> {
+    let team_vec = previous.borrow_vec();
+    if let Some(name) = absent.iter().find(|n| team_of(team_vec, n).is_none()) {
+        Err(RerollError::PersonNotFound(name.clone()))?
+    }
+
+    let is_absent = |p: &Person| absent.iter().any(|n| *n == *p.name);
+    let is_candidate = |p: &Person| setting.is_none_or(|s| s.leader_candidates().iter().any(|c| c.name == p.name));
+    let mut new_teams: Vec<Team> = Vec::new();
+    for (i, team) in team_vec.iter().enumerate() {
+        let mut members: Vec<Person> = team.member.iter().filter(|p| !is_absent(p)).cloned().collect();
+        let leader = if !is_absent(&team.leader) {
+            team.leader.clone()
+        } else if members.is_empty() {
+            continue;
+        } else {
+            let index = members.iter().position(is_candidate).ok_or(RerollError::LeaderMissing(i + 1))?;
+            members.remove(index)
+        };
+
+        // サブチームは欠席者を含むため作り直さずに除く
+        let roles = team.roles.iter()
+            .map(|(role, names)| (role.clone(), names.iter().filter(|n| !absent.iter().any(|a| *a == ***n)).cloned().collect()))
+            .collect();
+        new_teams.push(Team { leader, member: members, sub_team: Vec::new(), roles, ..team.clone() });
+    }
+
+    while let (Some(max), Some(min)) = (
+        (0..new_teams.len()).max_by_key(|i| new_teams[*i].member.len()),
+        (0..new_teams.len()).min_by_key(|i| new_teams[*i].member.len())
+    ) {
+        if new_teams[max].member.len() <= new_teams[min].member.len() + 1 {
+            break;
+        }
+        let moved = new_teams[max].member.pop().unwrap();
+        new_teams[min].assign(moved);
+    }
+
+    Ok(Teams::from_vec(new_teams))
+}
+
+/// 名前からその人がいるチームの位置を探す
+fn team_of(team_vec: &[Team], name: &str) -> Option<usize> {
+    team_vec.iter().position(|t| std::iter::once(&t.leader).chain(t.member.iter()).any(|p| &*p.name == name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::ShuffleStrategies;
+
+    const SETTING: &str = r#"
+num_of_teams = 2
+
+[[attendees]]
+leader = true
+person = { name = "A" }
+
+[[attendees]]
+leader = true
+person = { name = "B" }
+
+[[attendees]]
+person = { name = "C" }
+
+[[attendees]]
+person = { name = "E" }
+"#;
+
+    fn previous() -> Teams {
+        let mut team1 = Team::new(Person::new("A"));
+        team1.assign(Person::new("C"));
+        let mut team2 = Team::new(Person::new("B"));
+        team2.assign(Person::new("D"));
+
+        Teams::from_vec(vec![team1, team2])
+    }
+
+    /// TeamSelector#from_strのテスト
+    /// 数字であればチームの番号、それ以外は名前として扱う
+    #[test]
+    fn parse_team_selector() {
+        assert_eq!("2".parse(), Ok(TeamSelector::Index(2)));
+        assert_eq!("Taro".parse(), Ok(TeamSelector::Name("Taro".into())));
+    }
+
+    /// rerollのテスト
+    /// 指定したチームだけを作り直し、出席者から外れた人を除いて新しい参加者を加える
+    #[test]
+    fn reroll_one_team() {
+        let setting: TeamsCreationSetting = toml::from_str(SETTING).unwrap();
+
+        let teams = reroll(&previous(), &TeamSelector::Name("D".into()), &setting, &ShuffleStrategies::NoShuffle).unwrap();
+        let team_vec = teams.borrow_vec();
+
+        assert_eq!(&*team_vec[0].leader.name, "A");
+        assert_eq!(team_vec[0].member.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["C"]);
+        assert_eq!(&*team_vec[1].leader.name, "B");
+        assert_eq!(team_vec[1].member.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["E"]);
+    }
+
+    /// rerollのテスト
+    /// 指定したチームがなければエラー
+    #[test]
+    fn reroll_missing_team() {
+        let setting: TeamsCreationSetting = toml::from_str(SETTING).unwrap();
+
+        let result = reroll(&previous(), &TeamSelector::Index(3), &setting, &ShuffleStrategies::NoShuffle);
+
+        assert_eq!(result.unwrap_err().downcast_ref::<RerollError>(), Some(&RerollError::TeamNotFound("3".into())));
+    }
+
+    /// splitのテスト
+    /// 指定したチームだけを分割し、元のリーダーは最初のチームに残る
+    #[test]
+    fn split_one_team() {
+        let mut team1 = Team::new(Person::new("A"));
+        for name in ["C", "D", "E", "F", "G"] {
+            team1.assign(Person::new(name));
+        }
+        let team2 = Team::new(Person::new("B"));
+        let previous = Teams::from_vec(vec![team1, team2]);
+
+        let teams = split(&previous, &TeamSelector::Index(1), 3, None, &ShuffleStrategies::NoShuffle).unwrap();
+        let team_vec = teams.borrow_vec();
+
+        assert_eq!(team_vec.len(), 4);
+        assert_eq!(team_vec.iter().map(|t| &*t.leader.name).collect::<Vec<_>>(), vec!["A", "C", "D", "B"]);
+        assert_eq!(team_vec[..3].iter().map(|t| t.member.len()).collect::<Vec<_>>(), vec![1, 1, 1]);
+    }
+
+    /// splitのテスト
+    /// 設定が指定されていれば、メンバーのリーダー候補だけを新しいリーダーにする
+    #[test]
+    fn split_with_leader_candidates() {
+        let setting: TeamsCreationSetting = toml::from_str(SETTING).unwrap();
+
+        let result = split(&previous(), &TeamSelector::Name("A".into()), 2, Some(&setting), &ShuffleStrategies::NoShuffle);
+
+        assert_eq!(result.unwrap_err().downcast_ref::<RerollError>(), Some(&RerollError::SplitLeadersLack(1, 1)));
+    }
+
+    /// remove_absentのテスト
+    /// 欠席したリーダーをメンバーから選び直し、人数の差が1人以内になるようにメンバーを移す
+    #[test]
+    fn remove_absent_people() {
+        let mut team1 = Team::new(Person::new("A"));
+        team1.assign(Person::new("C"));
+        team1.assign(Person::new("D"));
+        let mut team2 = Team::new(Person::new("B"));
+        for name in ["E", "F", "G"] {
+            team2.assign(Person::new(name));
+        }
+        let previous = Teams::from_vec(vec![team1, team2]);
+
+        let teams = remove_absent(&previous, &["A".into(), "D".into()], None).unwrap();
+        let team_vec = teams.borrow_vec();
+
+        assert_eq!(&*team_vec[0].leader.name, "C");
+        assert_eq!(team_vec[0].member.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["G"]);
+        assert_eq!(&*team_vec[1].leader.name, "B");
+        assert_eq!(team_vec[1].member.iter().map(|p| &*p.name).collect::<Vec<_>>(), vec!["E", "F"]);
+    }
+
+    /// remove_absentのテスト
+    /// 結果にいない人を指定したり、リーダー候補が残らなければエラー
+    #[test]
+    fn remove_absent_errors() {
+        let setting: TeamsCreationSetting = toml::from_str(SETTING).unwrap();
+
+        let missing = remove_absent(&previous(), &["Z".into()], None);
+        assert_eq!(missing.unwrap_err().downcast_ref::<RerollError>(), Some(&RerollError::PersonNotFound("Z".into())));
+
+        let leaderless = remove_absent(&previous(), &["A".into()], Some(&setting));
+        assert_eq!(leaderless.unwrap_err().downcast_ref::<RerollError>(), Some(&RerollError::LeaderMissing(1)));
+    }
+}
@@ -0,0 +1,447 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+use crate::domain::{Attendee, Person};
+
+/// 名簿の読み込みに関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum RosterError {
+    /// CSVのヘッダーにname列がない
+    #[error("roster CSV needs a name column in its header")]
+    NameColumnMissing,
+    /// 行の列数がヘッダーと合わない
+    #[error("line {0} of roster CSV has {1} fields but the header has {2}")]
+    FieldCountMismatch(usize, usize, usize),
+    /// leader列の値が真偽値として読めない
+    #[error("line {0} of roster CSV has invalid leader value {1:?}")]
+    InvalidLeader(usize, String),
+    /// 名前が空
+    #[error("line {0} of roster has an empty name")]
+    EmptyName(usize)
+}
+
+/// 名簿のファイルかフォルダを読み込む
+/// フォルダであれば中の`.vcf`ファイルをファイル名順に全て読む
+/// # Attributes
+/// * `path` - 名簿のファイルかフォルダのパス
+///
+/// # Returns
+/// Ok(出席者のリスト)
+pub fn load_roster(path: &Path) -> anyhow::Result<Vec<Attendee>> {
+    let text = read_roster_text(path)?;
+    if path.is_dir() {
+        return Ok(parse_vcards(&text));
+    }
+
+    Ok(parse_roster(path, &text)?)
+}
+
+/// 名簿のファイルかフォルダの内容を読む
+/// フォルダであれば中の`.vcf`ファイルの内容をファイル名順につなげる
+/// # Attributes
+/// * `path` - 名簿のファイルかフォルダのパス
+pub fn read_roster_text(path: &Path) -> io::Result<String> {
+    if !path.is_dir() {
+        return fs::read_to_string(path);
+    }
+
+    let mut files: Vec<_> = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    files.retain(|f| f.is_file() && f.extension().is_some_and(|e| e.eq_ignore_ascii_case("vcf")));
+    files.sort();
+
+    let mut res = String::new();
+    for file in files {
+        res.push_str(&fs::read_to_string(file)?);
+        res.push('\n');
+    }
+
+    Ok(res)
+}
+
+/// 拡張子に合った形式で名簿を読み取る
+/// 拡張子(大文字小文字は区別しない)が`.csv`であればCSV、`.vcf`であればvCard、`.ics`であればiCalendarの予定、それ以外は1行1人の名前のリストとみなす
+/// # Attributes
+/// * `path` - 名簿のパス
+/// * `text` - 名簿の内容
+///
+/// # Returns
+/// Ok(出席者のリスト)
+pub fn parse_roster(path: &Path, text: &str) -> Result<Vec<Attendee>, RosterError> {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("csv") => parse_csv(text),
+        Some("vcf") => Ok(parse_vcards(text)),
+        Some("ics") => Ok(parse_ics(text)),
+        _ => parse_names(text)
+    }
+}
+
+/// ヘッダー付きのCSVから出席者を読み取る
+/// `name`列は必須で、空欄はエラー。`leader`列(true/false・1/0・yes/no、空欄はfalse)、`reading`列、`display_name`列は任意で、それ以外の列は無視する
+/// # Attributes
+/// * `text` - CSVの内容
+///
+/// # Returns
+/// Ok(出席者のリスト)
+pub fn parse_csv(text: &str) -> Result<Vec<Attendee>, RosterError> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let header = match lines.next() {
+        // ExcelのCSV UTF-8形式は先頭にBOMを付ける
+        Some((_, line)) => split_csv_line(line.strip_prefix('\u{feff}').unwrap_or(line)),
+        None => return Ok(Vec::new())
+    };
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let name_column = column("name").ok_or(RosterError::NameColumnMissing)?;
+    let (leader_column, reading_column, display_name_column) = (column("leader"), column("reading"), column("display_name"));
+
+    let mut res = Vec::new();
+    for (i, line) in lines {
+        let fields = split_csv_line(line);
+        if fields.len() != header.len() {
+            return Err(RosterError::FieldCountMismatch(i + 1, fields.len(), header.len()));
+        }
+        let field = |column: Option<usize>| column.map(|c| fields[c].trim()).filter(|f| !f.is_empty());
+
+        let leader = match field(leader_column).map(str::to_ascii_lowercase).as_deref() {
+            None | Some("false" | "0" | "no" | "n") => false,
+            Some("true" | "1" | "yes" | "y") => true,
+            Some(value) => return Err(RosterError::InvalidLeader(i + 1, value.to_string()))
+        };
+        let name = field(Some(name_column)).ok_or(RosterError::EmptyName(i + 1))?;
+        let person = Person {
+            reading: field(reading_column).map(Arc::from),
+            display_name: field(display_name_column).map(Arc::from),
+            ..Person::new(name)
+        };
+        res.push(Attendee::new(person, leader));
+    }
+
+    Ok(res)
+}
+
+/// CSVの1行をフィールドに分ける
+/// ダブルクオートで囲んだフィールドにはカンマと`""`(ダブルクオート)を書ける
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut res = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                res.last_mut().unwrap().push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => res.push(String::new()),
+            _ => res.last_mut().unwrap().push(c)
+        }
+    }
+
+    res
+}
+
+/// vCardの連絡先から出席者を読み取る
+/// `FN`(表示用の氏名)を名前にし、`FN`がない連絡先は無視する。リーダー候補にはしない
+/// # Attributes
+/// * `text` - 1つ以上のvCard
+///
+/// # Returns
+/// 出席者のリスト
+pub fn parse_vcards(text: &str) -> Vec<Attendee> {
+    let mut res = Vec::new();
+    let mut name: Option<String> = None;
+    for line in &unfold_lines(text) {
+        let (property, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => continue
+        };
+        // `FN;CHARSET=UTF-8`や`item1.FN`のようなパラメータとグループを除く
+        let property = property.split(';').next().unwrap_or("");
+        let property = property.rsplit('.').next().unwrap_or("").to_ascii_uppercase();
+
+        match property.as_str() {
+            "BEGIN" => name = None,
+            "FN" => name = Some(unescape_vcard(value.trim())).filter(|n| !n.is_empty()),
+            "END" => if let Some(name) = name.take() {
+                res.push(Attendee::new(Person::new(name), false));
+            },
+            _ => {}
+        }
+    }
+
+    res
+}
+
+/// iCalendarの予定の出席者を読み取る
+/// `ATTENDEE`の`CN`(なければメールアドレス)を名前にし、`ROLE=CHAIR`の出席者をリーダー候補にする
+/// 欠席と返答した(`PARTSTAT=DECLINED`)出席者と、通知(`VALARM`)の宛先は無視し、同じ名前は1人にまとめる
+/// # Attributes
+/// * `text` - iCalendarの内容
+///
+/// # Returns
+/// 出席者のリスト
+pub fn parse_ics(text: &str) -> Vec<Attendee> {
+    let mut res: Vec<Attendee> = Vec::new();
+    let mut in_alarm = false;
+    for line in &unfold_lines(text) {
+        let (property, params, value) = match split_ics_line(line) {
+            Some(parts) => parts,
+            None => continue
+        };
+        let param = |name: &str| params.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str());
+
+        match property.to_ascii_uppercase().as_str() {
+            "BEGIN" | "END" if value.trim().eq_ignore_ascii_case("VALARM") => in_alarm = property.eq_ignore_ascii_case("BEGIN"),
+            "ATTENDEE" if !in_alarm => {
+                if param("PARTSTAT").is_some_and(|p| p.eq_ignore_ascii_case("DECLINED")) {
+                    continue;
+                }
+                let address = value.trim();
+                let address = if address.get(..7).is_some_and(|p| p.eq_ignore_ascii_case("mailto:")) { &address[7..] } else { address };
+                let name = param("CN").map(|cn| unescape_vcard(cn.trim())).filter(|n| !n.is_empty()).unwrap_or_else(|| address.to_string());
+                if name.is_empty() || res.iter().any(|a| *a.person().name == *name) {
+                    continue;
+                }
+                let leader = param("ROLE").is_some_and(|r| r.eq_ignore_ascii_case("CHAIR"));
+                res.push(Attendee::new(Person::new(name), leader));
+            },
+            _ => {}
+        }
+    }
+
+    res
+}
+
+/// iCalendarのパラメータ(名前と値)のリスト
+type IcsParams = Vec<(String, String)>;
+
+/// iCalendarの1行を、プロパティ名・パラメータ・値に分ける
+/// ダブルクオートで囲んだパラメータの値には`:`・`;`・`,`を書ける
+/// # Returns
+/// `:`がなければNone
+fn split_ics_line(line: &str) -> Option<(String, IcsParams, &str)> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => fields.push(String::new()),
+            ':' if !quoted => {
+                let property = fields.remove(0);
+                let params = fields.into_iter()
+                    .filter_map(|f| f.split_once('=').map(|(n, v)| (n.to_string(), v.to_string())))
+                    .collect();
+                return Some((property, params, &line[i + 1..]));
+            },
+            c => fields.last_mut().unwrap().push(c)
+        }
+    }
+
+    None
+}
+
+/// 空白で始まる行を前の行の続きとしてつなげる(vCard・iCalendarの折り返し)
+fn unfold_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string())
+        }
+    }
+
+    lines
+}
+
+/// vCardの値のエスケープ(`\,`・`\;`・`\\`)を戻す
+/// 名前に改行は使えないため`\n`は空白にする
+fn unescape_vcard(value: &str) -> String {
+    let mut res = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => res.push(' '),
+                Some(next) => res.push(next),
+                None => {}
+            },
+            c => res.push(c)
+        }
+    }
+
+    res
+}
+
+/// 1行1人の名前のリストから出席者を読み取る
+/// 先頭か末尾に`*`が付いた名前はリーダー候補とする。空行と`#`で始まる行は無視し、`*`だけの行はエラー
+/// # Attributes
+/// * `text` - 名前のリスト
+///
+/// # Returns
+/// Ok(出席者のリスト)
+pub fn parse_names(text: &str) -> Result<Vec<Attendee>, RosterError> {
+    text.lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            let (name, leader) = match line.strip_prefix('*').or_else(|| line.strip_suffix('*')) {
+                Some(name) => (name.trim(), true),
+                None => (line, false)
+            };
+            if name.is_empty() {
+                return Err(RosterError::EmptyName(i + 1));
+            }
+
+            Ok(Attendee::new(Person::new(name), leader))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// parse_namesのテスト
+    /// 1行1人で読み取り、末尾の`*`でリーダー候補にする
+    #[test]
+    fn parse_names_file() {
+        let attendees = parse_names("# 1組\nTaro *\n\n  Hanako\nJiro*\r\n* Saburo\n").unwrap();
+
+        assert_eq!(attendees.len(), 4);
+        assert!(attendees[0].is_leader());
+        assert!(!attendees[1].is_leader());
+        assert!(attendees[2].is_leader());
+        assert!(attendees[3].is_leader());
+        assert_eq!(
+            attendees.iter().map(|a| a.person().name.to_string()).collect::<Vec<_>>(),
+            vec!["Taro", "Hanako", "Jiro", "Saburo"]
+        );
+    }
+
+    /// parse_namesのテスト
+    /// `*`だけの行は名前が空なのでエラー
+    #[test]
+    fn parse_names_rejects_empty_name() {
+        assert_eq!(parse_names("Taro\n*\n").err(), Some(RosterError::EmptyName(2)));
+        assert_eq!(parse_names("Taro\n  * \n").err(), Some(RosterError::EmptyName(2)));
+    }
+
+    /// parse_csvのテスト
+    /// ヘッダーの列名で読み取り、クオートしたフィールドのカンマはそのまま残す
+    #[test]
+    fn parse_csv_roster() {
+        let attendees = parse_csv("name,leader,team\nTaro,true,x\n\"Yamada, Hanako\",,y\nJiro,NO,z\r\n").unwrap();
+
+        assert_eq!(
+            attendees.iter().map(|a| (a.person().name.to_string(), a.is_leader())).collect::<Vec<_>>(),
+            vec![("Taro".into(), true), ("Yamada, Hanako".into(), false), ("Jiro".into(), false)]
+        );
+    }
+
+    /// parse_csvのテスト
+    /// ExcelのCSV UTF-8形式のように先頭にBOMがあっても、ヘッダーのname列を見つける
+    #[test]
+    fn parse_csv_roster_with_bom() {
+        let attendees = parse_csv("\u{feff}name,leader\nTaro,true\n").unwrap();
+
+        assert_eq!(attendees.len(), 1);
+        assert_eq!(&*attendees[0].person().name, "Taro");
+    }
+
+    /// parse_csvのテスト
+    /// name列がない、列数が合わない、leaderが読めない、名前が空であればエラー
+    #[test]
+    fn parse_invalid_csv_roster() {
+        assert_eq!(parse_csv("leader\ntrue\n").err(), Some(RosterError::NameColumnMissing));
+        assert_eq!(parse_csv("name,leader\nTaro\n").err(), Some(RosterError::FieldCountMismatch(2, 1, 2)));
+        assert_eq!(parse_csv("name,leader\nTaro,maybe\n").err(), Some(RosterError::InvalidLeader(2, "maybe".into())));
+        assert_eq!(parse_csv("name,leader\nTaro,true\n ,false\n").err(), Some(RosterError::EmptyName(3)));
+    }
+
+    /// parse_vcardsのテスト
+    /// FNを名前にし、折り返し・パラメータ・エスケープを扱い、FNがない連絡先は無視する
+    #[test]
+    fn parse_vcard_contacts() {
+        let attendees = parse_vcards(concat!(
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Yamada;Taro;;;\r\nFN:Yamada\r\n  Taro\r\nEND:VCARD\r\n",
+            "BEGIN:VCARD\nVERSION:4.0\nFN;CHARSET=UTF-8:佐藤 花子\nEND:VCARD\n",
+            "BEGIN:VCARD\nitem1.FN:Smith\\, John\nEND:VCARD\n",
+            "BEGIN:VCARD\nN:Nameless;;;;\nEND:VCARD\n"
+        ));
+
+        assert_eq!(
+            attendees.iter().map(|a| &*a.person().name).collect::<Vec<&str>>(),
+            vec!["Yamada Taro", "佐藤 花子", "Smith, John"]
+        );
+        assert!(attendees.iter().all(|a| !a.is_leader()));
+    }
+
+    /// parse_icsのテスト
+    /// ATTENDEEのCNかメールアドレスを名前にし、CHAIRをリーダー候補にする。欠席者と通知の宛先は無視する
+    #[test]
+    fn parse_ics_attendees() {
+        let attendees = parse_ics(concat!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nSUMMARY:Workshop\r\n",
+            "ORGANIZER;CN=Ken:mailto:ken@example.com\r\n",
+            "ATTENDEE;ROLE=CHAIR;CN=Ken;PARTSTAT=ACCEPTED:mailto:ken@example.com\r\n",
+            "ATTENDEE;CN=\"Smith, John\";ROLE=REQ-PARTICIPANT:MAILTO:john@example.com\r\n",
+            "ATTENDEE;RSVP=TRUE;CN=佐藤\r\n  花子:mailto:hanako@example.com\r\n",
+            "ATTENDEE:mailto:anon@example.com\r\n",
+            "ATTENDEE;CN=Declined;PARTSTAT=DECLINED:mailto:no@example.com\r\n",
+            "BEGIN:VALARM\r\nACTION:EMAIL\r\nATTENDEE;CN=Alarm:mailto:alarm@example.com\r\nEND:VALARM\r\n",
+            "END:VEVENT\r\nBEGIN:VEVENT\r\nRECURRENCE-ID:20260101T100000Z\r\n",
+            "ATTENDEE;CN=Ken;ROLE=CHAIR:mailto:ken@example.com\r\n",
+            "END:VEVENT\r\nEND:VCALENDAR\r\n"
+        ));
+
+        assert_eq!(
+            attendees.iter().map(|a| (&*a.person().name, a.is_leader())).collect::<Vec<(&str, bool)>>(),
+            vec![("Ken", true), ("Smith, John", false), ("佐藤 花子", false), ("anon@example.com", false)]
+        );
+        assert_eq!(parse_roster(Path::new("invite.ics"), "ATTENDEE;CN=A:mailto:a@example.com\n").unwrap().len(), 1);
+    }
+
+    /// parse_icsのテスト
+    /// mailto:のない値が日本語でも、そのまま名前にする
+    #[test]
+    fn parse_ics_attendees_without_mailto() {
+        let attendees = parse_ics("ATTENDEE;CN=x:佐藤花子\nATTENDEE:山田太郎\nATTENDEE:佐\n");
+
+        assert_eq!(
+            attendees.iter().map(|a| &*a.person().name).collect::<Vec<&str>>(),
+            vec!["x", "山田太郎", "佐"]
+        );
+    }
+
+    /// load_rosterのテスト
+    /// フォルダを指定すれば中の.vcfファイルをファイル名順に読む
+    #[test]
+    fn load_vcard_folder() {
+        let dir = std::env::temp_dir().join(format!("guccicci-vcards-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.vcf"), "BEGIN:VCARD\nFN:B\nEND:VCARD\n").unwrap();
+        fs::write(dir.join("a.VCF"), "BEGIN:VCARD\nFN:A\nEND:VCARD").unwrap();
+        fs::write(dir.join("notes.txt"), "C\n").unwrap();
+
+        let attendees = load_roster(&dir).unwrap();
+
+        assert_eq!(attendees.iter().map(|a| &*a.person().name).collect::<Vec<&str>>(), vec!["A", "B"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// parse_rosterのテスト
+    /// 拡張子が.csvであればCSVとして読み取る。拡張子の大文字小文字は区別しない
+    #[test]
+    fn parse_roster_by_extension() {
+        assert_eq!(parse_roster(Path::new("names.csv"), "name\nTaro\n").unwrap().len(), 1);
+        assert_eq!(parse_roster(Path::new("names.txt"), "name\nTaro\n").unwrap().len(), 2);
+        assert_eq!(parse_roster(Path::new("NAMES.CSV"), "name\nTaro\n").unwrap().len(), 1);
+        assert_eq!(parse_roster(Path::new("Contacts.VCF"), "BEGIN:VCARD\nFN:Taro\nEND:VCARD\n").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use thiserror::Error;
+use crate::domain::{Person, Teams};
+
+/// 座席表に関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum SeatingError {
+    /// 設定にテーブルがない
+    #[error("seating chart needs [[tables]] in the setting")]
+    NoTables,
+    /// チーム数に対してテーブルが足りない
+    #[error("num of tables({0}) must be equal or grater than num of teams({1})")]
+    TablesLack(usize, usize),
+    /// チームの人数に対してテーブルの席が足りない
+    #[error("team {0} has {1} people but its table has only {2} seats")]
+    SeatsLack(usize, usize, u8)
+}
+
+/// 会場のテーブル
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Table {
+    /// 会場のグリッド上の列(0始まり)
+    pub x: u8,
+    /// 会場のグリッド上の行(0始まり)
+    pub y: u8,
+    /// 席数
+    pub seats: u8
+}
+
+/// 座席表の1席
+#[derive(Debug, PartialEq)]
+pub struct Seat {
+    /// チームの番号(1始まり)
+    pub team: usize,
+    /// テーブルの列
+    pub x: u8,
+    /// テーブルの行
+    pub y: u8,
+    /// テーブル内の席番号(1始まり)
+    pub seat: usize,
+    /// 座る人
+    pub person: Person,
+    /// チームリーダーか
+    pub leader: bool
+}
+
+/// チームを設定の順番にテーブルへ割り当て、リーダーを1番の席にして座席を決める
+/// # Attributes
+/// * `teams` - 作成したチーム
+/// * `tables` - 会場のテーブル
+///
+/// # Returns
+/// Ok(チーム順・席番号順の座席のリスト)
+pub fn assign_seats(teams: &Teams, tables: &[Table]) -> Result<Vec<Seat>, SeatingError> {
+    let team_vec = teams.borrow_vec();
+    if tables.is_empty() {
+        return Err(SeatingError::NoTables);
+    }
+    if tables.len() < team_vec.len() {
+        return Err(SeatingError::TablesLack(tables.len(), team_vec.len()));
+    }
+
+    let mut res = Vec::new();
+    for (i, (team, table)) in team_vec.iter().zip(tables).enumerate() {
+        let people: Vec<&Person> = std::iter::once(&team.leader).chain(team.member.iter()).collect();
+        if people.len() > usize::from(table.seats) {
+            return Err(SeatingError::SeatsLack(i + 1, people.len(), table.seats));
+        }
+
+        for (j, person) in people.into_iter().enumerate() {
+            res.push(Seat {
+                team: i + 1,
+                x: table.x,
+                y: table.y,
+                seat: j + 1,
+                person: person.clone(),
+                leader: j == 0
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Team;
+
+    fn teams() -> Teams {
+        let mut team1 = Team::new(Person::new("A"));
+        team1.assign(Person::new("B"));
+        let team2 = Team::new(Person::new("C"));
+
+        Teams::from_vec(vec![team1, team2])
+    }
+
+    /// assign_seatsのテスト
+    /// チームを設定順にテーブルへ割り当て、リーダーが1番の席に座る
+    #[test]
+    fn assign_seats_in_order() {
+        let tables = vec![Table{x: 1, y: 0, seats: 4}, Table{x: 0, y: 1, seats: 4}];
+
+        let seats = assign_seats(&teams(), &tables).unwrap();
+
+        assert_eq!(seats.len(), 3);
+        assert_eq!(seats[0], Seat{team: 1, x: 1, y: 0, seat: 1, person: Person::new("A"), leader: true});
+        assert_eq!(seats[1], Seat{team: 1, x: 1, y: 0, seat: 2, person: Person::new("B"), leader: false});
+        assert_eq!(seats[2], Seat{team: 2, x: 0, y: 1, seat: 1, person: Person::new("C"), leader: true});
+    }
+
+    /// assign_seatsのテスト
+    /// テーブルや席が足りなければエラー
+    #[test]
+    fn assign_seats_lack() {
+        assert_eq!(assign_seats(&teams(), &[]), Err(SeatingError::NoTables));
+        assert_eq!(
+            assign_seats(&teams(), &[Table{x: 0, y: 0, seats: 4}]),
+            Err(SeatingError::TablesLack(1, 2))
+        );
+        assert_eq!(
+            assign_seats(&teams(), &[Table{x: 0, y: 0, seats: 1}, Table{x: 1, y: 0, seats: 1}]),
+            Err(SeatingError::SeatsLack(1, 2, 1))
+        );
+    }
+}
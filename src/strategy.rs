@@ -1,7 +1,10 @@
+use std::sync::Mutex;
 use anyhow::Result;
-use rand::thread_rng;
+use rand::{thread_rng, Rng, RngCore, SeedableRng};
 use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
 use crate::domain::VecShuffleStrategy;
+use crate::hash::sha256;
 
 /// `Vec`をシャッフルする方式(Stratery)
 pub enum ShuffleStrategies{
@@ -29,6 +32,65 @@ impl VecShuffleStrategy for ShuffleStrategies {
         }
 
     }
+
+    /// シャッフルしない場合は並び順のまま、ランダムの場合は毎回ランダムなキーを返す
+    fn tiebreak_key(&self, _name: &str) -> u64 {
+        match self {
+            Self::NoShuffle => 0,
+            Self::RandomShuffle => thread_rng().gen()
+        }
+    }
+}
+
+/// シード値を元に再現可能なシャッフルを行う方式(Strategy)
+/// 同じシード値・同じ入力であれば何度実行しても同じ結果になる
+/// 依存クレートを更新しても結果が変わらないよう、アルゴリズムが固定されたChaCha8と自前のシャッフルを使う
+pub struct SeededShuffle {
+    /// シード値
+    seed: u64,
+    /// シード値から初期化した乱数生成器
+    /// 複数回の`shuffle`呼び出しで状態を引き継ぐため保持する
+    rng: Mutex<ChaCha8Rng>
+}
+
+impl SeededShuffle {
+    /// シード値を指定して作成する
+    /// # Attributes
+    /// * `seed` - シード値
+    pub fn new(seed: u64) -> SeededShuffle {
+        SeededShuffle {
+            seed,
+            rng: Mutex::new(ChaCha8Rng::seed_from_u64(seed))
+        }
+    }
+}
+
+impl VecShuffleStrategy for SeededShuffle {
+    /// 与えられた`Vec`をシード値に基づいてシャッフルする。`Vec`を破壊するメソッドである点注意
+    /// # Attributes
+    /// * `vec` - シャッフルする配列
+    /// # Returns
+    /// Ok(()) - `vec`の参照を受け取り直接シャッフルする
+    fn shuffle<T>(&self, vec: &mut Vec<T>) -> Result<()> {
+        let mut rng = self.rng.lock().map_err(|_| anyhow::anyhow!("random number generator is poisoned"))?;
+        // randの`SliceRandom::shuffle`はバージョンによって乱数の使い方が変わりうるため、Fisher-Yatesを自前で行う
+        for i in (1..vec.len()).rev() {
+            let j = ((u128::from(rng.next_u64()) * (i as u128 + 1)) >> 64) as usize;
+            vec.swap(i, j);
+        }
+
+        Ok(())
+    }
+
+    /// シード値と名前のハッシュ値をキーにする
+    /// 乱数生成器の状態によらないため、呼び出す順番が変わっても同じキーになる
+    fn tiebreak_key(&self, name: &str) -> u64 {
+        let mut data = self.seed.to_le_bytes().to_vec();
+        data.extend_from_slice(name.as_bytes());
+        let hash = sha256(&data);
+
+        u64::from_le_bytes(hash[..8].try_into().unwrap_or_default())
+    }
 }
 
 #[cfg(test)]
@@ -54,4 +116,52 @@ mod tests {
 
         assert_ne!(v, clone);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn seeded_shuffle_is_reproducible () {
+        let mut v1: Vec<u32> = (0..20).collect();
+        let mut v2 = v1.clone();
+
+        SeededShuffle::new(42).shuffle(&mut v1).unwrap();
+        SeededShuffle::new(42).shuffle(&mut v2).unwrap();
+
+        assert_eq!(v1, v2);
+    }
+
+    /// SeededShuffle#shuffleのテスト
+    /// 依存クレートのバージョンによらず、シード値ごとの結果が固定されている
+    #[test]
+    fn seeded_shuffle_is_pinned () {
+        let mut v: Vec<u32> = (0..10).collect();
+
+        SeededShuffle::new(42).shuffle(&mut v).unwrap();
+
+        assert_eq!(v, vec![9, 7, 2, 5, 0, 1, 4, 3, 8, 6]);
+    }
+
+    /// SeededShuffle#tiebreak_keyのテスト
+    /// 同じシード値・同じ名前であれば何度呼び出しても同じキーになる
+    #[test]
+    fn seeded_tiebreak_key_is_stable () {
+        let strategy = SeededShuffle::new(42);
+        let first = strategy.tiebreak_key("Taro");
+        strategy.shuffle(&mut vec![0, 1, 2]).unwrap();
+
+        assert_eq!(strategy.tiebreak_key("Taro"), first);
+        assert_eq!(SeededShuffle::new(42).tiebreak_key("Taro"), first);
+        assert_ne!(SeededShuffle::new(43).tiebreak_key("Taro"), first);
+        assert_ne!(strategy.tiebreak_key("Hanako"), first);
+    }
+
+    #[test]
+    fn seeded_shuffle_carries_state_between_calls () {
+        let strategy = SeededShuffle::new(42);
+        let mut v1: Vec<u32> = (0..20).collect();
+        let mut v2 = v1.clone();
+
+        strategy.shuffle(&mut v1).unwrap();
+        strategy.shuffle(&mut v2).unwrap();
+
+        assert_ne!(v1, v2);
+    }
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+use anyhow::Result;
+use thiserror::Error;
+use toml::Value;
+use crate::formatter::OutputFormat;
+
+/// テンプレートに関するエラー
+#[derive(Debug, Error, PartialEq)]
+pub enum TemplateError {
+    /// 未知のテンプレート
+    #[error("unknown template preset {0}; choose from standup, hackathon, lunch or retro")]
+    UnknownPreset(String)
+}
+
+/// よくある場面向けの組み込みの設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemplatePreset {
+    /// 朝会。全員がリーダー候補の3〜5人のチーム
+    Standup,
+    /// ハッカソン。リーダー候補がまとめる4〜6人のチーム
+    Hackathon,
+    /// ランチ。全員がリーダー候補の4〜6人のテーブル
+    Lunch,
+    /// 振り返り。全員がリーダー候補の5〜7人のチーム
+    Retro
+}
+
+impl TemplatePreset {
+    /// テンプレートの設定値(TOML)
+    /// 出席者は含まないため、設定ファイルか`--names-file`で指定する
+    pub fn setting(&self) -> &'static str {
+        match self {
+            Self::Standup => "flat = true\ntarget_size = { min = 3, max = 5 }\n",
+            Self::Hackathon => "target_size = { min = 4, max = 6 }\n",
+            Self::Lunch => "flat = true\ntarget_size = { min = 4, max = 6 }\n",
+            Self::Retro => "flat = true\ntarget_size = { min = 5, max = 7 }\n"
+        }
+    }
+
+    /// テンプレートの出力形式
+    /// `--format`を指定した場合はそちらを使う
+    pub fn format(&self) -> OutputFormat {
+        match self {
+            Self::Standup | Self::Lunch | Self::Retro => OutputFormat::Org,
+            Self::Hackathon => OutputFormat::Svg
+        }
+    }
+
+    /// 設定ファイルの内容にテンプレートの設定値を合わせる
+    /// 同じ設定値が両方にあれば設定ファイルの値を使う
+    /// 設定ファイルに`num_of_teams`があればチーム数を指定したものとみなし、テンプレートの`target_size`は使わない
+    /// # Attributes
+    /// * `setting_str` - 設定ファイルの内容
+    ///
+    /// # Returns
+    /// Ok(テンプレートを合わせた設定ファイルの内容)
+    pub fn apply(&self, setting_str: &str) -> Result<String> {
+        let mut merged: Value = toml::from_str(self.setting())?;
+        let setting: Value = toml::from_str(setting_str)?;
+
+        if let (Some(merged), Value::Table(setting)) = (merged.as_table_mut(), setting) {
+            if setting.contains_key("num_of_teams") {
+                merged.remove("target_size");
+            }
+            merged.extend(setting);
+        }
+
+        Ok(toml::to_string(&merged)?)
+    }
+}
+
+impl FromStr for TemplatePreset {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standup" => Ok(Self::Standup),
+            "hackathon" => Ok(Self::Hackathon),
+            "lunch" => Ok(Self::Lunch),
+            "retro" => Ok(Self::Retro),
+            _ => Err(TemplateError::UnknownPreset(s.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::TeamsCreationSetting;
+
+    /// TemplatePreset#applyのテスト
+    /// テンプレートの設定値に出席者を加え、設定ファイルの値を優先する
+    #[test]
+    fn apply_template_preset() {
+        let merged = TemplatePreset::Standup.apply("flat = false\n\n[[attendees]]\nleader = true\nperson = { name = \"A\" }\n").unwrap();
+        let setting: TeamsCreationSetting = toml::from_str(&merged).unwrap();
+
+        assert!(!setting.is_flat());
+        assert_eq!(setting.target_size().map(|t| (t.min, t.max)), Some((3, 5)));
+        assert_eq!(setting.all_people().len(), 1);
+    }
+
+    /// TemplatePreset#applyのテスト
+    /// 設定ファイルにnum_of_teamsがあればテンプレートのtarget_sizeを使わない
+    #[test]
+    fn apply_template_preset_with_num_of_teams() {
+        let merged = TemplatePreset::Hackathon.apply("num_of_teams = 3\n").unwrap();
+        let setting: TeamsCreationSetting = toml::from_str(&merged).unwrap();
+
+        assert_eq!(setting.target_size(), None);
+        assert!(merged.contains("num_of_teams = 3"));
+    }
+
+    /// 全てのテンプレートの設定値が読み込めること
+    #[test]
+    fn template_presets_are_valid() {
+        for name in ["standup", "hackathon", "lunch", "retro"] {
+            let preset: TemplatePreset = name.parse().unwrap();
+
+            assert!(toml::from_str::<TeamsCreationSetting>(&preset.apply("").unwrap()).is_ok());
+        }
+        assert_eq!("party".parse::<TemplatePreset>(), Err(TemplateError::UnknownPreset("party".into())));
+    }
+}
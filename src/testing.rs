@@ -0,0 +1,125 @@
+//! 埋め込み先のテストで使うヘルパー
+//! `testing`featureを有効にすると使える
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use crate::domain::{Teams, TeamsCreationSetting};
+use crate::strategy::{SeededShuffle, ShuffleStrategies};
+
+/// スナップショットを書き換えるときに設定する環境変数
+pub const UPDATE_ENV: &str = "GUCCICCI_UPDATE_SNAPSHOTS";
+
+/// 再現可能な方法でチームを作成し、正規化したTOMLを返す
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `seed` - シード値。Noneであればシャッフルしない(`NoShuffle`)
+///
+/// # Returns
+/// Ok(正規化したTOML)
+pub fn snapshot_output(setting: TeamsCreationSetting, seed: Option<u64>) -> Result<String> {
+    let teams = match seed {
+        Some(seed) => Teams::create(setting, &SeededShuffle::new(seed))?,
+        None => Teams::create(setting, &ShuffleStrategies::NoShuffle)?
+    };
+
+    Ok(canonicalize(&toml::to_string_pretty(&teams)?))
+}
+
+/// 作成したチームがスナップショットファイルと一致することを確かめる
+/// スナップショットファイルがない場合、または環境変数`GUCCICCI_UPDATE_SNAPSHOTS`が設定されている場合は
+/// 現在の出力でスナップショットファイルを書き込む
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `seed` - シード値。Noneであればシャッフルしない(`NoShuffle`)
+/// * `path` - スナップショットファイルのパス
+///
+/// # Panics
+/// 出力がスナップショットと一致しない場合
+pub fn assert_snapshot(setting: TeamsCreationSetting, seed: Option<u64>, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual = snapshot_output(setting, seed).expect("failed to create teams for snapshot");
+
+    if env::var_os(UPDATE_ENV).is_some() || !path.exists() {
+        fs::write(path, &actual).unwrap_or_else(|e| panic!("failed to write snapshot {}: {}", path.display(), e));
+        return;
+    }
+
+    let expected = canonicalize(&fs::read_to_string(path).unwrap_or_else(|e| panic!("failed to read snapshot {}: {}", path.display(), e)));
+    if expected != actual {
+        panic!(
+            "snapshot {} does not match (set {}=1 to update)\n--- expected\n{}\n--- actual\n{}",
+            path.display(), UPDATE_ENV, expected, actual
+        );
+    }
+}
+
+/// 改行コードと行末の空白の違いを無視できるように正規化する
+fn canonicalize(s: &str) -> String {
+    let mut res: String = s.lines().map(|l| format!("{}\n", l.trim_end())).collect();
+    while res.ends_with("\n\n") {
+        res.pop();
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTING: &str = r#"
+num_of_teams = 2
+
+[[attendees]]
+leader = true
+person = { name = "A" }
+
+[[attendees]]
+leader = true
+person = { name = "B" }
+
+[[attendees]]
+person = { name = "C" }
+"#;
+
+    /// snapshot_outputのテスト
+    /// シード値なしではシャッフルせず、毎回同じ出力になる
+    #[test]
+    fn snapshot_output_without_seed() {
+        let output = snapshot_output(toml::from_str(SETTING).unwrap(), None).unwrap();
+
+        assert_eq!(output, snapshot_output(toml::from_str(SETTING).unwrap(), None).unwrap());
+        assert!(output.contains("name = 'B'"));
+    }
+
+    /// assert_snapshotのテスト
+    /// 初回はスナップショットを書き込み、以降は改行コードの違いを無視して比較する
+    #[test]
+    fn assert_snapshot_writes_and_compares() {
+        let path = env::temp_dir().join(format!("guccicci-snapshot-{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        assert_snapshot(toml::from_str(SETTING).unwrap(), Some(1), &path);
+        let written = fs::read_to_string(&path).unwrap();
+        fs::write(&path, written.replace('\n', "\r\n")).unwrap();
+        assert_snapshot(toml::from_str(SETTING).unwrap(), Some(1), &path);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// assert_snapshotのテスト
+    /// スナップショットと一致しなければpanicする
+    #[test]
+    #[should_panic(expected = "does not match")]
+    fn assert_snapshot_mismatch() {
+        let path = env::temp_dir().join(format!("guccicci-snapshot-mismatch-{}.toml", std::process::id()));
+        fs::write(&path, "[[team]]\n").unwrap();
+
+        let result = std::panic::catch_unwind(|| assert_snapshot(toml::from_str(SETTING).unwrap(), None, &path));
+        fs::remove_file(&path).unwrap();
+        if let Err(e) = result {
+            std::panic::resume_unwind(e);
+        }
+    }
+}
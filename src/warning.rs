@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::domain::{Teams, TeamsCreationSetting};
+
+/// 実行を止めるほどではない問題
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// 同じ名前の出席者が複数いる
+    DuplicateName(String),
+    /// 大文字小文字・空白・カタカナとひらがなの違いを除くと同じになる名前がある
+    NearDuplicateNames(String, String),
+    /// タグを持つ人の数がチームによって2人以上違う
+    UnbalancedTag(String, Vec<usize>)
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "attendee {} appears more than once", name),
+            Self::NearDuplicateNames(a, b) => write!(f, "attendees {} and {} have nearly the same name", a, b),
+            Self::UnbalancedTag(tag, counts) => write!(
+                f,
+                "tag {} is unbalanced across teams ({})",
+                tag,
+                counts.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(", ")
+            )
+        }
+    }
+}
+
+/// 設定と作成したチームから警告を集める
+/// # Attributes
+/// * `setting` - チーム作成設定
+/// * `teams` - 作成したチーム
+///
+/// # Returns
+/// 警告のリスト(なければ空)
+pub fn check(setting: &TeamsCreationSetting, teams: &Teams) -> Vec<Warning> {
+    let mut res = Vec::new();
+
+    let mut seen: BTreeMap<String, &str> = BTreeMap::new();
+    for person in setting.all_people() {
+        match seen.get(&normalize(&person.name)) {
+            Some(other) if *other == &*person.name => res.push(Warning::DuplicateName(person.name.to_string())),
+            Some(other) => res.push(Warning::NearDuplicateNames(other.to_string(), person.name.to_string())),
+            None => {
+                seen.insert(normalize(&person.name), &person.name);
+            }
+        }
+    }
+
+    let index = setting.attendee_index();
+    let mut tags: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, team) in teams.borrow_vec().iter().enumerate() {
+        for person in std::iter::once(&team.leader).chain(team.member.iter()) {
+            for tag in index.tags_of(&person.name) {
+                tags.entry(tag).or_insert_with(|| vec![0; teams.borrow_vec().len()])[i] += 1;
+            }
+        }
+    }
+    for (tag, counts) in tags {
+        let max = counts.iter().max().copied().unwrap_or(0);
+        let min = counts.iter().min().copied().unwrap_or(0);
+        if max - min > 1 {
+            res.push(Warning::UnbalancedTag(tag.to_string(), counts));
+        }
+    }
+
+    res
+}
+
+/// 名前の表記揺れを除いた比較用の文字列
+/// 空白を除き、小文字にし、カタカナをひらがなにする
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Person, Team};
+
+    const SETTING: &str = r#"
+num_of_teams = 2
+
+[[attendees]]
+leader = true
+tags = ["senior"]
+person = { name = "Taro Yamada" }
+
+[[attendees]]
+leader = true
+person = { name = "taroyamada" }
+
+[[attendees]]
+tags = ["senior"]
+person = { name = "ハナコ" }
+
+[[attendees]]
+tags = ["senior"]
+person = { name = "はなこ" }
+
+[[attendees]]
+person = { name = "Jiro" }
+
+[[attendees]]
+person = { name = "Jiro" }
+"#;
+
+    /// checkのテスト
+    /// 同じ名前・ほぼ同じ名前と、チームによって偏ったタグを警告する
+    #[test]
+    fn check_warnings() {
+        let setting: TeamsCreationSetting = toml::from_str(SETTING).unwrap();
+        let mut team1 = Team::new(Person::new("Taro Yamada"));
+        team1.assign(Person::new("ハナコ"));
+        team1.assign(Person::new("はなこ"));
+        let mut team2 = Team::new(Person::new("taroyamada"));
+        team2.assign(Person::new("Jiro"));
+        team2.assign(Person::new("Jiro"));
+
+        let warnings = check(&setting, &Teams::from_vec(vec![team1, team2]));
+
+        assert_eq!(warnings, vec![
+            Warning::NearDuplicateNames("Taro Yamada".into(), "taroyamada".into()),
+            Warning::NearDuplicateNames("ハナコ".into(), "はなこ".into()),
+            Warning::DuplicateName("Jiro".into()),
+            Warning::UnbalancedTag("senior".into(), vec![3, 0]),
+        ]);
+        assert_eq!(warnings[3].to_string(), "tag senior is unbalanced across teams (3, 0)");
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// 変更を確かめる間隔
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// ファイルの更新時刻と大きさ。ファイルがなければNone
+type Stamp = Option<(SystemTime, u64)>;
+
+/// 設定ファイルの変更を定期的に確かめる
+/// 更新時刻か大きさが変わったファイルがあれば変更とみなす
+pub struct SettingWatcher {
+    /// 監視するファイルのパス
+    paths: Vec<PathBuf>,
+    /// 前回確かめたときの各ファイルの状態
+    stamps: Vec<Stamp>
+}
+
+impl SettingWatcher {
+    /// 監視するファイルを指定して作成する
+    /// 作成した時点の状態を基準にする
+    /// # Attributes
+    /// * `paths` - 監視するファイルのパス
+    pub fn new(paths: Vec<PathBuf>) -> SettingWatcher {
+        let stamps = paths.iter().map(|p| stamp(p)).collect();
+
+        SettingWatcher { paths, stamps }
+    }
+
+    /// 前回確かめたときから変わったファイルがあるかを返す
+    /// 変わっていれば今の状態を次の基準にする
+    pub fn changed(&mut self) -> bool {
+        let stamps: Vec<Stamp> = self.paths.iter().map(|p| stamp(p)).collect();
+        if stamps == self.stamps {
+            return false;
+        }
+
+        self.stamps = stamps;
+        true
+    }
+
+    /// いずれかのファイルが変わるまで待つ
+    /// エディタが何回かに分けて書き込む場合に備え、変わった後も書き込みが落ち着くまで待つ
+    /// # Attributes
+    /// * `interval` - 変更を確かめる間隔
+    pub fn wait(&mut self, interval: Duration) {
+        while !self.changed() {
+            thread::sleep(interval);
+        }
+        thread::sleep(interval);
+        self.changed();
+    }
+}
+
+/// ファイルの今の状態
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SettingWatcher#changedのテスト
+    /// ファイルの内容が変わったときと、ファイルが消えたときだけtrue
+    #[test]
+    fn setting_watcher_changed() {
+        let path = std::env::temp_dir().join(format!("guccicci-watch-{}.toml", std::process::id()));
+        fs::write(&path, "num_of_teams = 1\n").unwrap();
+        let mut watcher = SettingWatcher::new(vec![path.clone()]);
+
+        assert!(!watcher.changed());
+        fs::write(&path, "num_of_teams = 12\n").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+    }
+}
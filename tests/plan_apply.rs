@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const SETTING: &str = r#"
+num_of_teams = 2
+
+[[attendees]]
+leader = true
+person = { name = "A" }
+
+[[attendees]]
+leader = true
+person = { name = "B" }
+
+[[attendees]]
+person = { name = "C" }
+
+[[attendees]]
+person = { name = "D" }
+"#;
+
+/// guccicciを実行し、標準出力を返す
+fn guccicci(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_guccicci")).current_dir(dir).args(args).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// planが表示したapplyのコマンドのテスト
+/// 表示されたコマンドをそのまま実行すると、planで示したとおりの結果になる
+#[test]
+fn apply_hint_reproduces_plan() {
+    let dir = std::env::temp_dir().join(format!("guccicci-plan-apply-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("base.toml"), "flat = false\n").unwrap();
+    fs::write(dir.join("setting.toml"), SETTING).unwrap();
+    fs::write(dir.join("names.txt"), "E *\nF\n").unwrap();
+    let plan_args = ["plan", "--result", "result.toml", "--names-file", "names.txt", "--no-lead", "A", "base.toml", "setting.toml"];
+
+    let plan = guccicci(&dir, &plan_args);
+    let hint = plan.lines().find_map(|l| l.strip_prefix("To apply: guccicci ")).unwrap();
+    let apply_args: Vec<&str> = hint.split(' ').collect();
+    guccicci(&dir, &apply_args);
+
+    // planと同じシード値でもう一度planすると、applyした結果との違いはない
+    let seed = apply_args[apply_args.iter().position(|a| *a == "--seed").unwrap() + 1];
+    let replan_args: Vec<&str> = ["plan", "--seed", seed].iter().copied().chain(plan_args[1..].iter().copied()).collect();
+    assert!(plan.lines().count() > 2);
+    assert!(apply_args.contains(&"--names-file") && apply_args.contains(&"--no-lead") && apply_args.contains(&"base.toml"));
+    assert!(guccicci(&dir, &replan_args).starts_with("No changes."));
+
+    fs::remove_dir_all(&dir).unwrap();
+}